@@ -0,0 +1,285 @@
+//! Batch mode: decomposes the polygons of many files, or of one file
+//! with many features, in parallel.  A polygon that fails is recorded
+//! in the summary instead of aborting the run.
+use crate::input::{self, InputFormat, Ring};
+use crate::output::{self, OutputFormat};
+use crate::{decompose, Algorithm};
+use rayon::prelude::*;
+use serde_json::json;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+pub struct BatchSettings {
+    pub input_format: Option<InputFormat>,
+    pub format: OutputFormat,
+    pub algorithm: Algorithm,
+    pub thickness: f32,
+    /// Number of worker threads; rayon's default if None
+    pub threads: Option<usize>,
+}
+
+/// A polygon that could not be read, decomposed or written.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Failure {
+    pub source: String,
+    /// None if the whole source file could not be read
+    pub polygon: Option<usize>,
+    pub message: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Summary {
+    pub inputs: usize,
+    pub polygons: usize,
+    pub succeeded: usize,
+    pub vertices: usize,
+    pub read_time: Duration,
+    pub decomposition_time: Duration,
+    pub slowest_decomposition: Duration,
+    pub total_time: Duration,
+    pub failures: Vec<Failure>,
+}
+
+impl Summary {
+    fn to_json(&self) -> serde_json::Value {
+        let failures: Vec<serde_json::Value> = self
+            .failures
+            .iter()
+            .map(|f| json!({"source": f.source, "polygon": f.polygon, "error": f.message}))
+            .collect();
+        json!({
+            "inputs": self.inputs,
+            "polygons": self.polygons,
+            "succeeded": self.succeeded,
+            "failed": self.failures.len(),
+            "vertices": self.vertices,
+            "timings": {
+                "total_seconds": self.total_time.as_secs_f64(),
+                "read_seconds": self.read_time.as_secs_f64(),
+                "decomposition_seconds": self.decomposition_time.as_secs_f64(),
+                "slowest_decomposition_seconds": self.slowest_decomposition.as_secs_f64(),
+            },
+            "failures": failures,
+        })
+    }
+}
+
+/// Files to process: the input itself, or the files of the input directory
+/// with a supported extension, in name order.
+fn input_files(input: &Path) -> io::Result<Vec<PathBuf>> {
+    if !input.is_dir() {
+        return Ok(vec![input.to_path_buf()]);
+    }
+    let mut files = vec![];
+    for entry in fs::read_dir(input)? {
+        let path = entry?.path();
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase());
+        if path.is_file()
+            && matches!(
+                extension.as_deref(),
+                Some("json") | Some("geojson") | Some("wkt") | Some("csv")
+            )
+        {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn read_rings(path: &Path, input_format: Option<InputFormat>) -> Result<Vec<Ring>, String> {
+    let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let format = input_format.unwrap_or_else(|| InputFormat::detect(Some(path), &text));
+    input::parse(&text, format).map_err(|e| e.to_string())
+}
+
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        format!("panicked: {}", message)
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        format!("panicked: {}", message)
+    } else {
+        "panicked".to_string()
+    }
+}
+
+fn write_polygon(
+    poly: &polygon::poly::Poly,
+    path: &Path,
+    settings: &BatchSettings,
+) -> io::Result<()> {
+    if settings.format == OutputFormat::Gltf {
+        return output::write_gltf_files(poly, path);
+    }
+    let mut writer = BufWriter::new(File::create(path)?);
+    output::write(
+        std::slice::from_ref(poly),
+        settings.format,
+        settings.thickness,
+        &mut writer,
+    )?;
+    writer.flush()
+}
+
+/// Processes every polygon of the input file or directory, writing one
+/// output file per polygon and summary.json into the output directory.
+pub fn run_batch(input: &Path, output_dir: &Path, settings: &BatchSettings) -> io::Result<Summary> {
+    let start = Instant::now();
+    fs::create_dir_all(output_dir)?;
+    let files = input_files(input)?;
+
+    let mut builder = rayon::ThreadPoolBuilder::new();
+    if let Some(threads) = settings.threads {
+        builder = builder.num_threads(threads);
+    }
+    let pool = builder
+        .build()
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+
+    let mut summary = pool.install(|| process(&files, output_dir, settings));
+    summary.inputs = files.len();
+
+    summary.total_time = start.elapsed();
+    let mut writer = BufWriter::new(File::create(output_dir.join("summary.json"))?);
+    serde_json::to_writer_pretty(&mut writer, &summary.to_json())?;
+    writer.flush()?;
+    Ok(summary)
+}
+
+struct Outcome {
+    vertices: usize,
+    time: Duration,
+    failure: Option<Failure>,
+}
+
+fn process(files: &[PathBuf], output_dir: &Path, settings: &BatchSettings) -> Summary {
+    let mut summary = Summary::default();
+
+    let read_start = Instant::now();
+    let parsed: Vec<(String, Result<Vec<Ring>, String>)> = files
+        .par_iter()
+        .map(|path| {
+            let name = path.display().to_string();
+            (name, read_rings(path, settings.input_format))
+        })
+        .collect();
+    summary.read_time = read_start.elapsed();
+
+    let mut jobs: Vec<(usize, usize, Ring)> = vec![];
+    for (file_idx, (name, rings)) in parsed.iter().enumerate() {
+        match rings {
+            Ok(rings) => {
+                for (idx, ring) in rings.iter().enumerate() {
+                    jobs.push((file_idx, idx, ring.clone()));
+                }
+            }
+            Err(message) => summary.failures.push(Failure {
+                source: name.clone(),
+                polygon: None,
+                message: message.clone(),
+            }),
+        }
+    }
+    summary.polygons = jobs.len();
+
+    let outcomes: Vec<Outcome> = jobs
+        .into_par_iter()
+        .map(|(file_idx, idx, ring)| {
+            let source = &files[file_idx];
+            // the whole file name, so a.json and a.wkt do not overwrite
+            // each other's results
+            let name = source
+                .file_name()
+                .and_then(|s| s.to_str())
+                .unwrap_or("polygon");
+            let path = output_dir.join(format!("{}_{}.{}", name, idx, settings.format.extension()));
+            let vertices = ring.len();
+
+            let start = Instant::now();
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                decompose(ring, settings.algorithm).map_err(|e| e.to_string())
+            }))
+            .unwrap_or_else(|payload| Err(panic_message(payload)));
+            let time = start.elapsed();
+
+            let result = result.and_then(|poly| {
+                write_polygon(&poly, &path, settings)
+                    .map_err(|e| format!("cannot write {}: {}", path.display(), e))
+            });
+            Outcome {
+                vertices,
+                time,
+                failure: result.err().map(|message| Failure {
+                    source: source.display().to_string(),
+                    polygon: Some(idx),
+                    message,
+                }),
+            }
+        })
+        .collect();
+
+    for outcome in outcomes {
+        summary.vertices += outcome.vertices;
+        summary.decomposition_time += outcome.time;
+        summary.slowest_decomposition = summary.slowest_decomposition.max(outcome.time);
+        match outcome.failure {
+            Some(failure) => summary.failures.push(failure),
+            None => summary.succeeded += 1,
+        }
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_isolates_failures() {
+        let dir = std::env::temp_dir().join(format!("polydecomp_batch_{}", std::process::id()));
+        let input_dir = dir.join("input");
+        let output_dir = dir.join("output");
+        fs::create_dir_all(&input_dir).unwrap();
+
+        fs::write(
+            input_dir.join("footprints.wkt"),
+            "POLYGON ((10 10, 10 5, 20 0, 0 0, 10 10))\n\
+             POLYGON ((0 0, 4 4, 4 0, 0 6, 0 0))\n\
+             POLYGON ((0 0, 4 0, 4 4, 0 4, 0 0))\n",
+        )
+        .unwrap();
+        fs::write(input_dir.join("broken.json"), "[[0, 0], [1,").unwrap();
+        fs::write(input_dir.join("footprints.csv"), "0,0\n4,0\n4,4\n").unwrap();
+        fs::write(input_dir.join("notes.txt"), "not an input").unwrap();
+
+        let settings = BatchSettings {
+            input_format: None,
+            format: OutputFormat::Json,
+            algorithm: Algorithm::HertelMehlhorn,
+            thickness: 1.0,
+            threads: Some(2),
+        };
+        let summary = run_batch(&input_dir, &output_dir, &settings).unwrap();
+
+        assert_eq!(summary.inputs, 3);
+        assert_eq!(summary.polygons, 4);
+        assert_eq!(summary.succeeded, 3);
+        assert_eq!(summary.failures.len(), 2);
+        assert!(summary
+            .failures
+            .iter()
+            .any(|f| f.polygon == Some(1) && f.source.ends_with("footprints.wkt")));
+        assert!(output_dir.join("footprints.wkt_0.json").exists());
+        assert!(!output_dir.join("footprints.wkt_1.json").exists());
+        assert!(output_dir.join("footprints.csv_0.json").exists());
+        assert!(output_dir.join("summary.json").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,448 @@
+use polygon::error::{error, ParseError};
+use serde_json::Value;
+use std::path::Path;
+use std::str::FromStr;
+
+/// A polygon as read from the input: its vertices, without
+/// repeating the first vertex at the end.
+pub type Ring = Vec<[f32; 2]>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputFormat {
+    Json,
+    GeoJson,
+    Wkt,
+    Csv,
+}
+
+impl FromStr for InputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(InputFormat::Json),
+            "geojson" => Ok(InputFormat::GeoJson),
+            "wkt" => Ok(InputFormat::Wkt),
+            "csv" => Ok(InputFormat::Csv),
+            _ => Err(format!(
+                "unknown input format {}, expected json, geojson, wkt or csv",
+                s
+            )),
+        }
+    }
+}
+
+impl InputFormat {
+    /// Guesses the format from the file extension, or else from the content.
+    pub fn detect(path: Option<&Path>, text: &str) -> InputFormat {
+        let extension = path
+            .and_then(|p| p.extension())
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase());
+        match extension.as_deref() {
+            Some("geojson") => return InputFormat::GeoJson,
+            Some("wkt") => return InputFormat::Wkt,
+            Some("csv") => return InputFormat::Csv,
+            _ => {}
+        }
+
+        let start = text.trim_start();
+        if start.starts_with('{') && start.contains("\"type\"") {
+            InputFormat::GeoJson
+        } else if start.starts_with('{') || start.starts_with('[') {
+            InputFormat::Json
+        } else if start.starts_with(|c: char| c.is_ascii_alphabetic())
+            && start.to_uppercase().contains("POLYGON")
+        {
+            InputFormat::Wkt
+        } else {
+            InputFormat::Csv
+        }
+    }
+}
+
+/// Removes a closing vertex equal to the first one.
+fn open_ring(mut ring: Ring) -> Ring {
+    if ring.len() > 1 && ring.first() == ring.last() {
+        ring.pop();
+    }
+    ring
+}
+
+/// Reads the polygons of the input.
+pub fn parse(text: &str, format: InputFormat) -> Result<Vec<Ring>, ParseError> {
+    let rings = match format {
+        InputFormat::Json => parse_json(text)?,
+        InputFormat::GeoJson => parse_geojson(text)?,
+        InputFormat::Wkt => parse_wkt(text)?,
+        InputFormat::Csv => parse_csv(text)?,
+    };
+    if rings.is_empty() {
+        return Err(error(0, "the input contains no polygon"));
+    }
+    Ok(rings.into_iter().map(open_ring).collect())
+}
+
+fn json_value(text: &str) -> Result<Value, ParseError> {
+    serde_json::from_str(text).map_err(|e| error(e.line(), &e.to_string()))
+}
+
+fn json_point(value: &Value) -> Result<[f32; 2], ParseError> {
+    match value.as_array().map(|a| a.as_slice()) {
+        Some([x, y, ..]) => match (x.as_f64(), y.as_f64()) {
+            (Some(x), Some(y)) => Ok([x as f32, y as f32]),
+            _ => Err(error(0, &format!("invalid coordinates {}", value))),
+        },
+        _ => Err(error(0, &format!("expected [x, y], got {}", value))),
+    }
+}
+
+fn json_ring(value: &Value) -> Result<Ring, ParseError> {
+    value
+        .as_array()
+        .ok_or_else(|| error(0, &format!("expected a list of points, got {}", value)))?
+        .iter()
+        .map(json_point)
+        .collect()
+}
+
+/// Plain JSON: a list of [x, y] points, a list of such lists,
+/// {"vertices": [...]} or {"polygons": [...]}.
+fn parse_json(text: &str) -> Result<Vec<Ring>, ParseError> {
+    fn polygon(value: &Value) -> Result<Ring, ParseError> {
+        match value.get("vertices") {
+            Some(vertices) => json_ring(vertices),
+            None => json_ring(value),
+        }
+    }
+
+    let value = json_value(text)?;
+    if let Some(polygons) = value.get("polygons") {
+        return polygons
+            .as_array()
+            .ok_or_else(|| error(0, "\"polygons\" must be a list"))?
+            .iter()
+            .map(polygon)
+            .collect();
+    }
+    if value.get("vertices").is_some() {
+        return Ok(vec![polygon(&value)?]);
+    }
+
+    // a single polygon starts with a point, i.e. a list of numbers
+    let items = value
+        .as_array()
+        .ok_or_else(|| error(0, "expected a list of polygons or points"))?;
+    let first_item = items
+        .first()
+        .and_then(|first| first.as_array())
+        .and_then(|first| first.first());
+    let is_single = !matches!(first_item, Some(x) if !x.is_number());
+    if is_single {
+        Ok(vec![json_ring(&value)?])
+    } else {
+        items.iter().map(polygon).collect()
+    }
+}
+
+/// GeoJSON: exterior rings of the Polygon and MultiPolygon geometries
+/// of Features, FeatureCollections and GeometryCollections.
+fn parse_geojson(text: &str) -> Result<Vec<Ring>, ParseError> {
+    fn polygon(coordinates: &Value, rings: &mut Vec<Ring>) -> Result<(), ParseError> {
+        let linear_rings = coordinates
+            .as_array()
+            .ok_or_else(|| error(0, "Polygon coordinates must be a list of rings"))?;
+        if linear_rings.len() > 1 {
+            return Err(error(0, "polygons with holes are not supported"));
+        }
+        if let Some(exterior) = linear_rings.first() {
+            rings.push(json_ring(exterior)?);
+        }
+        Ok(())
+    }
+
+    fn collect(value: &Value, rings: &mut Vec<Ring>) -> Result<(), ParseError> {
+        let kind = value.get("type").and_then(|t| t.as_str()).unwrap_or("");
+        let list = |key: &str| -> Result<&Vec<Value>, ParseError> {
+            value
+                .get(key)
+                .and_then(|v| v.as_array())
+                .ok_or_else(|| error(0, &format!("{} without \"{}\" list", kind, key)))
+        };
+        match kind {
+            "FeatureCollection" => {
+                for feature in list("features")? {
+                    collect(feature, rings)?;
+                }
+            }
+            "Feature" => {
+                if let Some(geometry) = value.get("geometry").filter(|g| !g.is_null()) {
+                    collect(geometry, rings)?;
+                }
+            }
+            "GeometryCollection" => {
+                for geometry in list("geometries")? {
+                    collect(geometry, rings)?;
+                }
+            }
+            "Polygon" => polygon(value.get("coordinates").unwrap_or(&Value::Null), rings)?,
+            "MultiPolygon" => {
+                for coordinates in list("coordinates")? {
+                    polygon(coordinates, rings)?;
+                }
+            }
+            // points and lines carry no polygon
+            "Point" | "MultiPoint" | "LineString" | "MultiLineString" => {}
+            _ => return Err(error(0, &format!("unknown GeoJSON type \"{}\"", kind))),
+        }
+        Ok(())
+    }
+
+    let mut rings = vec![];
+    collect(&json_value(text)?, &mut rings)?;
+    Ok(rings)
+}
+
+/// Tokens of a WKT text with the line they are on.
+fn wkt_tokens(text: &str) -> Vec<(usize, String)> {
+    let mut tokens = vec![];
+    for (idx, line) in text.lines().enumerate() {
+        let mut current = String::new();
+        for c in line.chars() {
+            if c == '(' || c == ')' || c == ',' || c.is_whitespace() {
+                if !current.is_empty() {
+                    tokens.push((idx + 1, std::mem::take(&mut current)));
+                }
+                if !c.is_whitespace() {
+                    tokens.push((idx + 1, c.to_string()));
+                }
+            } else {
+                current.push(c);
+            }
+        }
+        if !current.is_empty() {
+            tokens.push((idx + 1, current));
+        }
+    }
+    tokens
+}
+
+struct WktParser {
+    tokens: Vec<(usize, String)>,
+    position: usize,
+}
+
+impl WktParser {
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .or_else(|| self.tokens.last())
+            .map_or(0, |(line, _)| *line)
+    }
+
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.position).map(|(_, t)| t.as_str())
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), ParseError> {
+        if self.peek() == Some(token) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(error(
+                self.line(),
+                &format!(
+                    "expected \"{}\", got \"{}\"",
+                    token,
+                    self.peek().unwrap_or("end of input")
+                ),
+            ))
+        }
+    }
+
+    fn number(&mut self) -> Result<f32, ParseError> {
+        let line = self.line();
+        let token = self.peek().unwrap_or("end of input").to_string();
+        let value = token
+            .parse()
+            .map_err(|_| error(line, &format!("expected a number, got \"{}\"", token)))?;
+        self.position += 1;
+        Ok(value)
+    }
+
+    /// Parses "(" item ("," item)* ")".
+    fn list<T>(
+        &mut self,
+        mut item: impl FnMut(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<Vec<T>, ParseError> {
+        self.expect("(")?;
+        let mut items = vec![item(self)?];
+        while self.peek() == Some(",") {
+            self.position += 1;
+            items.push(item(self)?);
+        }
+        self.expect(")")?;
+        Ok(items)
+    }
+
+    fn point(&mut self) -> Result<[f32; 2], ParseError> {
+        let point = [self.number()?, self.number()?];
+        // ignore z and m values
+        while matches!(self.peek(), Some(t) if t.parse::<f32>().is_ok()) {
+            self.position += 1;
+        }
+        Ok(point)
+    }
+
+    fn polygon(&mut self) -> Result<Ring, ParseError> {
+        let line = self.line();
+        let mut rings = self.list(|parser| parser.list(Self::point))?;
+        if rings.len() > 1 {
+            return Err(error(line, "polygons with holes are not supported"));
+        }
+        Ok(rings.remove(0))
+    }
+}
+
+/// WKT: POLYGON and MULTIPOLYGON geometries, e.g. one per line.
+fn parse_wkt(text: &str) -> Result<Vec<Ring>, ParseError> {
+    let mut parser = WktParser {
+        tokens: wkt_tokens(text),
+        position: 0,
+    };
+    let mut rings = vec![];
+    while let Some(keyword) = parser.peek().map(|k| k.to_uppercase()) {
+        let line = parser.line();
+        parser.position += 1;
+        // dimension markers such as "POLYGON Z"
+        if matches!(
+            parser.peek().map(|t| t.to_uppercase()).as_deref(),
+            Some("Z") | Some("M") | Some("ZM")
+        ) {
+            parser.position += 1;
+        }
+        if parser.peek().map(|t| t.to_uppercase()).as_deref() == Some("EMPTY") {
+            parser.position += 1;
+            continue;
+        }
+        match keyword.as_str() {
+            "POLYGON" => rings.push(parser.polygon()?),
+            "MULTIPOLYGON" => rings.extend(parser.list(WktParser::polygon)?),
+            _ => {
+                return Err(error(
+                    line,
+                    &format!("expected POLYGON or MULTIPOLYGON, got \"{}\"", keyword),
+                ))
+            }
+        }
+    }
+    Ok(rings)
+}
+
+/// CSV: "x,y" rows with polygons separated by empty lines,
+/// or "id,x,y" rows with consecutive rows of the same id forming a polygon.
+/// A header row is skipped.
+fn parse_csv(text: &str) -> Result<Vec<Ring>, ParseError> {
+    let mut rings: Vec<Ring> = vec![];
+    let mut current: Ring = vec![];
+    let mut current_id: Option<String> = None;
+
+    for (idx, line) in text.lines().enumerate() {
+        let fields: Vec<&str> = line.split(',').map(|f| f.trim()).collect();
+        if line.trim().is_empty() {
+            if !current.is_empty() {
+                rings.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+
+        let number = |field: &str| -> Result<f32, ParseError> {
+            field
+                .parse()
+                .map_err(|_| error(idx + 1, &format!("invalid number \"{}\"", field)))
+        };
+        let (id, x, y) = match fields.as_slice() {
+            [x, y] => (None, *x, *y),
+            [id, x, y] => (Some(id.to_string()), *x, *y),
+            _ => {
+                return Err(error(
+                    idx + 1,
+                    "expected two (x,y) or three (id,x,y) columns",
+                ))
+            }
+        };
+        if idx == 0 && x.parse::<f32>().is_err() {
+            continue;
+        }
+        if id.is_some() && id != current_id && !current.is_empty() {
+            rings.push(std::mem::take(&mut current));
+        }
+        current_id = id;
+        current.push([number(x)?, number(y)?]);
+    }
+    if !current.is_empty() {
+        rings.push(current);
+    }
+    Ok(rings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SQUARE: [[f32; 2]; 4] = [[0.0, 0.0], [4.0, 0.0], [4.0, 4.0], [0.0, 4.0]];
+
+    #[test]
+    fn test_json() {
+        let single = parse("[[0,0],[4,0],[4,4],[0,4],[0,0]]", InputFormat::Json).unwrap();
+        assert_eq!(single, vec![SQUARE.to_vec()]);
+        let many = parse(
+            r#"{"polygons": [{"vertices": [[0,0],[4,0],[4,4],[0,4]]}, [[0,0],[1,0],[0,1]]]}"#,
+            InputFormat::Json,
+        )
+        .unwrap();
+        assert_eq!(many.len(), 2);
+        assert!(parse("[[0,0],[1]]", InputFormat::Json).is_err());
+    }
+
+    #[test]
+    fn test_geojson() {
+        let text = r#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "properties": {}, "geometry":
+                {"type": "Polygon", "coordinates": [[[0,0],[4,0],[4,4],[0,4],[0,0]]]}},
+            {"type": "Feature", "properties": {}, "geometry":
+                {"type": "MultiPolygon", "coordinates": [[[[0,0],[1,0],[0,1],[0,0]]], [[[5,5],[6,5],[5,6],[5,5]]]]}}
+        ]}"#;
+        assert_eq!(InputFormat::detect(None, text), InputFormat::GeoJson);
+        let rings = parse(text, InputFormat::GeoJson).unwrap();
+        assert_eq!(rings.len(), 3);
+        assert_eq!(rings[0], SQUARE.to_vec());
+
+        let hole = r#"{"type": "Polygon", "coordinates": [[[0,0],[4,0],[4,4],[0,0]], [[1,1],[2,1],[1,2],[1,1]]]}"#;
+        assert!(parse(hole, InputFormat::GeoJson).is_err());
+    }
+
+    #[test]
+    fn test_wkt() {
+        let text = "POLYGON ((0 0, 4 0, 4 4, 0 4, 0 0))\n\
+                    MULTIPOLYGON (((0 0, 1 0, 0 1, 0 0)), ((5 5, 6 5, 5 6, 5 5)))\n";
+        assert_eq!(InputFormat::detect(None, text), InputFormat::Wkt);
+        let rings = parse(text, InputFormat::Wkt).unwrap();
+        assert_eq!(rings.len(), 3);
+        assert_eq!(rings[0], SQUARE.to_vec());
+
+        let error = parse("POLYGON ((0 0, 4 0,\n 4 x))", InputFormat::Wkt).unwrap_err();
+        assert_eq!(error.line, 2);
+    }
+
+    #[test]
+    fn test_csv() {
+        let text = "x,y\n0,0\n4,0\n4,4\n0,4\n\n0,0\n1,0\n0,1\n";
+        assert_eq!(parse(text, InputFormat::Csv).unwrap().len(), 2);
+        let with_ids = "a,0,0\na,4,0\na,4,4\na,0,4\nb,0,0\nb,1,0\nb,0,1\n";
+        let rings = parse(with_ids, InputFormat::Csv).unwrap();
+        assert_eq!(rings[0], SQUARE.to_vec());
+        assert_eq!(rings.len(), 2);
+        assert_eq!(parse("0,0\n1,x\n", InputFormat::Csv).unwrap_err().line, 2);
+    }
+}
@@ -0,0 +1,414 @@
+#![forbid(unsafe_code)]
+#![warn(clippy::all, rust_2018_idioms)]
+
+//! Headless convex decomposition of polygons read from a file or stdin.
+
+mod batch;
+mod input;
+mod output;
+
+use input::{InputFormat, Ring};
+use output::OutputFormat;
+use polygon::error::PolyError;
+use polygon::generate::{random_polygon, Method};
+use polygon::poly::Poly;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+const USAGE: &str = "\
+Convex decomposition of simple polygons
+
+USAGE:
+    polydecomp [OPTIONS] [INPUT]
+    polydecomp --batch [OPTIONS] <INPUT> -o <DIR>
+    polydecomp --generate <METHOD> [OPTIONS]
+
+ARGS:
+    <INPUT>    Input file; stdin if omitted or -.  In batch mode a file with
+               many polygons or a directory of json, geojson, wkt and csv files
+
+OPTIONS:
+    -i, --input-format <FORMAT>    json, geojson, wkt or csv
+                                   [default: from the file extension or the content]
+    -o, --output <FILE>            Output file; stdout if omitted.  In batch mode the
+                                   directory receiving one file per polygon and
+                                   summary.json
+    -f, --format <FORMAT>          json, geojson, wkt, svg, obj, ply, ply-binary, stl,
+                                   gltf, glb or dxf
+                                   [default: from the output extension, else json]
+    -a, --algorithm <ALGORITHM>    hm (Hertel-Mehlhorn convex decomposition),
+                                   min-weight (convex decomposition with the
+                                   shortest total length of the diagonals) or
+                                   triangulation [default: hm]
+        --thickness <THICKNESS>    Extrusion thickness of the stl output [default: 1]
+        --batch                    Process the polygons in parallel, reporting the ones
+                                   that fail in the summary instead of stopping
+    -j, --threads <N>              Number of threads of the batch mode
+                                   [default: one per core]
+    -g, --generate <METHOD>        Decompose a random polygon instead of reading one:
+                                   convex, star, two-opt, space-partition,
+                                   grid-walk, orthogonal, spiral or comb
+    -n, --vertices <N>             Number of vertices of the random polygon [default: 50]
+        --seed <SEED>              Seed of the random polygon [default: 0]
+    -h, --help                     Print this help
+
+EXIT CODES:
+    0    success
+    1    invalid input, e.g. a polygon that is not simple; in batch mode,
+         some polygons failed
+    2    invalid command line
+    3    input or output error
+";
+
+const EXIT_INVALID_INPUT: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_IO: i32 = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Algorithm {
+    HertelMehlhorn,
+    MinWeight,
+    Triangulation,
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "hm" | "hertel-mehlhorn" => Ok(Algorithm::HertelMehlhorn),
+            "min-weight" => Ok(Algorithm::MinWeight),
+            "triangulation" => Ok(Algorithm::Triangulation),
+            _ => Err(format!(
+                "unknown algorithm {}, expected hm, min-weight or triangulation",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Args {
+    input: Option<PathBuf>,
+    input_format: Option<InputFormat>,
+    output: Option<PathBuf>,
+    format: Option<OutputFormat>,
+    algorithm: Algorithm,
+    thickness: f32,
+    batch: bool,
+    threads: Option<usize>,
+    generate: Option<Method>,
+    vertices: usize,
+    seed: u64,
+}
+
+#[derive(Debug)]
+enum CliError {
+    Usage(String),
+    Input(String),
+    Io(String),
+}
+
+impl CliError {
+    fn exit_code(&self) -> i32 {
+        match self {
+            CliError::Usage(_) => EXIT_USAGE,
+            CliError::Input(_) => EXIT_INVALID_INPUT,
+            CliError::Io(_) => EXIT_IO,
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Usage(message) => {
+                write!(f, "{}\nRun polydecomp --help for usage", message)
+            }
+            CliError::Input(message) | CliError::Io(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+/// Parses the command line; None if the help was requested.
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Option<Args>, CliError> {
+    let mut parsed = Args {
+        input: None,
+        input_format: None,
+        output: None,
+        format: None,
+        algorithm: Algorithm::HertelMehlhorn,
+        thickness: 1.0,
+        batch: false,
+        threads: None,
+        generate: None,
+        vertices: 50,
+        seed: 0,
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| CliError::Usage(format!("{} needs a value", name)))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-i" | "--input-format" => {
+                parsed.input_format = Some(value(&arg)?.parse().map_err(CliError::Usage)?)
+            }
+            "-o" | "--output" => parsed.output = Some(PathBuf::from(value(&arg)?)),
+            "-f" | "--format" => {
+                parsed.format = Some(value(&arg)?.parse().map_err(CliError::Usage)?)
+            }
+            "-a" | "--algorithm" => {
+                parsed.algorithm = value(&arg)?.parse().map_err(CliError::Usage)?
+            }
+            "--thickness" => {
+                let thickness = value(&arg)?;
+                parsed.thickness = thickness
+                    .parse()
+                    .ok()
+                    .filter(|t: &f32| *t > 0.0)
+                    .ok_or_else(|| CliError::Usage(format!("invalid thickness {}", thickness)))?;
+            }
+            "--batch" => parsed.batch = true,
+            "-j" | "--threads" => {
+                let threads = value(&arg)?;
+                parsed.threads = Some(threads.parse().ok().filter(|t: &usize| *t > 0).ok_or_else(
+                    || CliError::Usage(format!("invalid number of threads {}", threads)),
+                )?);
+            }
+            "-g" | "--generate" => {
+                parsed.generate = Some(value(&arg)?.parse().map_err(CliError::Usage)?)
+            }
+            "-n" | "--vertices" => {
+                let vertices = value(&arg)?;
+                parsed.vertices = vertices
+                    .parse()
+                    .ok()
+                    .filter(|n: &usize| *n >= 3)
+                    .ok_or_else(|| {
+                        CliError::Usage(format!("invalid number of vertices {}", vertices))
+                    })?;
+            }
+            "--seed" => {
+                let seed = value(&arg)?;
+                parsed.seed = seed
+                    .parse()
+                    .map_err(|_| CliError::Usage(format!("invalid seed {}", seed)))?;
+            }
+            "-" => parsed.input = None,
+            _ if arg.starts_with('-') => {
+                return Err(CliError::Usage(format!("unknown option {}", arg)))
+            }
+            _ => {
+                if parsed.input.is_some() {
+                    return Err(CliError::Usage(format!("unexpected argument {}", arg)));
+                }
+                parsed.input = Some(PathBuf::from(arg));
+            }
+        }
+    }
+    if parsed.batch && (parsed.input.is_none() || parsed.output.is_none()) {
+        return Err(CliError::Usage(
+            "the batch mode needs an input file or directory and an output directory".to_string(),
+        ));
+    }
+    if parsed.generate.is_some() && (parsed.batch || parsed.input.is_some()) {
+        return Err(CliError::Usage(
+            "a random polygon cannot be combined with an input or the batch mode".to_string(),
+        ));
+    }
+    Ok(Some(parsed))
+}
+
+/// Runs the chosen algorithm on one polygon, after checking that it is simple.
+pub fn decompose(ring: Ring, algorithm: Algorithm) -> Result<Poly, PolyError> {
+    let mut poly = Poly {
+        vertices: ring,
+        ..Default::default()
+    };
+    poly.check_simple()?;
+    poly.triang();
+    match algorithm {
+        Algorithm::HertelMehlhorn => poly.decomposition(),
+        Algorithm::MinWeight => {
+            poly.min_weight_decomposition();
+        }
+        Algorithm::Triangulation => {}
+    }
+    Ok(poly)
+}
+
+fn run_batch(args: Args) -> Result<(), CliError> {
+    // checked by parse_args
+    let input = args.input.unwrap_or_default();
+    let output_dir = args.output.unwrap_or_default();
+    let settings = batch::BatchSettings {
+        input_format: args.input_format,
+        format: args.format.unwrap_or(OutputFormat::Json),
+        algorithm: args.algorithm,
+        thickness: args.thickness,
+        threads: args.threads,
+    };
+    let summary = batch::run_batch(&input, &output_dir, &settings)
+        .map_err(|e| CliError::Io(format!("batch failed: {}", e)))?;
+
+    eprintln!(
+        "polydecomp: {} polygons from {} inputs in {:.3}s: {} succeeded, {} failed",
+        summary.polygons,
+        summary.inputs,
+        summary.total_time.as_secs_f64(),
+        summary.succeeded,
+        summary.failures.len()
+    );
+    if summary.failures.is_empty() {
+        Ok(())
+    } else {
+        Err(CliError::Input(format!(
+            "see {} for the failures",
+            output_dir.join("summary.json").display()
+        )))
+    }
+}
+
+/// Reads the polygons of the input file or stdin, with the name of the input
+/// for the error messages.
+fn read_input(args: &Args) -> Result<(String, Vec<Ring>), CliError> {
+    let mut text = String::new();
+    match &args.input {
+        Some(path) => File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut text))
+            .map_err(|e| CliError::Io(format!("cannot read {}: {}", path.display(), e)))?,
+        None => io::stdin()
+            .read_to_string(&mut text)
+            .map_err(|e| CliError::Io(format!("cannot read stdin: {}", e)))?,
+    };
+
+    let input_name = args
+        .input
+        .as_ref()
+        .map_or("stdin".to_string(), |p| p.display().to_string());
+    let input_format = args
+        .input_format
+        .unwrap_or_else(|| InputFormat::detect(args.input.as_deref(), &text));
+    let rings = input::parse(&text, input_format)
+        .map_err(|e| CliError::Input(format!("{}: {}", input_name, e)))?;
+    Ok((input_name, rings))
+}
+
+fn run(args: Args) -> Result<(), CliError> {
+    if args.batch {
+        return run_batch(args);
+    }
+
+    let (input_name, rings) = match args.generate {
+        Some(method) => (
+            format!("{} polygon", method),
+            vec![random_polygon(method, args.vertices, args.seed)],
+        ),
+        None => read_input(&args)?,
+    };
+
+    let mut polys = Vec::with_capacity(rings.len());
+    for (idx, ring) in rings.into_iter().enumerate() {
+        let poly = decompose(ring, args.algorithm)
+            .map_err(|e| CliError::Input(format!("{}: polygon {}: {}", input_name, idx, e)))?;
+        polys.push(poly);
+    }
+
+    let format = args
+        .format
+        .or_else(|| args.output.as_deref().and_then(OutputFormat::from_path))
+        .unwrap_or(OutputFormat::Json);
+    let result = match (&args.output, format) {
+        (Some(path), OutputFormat::Gltf) if polys.len() == 1 => {
+            output::write_gltf_files(&polys[0], path)
+        }
+        (Some(path), _) => File::create(path).and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            output::write(&polys, format, args.thickness, &mut writer)?;
+            writer.flush()
+        }),
+        (None, _) => {
+            let stdout = io::stdout();
+            let mut writer = stdout.lock();
+            output::write(&polys, format, args.thickness, &mut writer)
+        }
+    };
+    result.map_err(|e| match e.kind() {
+        io::ErrorKind::InvalidInput => CliError::Usage(e.to_string()),
+        _ => CliError::Io(format!("cannot write output: {}", e)),
+    })
+}
+
+fn main() {
+    match parse_args(std::env::args().skip(1)) {
+        Ok(None) => print!("{}", USAGE),
+        Ok(Some(args)) => {
+            if let Err(error) = run(args) {
+                eprintln!("polydecomp: {}", error);
+                std::process::exit(error.exit_code());
+            }
+        }
+        Err(error) => {
+            eprintln!("polydecomp: {}", error);
+            std::process::exit(error.exit_code());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Result<Option<Args>, CliError> {
+        parse_args(line.split_whitespace().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let parsed = args("in.wkt -o out.svg -a triangulation").unwrap().unwrap();
+        assert_eq!(parsed.input, Some(PathBuf::from("in.wkt")));
+        assert_eq!(parsed.output, Some(PathBuf::from("out.svg")));
+        assert_eq!(parsed.algorithm, Algorithm::Triangulation);
+        assert_eq!(parsed.format, None);
+
+        assert!(args("--help").unwrap().is_none());
+        assert_eq!(args("-f xyz").unwrap_err().exit_code(), EXIT_USAGE);
+        assert_eq!(args("--thickness -1").unwrap_err().exit_code(), EXIT_USAGE);
+        assert_eq!(args("a b").unwrap_err().exit_code(), EXIT_USAGE);
+
+        let parsed = args("--batch footprints -o out -j 4").unwrap().unwrap();
+        assert!(parsed.batch);
+        assert_eq!(parsed.threads, Some(4));
+        assert_eq!(
+            args("--batch footprints").unwrap_err().exit_code(),
+            EXIT_USAGE
+        );
+
+        let parsed = args("-g comb -n 12 --seed 3").unwrap().unwrap();
+        assert_eq!(parsed.generate, Some(Method::Comb));
+        assert_eq!((parsed.vertices, parsed.seed), (12, 3));
+        assert_eq!(args("-g comb in.wkt").unwrap_err().exit_code(), EXIT_USAGE);
+        assert_eq!(args("-g blob").unwrap_err().exit_code(), EXIT_USAGE);
+    }
+
+    #[test]
+    fn test_decompose() {
+        let beak = vec![[10.0, 10.0], [10.0, 5.0], [20.0, 0.0], [0.0, 0.0]];
+        let poly = decompose(beak.clone(), Algorithm::HertelMehlhorn).unwrap();
+        assert_eq!(poly.convex_parts.len(), 2);
+        let poly = decompose(beak.clone(), Algorithm::MinWeight).unwrap();
+        assert_eq!(poly.convex_parts.len(), 2);
+        let poly = decompose(beak, Algorithm::Triangulation).unwrap();
+        assert_eq!(poly.triangles.len(), 2);
+        assert!(poly.convex_parts.is_empty());
+
+        let bowtie = vec![[0.0, 0.0], [4.0, 4.0], [4.0, 0.0], [0.0, 6.0]];
+        assert!(decompose(bowtie, Algorithm::HertelMehlhorn).is_err());
+    }
+}
@@ -0,0 +1,295 @@
+use polygon::dxf::{write_dxf_polys, DxfExportOptions};
+use polygon::mesh::PlyFormat;
+use polygon::poly::Poly;
+use polygon::svg::SvgOptions;
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    Json,
+    GeoJson,
+    Wkt,
+    Svg,
+    Obj,
+    Ply,
+    PlyBinary,
+    Stl,
+    Gltf,
+    Glb,
+    Dxf,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(OutputFormat::Json),
+            "geojson" => Ok(OutputFormat::GeoJson),
+            "wkt" => Ok(OutputFormat::Wkt),
+            "svg" => Ok(OutputFormat::Svg),
+            "obj" => Ok(OutputFormat::Obj),
+            "ply" => Ok(OutputFormat::Ply),
+            "ply-binary" => Ok(OutputFormat::PlyBinary),
+            "stl" => Ok(OutputFormat::Stl),
+            "gltf" => Ok(OutputFormat::Gltf),
+            "glb" => Ok(OutputFormat::Glb),
+            "dxf" => Ok(OutputFormat::Dxf),
+            _ => Err(format!(
+                "unknown output format {}, expected json, geojson, wkt, svg, obj, ply, \
+                 ply-binary, stl, gltf, glb or dxf",
+                s
+            )),
+        }
+    }
+}
+
+impl OutputFormat {
+    /// Format matching the extension of the output file, if any.
+    pub fn from_path(path: &Path) -> Option<OutputFormat> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        extension.parse().ok()
+    }
+
+    /// Formats describing a single polygon
+    pub fn is_single_polygon(self) -> bool {
+        !matches!(
+            self,
+            OutputFormat::Json | OutputFormat::GeoJson | OutputFormat::Wkt | OutputFormat::Dxf
+        )
+    }
+
+    /// Extension used for files written in this format
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Json => "json",
+            OutputFormat::GeoJson => "geojson",
+            OutputFormat::Wkt => "wkt",
+            OutputFormat::Svg => "svg",
+            OutputFormat::Obj => "obj",
+            OutputFormat::Ply | OutputFormat::PlyBinary => "ply",
+            OutputFormat::Stl => "stl",
+            OutputFormat::Gltf => "gltf",
+            OutputFormat::Glb => "glb",
+            OutputFormat::Dxf => "dxf",
+        }
+    }
+}
+
+/// Essential diagonals in the coordinates of the input.
+/// Poly stores them in screen coordinates, i.e. with y flipped.
+fn essential_diagonals(poly: &Poly) -> Vec<[[f32; 2]; 2]> {
+    poly.essential_diagonals
+        .iter()
+        .map(|d| [[d[0][0], -d[0][1]], [d[1][0], -d[1][1]]])
+        .collect()
+}
+
+fn triangles(poly: &Poly) -> Vec<Vec<[f32; 2]>> {
+    poly.triangles
+        .iter()
+        .map(|t| t.iter().map(|p| [p.x, p.y]).collect())
+        .collect()
+}
+
+fn convex_parts(poly: &Poly) -> Vec<Vec<[f32; 2]>> {
+    poly.convex_part_cycles()
+        .iter()
+        .map(|part| part.iter().map(|p| [p.x, p.y]).collect())
+        .collect()
+}
+
+/// Results of one polygon as a JSON object.
+pub fn json_polygon(poly: &Poly) -> Value {
+    json!({
+        "vertices": poly.vertices,
+        "triangles": triangles(poly),
+        "essential_diagonals": essential_diagonals(poly),
+        "convex_parts": convex_parts(poly),
+    })
+}
+
+fn closed(ring: &[[f32; 2]]) -> Vec<[f32; 2]> {
+    let mut ring = ring.to_vec();
+    if let Some(first) = ring.first().cloned() {
+        ring.push(first);
+    }
+    ring
+}
+
+fn geojson(polys: &[Poly]) -> Value {
+    let mut features = vec![];
+    let feature = |geometry: Value, kind: &str, polygon: usize, index: usize| {
+        json!({
+            "type": "Feature",
+            "geometry": geometry,
+            "properties": {"kind": kind, "polygon": polygon, "index": index},
+        })
+    };
+    for (polygon, poly) in polys.iter().enumerate() {
+        for (idx, triangle) in triangles(poly).iter().enumerate() {
+            let geometry = json!({"type": "Polygon", "coordinates": [closed(triangle)]});
+            features.push(feature(geometry, "triangle", polygon, idx));
+        }
+        for (idx, diagonal) in essential_diagonals(poly).iter().enumerate() {
+            let geometry = json!({"type": "LineString", "coordinates": diagonal});
+            features.push(feature(geometry, "essential_diagonal", polygon, idx));
+        }
+        for (idx, part) in convex_parts(poly).iter().enumerate() {
+            let geometry = json!({"type": "Polygon", "coordinates": [closed(part)]});
+            features.push(feature(geometry, "convex_part", polygon, idx));
+        }
+    }
+    json!({"type": "FeatureCollection", "features": features})
+}
+
+fn wkt_ring(ring: &[[f32; 2]]) -> String {
+    let points: Vec<String> = closed(ring)
+        .iter()
+        .map(|p| format!("{} {}", p[0], p[1]))
+        .collect();
+    format!("(({}))", points.join(", "))
+}
+
+/// One GEOMETRYCOLLECTION per polygon holding the triangles, the essential
+/// diagonals and the convex parts, in that order.
+fn wkt(poly: &Poly) -> String {
+    let multipolygon = |rings: Vec<Vec<[f32; 2]>>| {
+        if rings.is_empty() {
+            "MULTIPOLYGON EMPTY".to_string()
+        } else {
+            let rings: Vec<String> = rings.iter().map(|r| wkt_ring(r)).collect();
+            format!("MULTIPOLYGON ({})", rings.join(", "))
+        }
+    };
+    let diagonals = essential_diagonals(poly);
+    let lines = if diagonals.is_empty() {
+        "MULTILINESTRING EMPTY".to_string()
+    } else {
+        let lines: Vec<String> = diagonals
+            .iter()
+            .map(|d| format!("({} {}, {} {})", d[0][0], d[0][1], d[1][0], d[1][1]))
+            .collect();
+        format!("MULTILINESTRING ({})", lines.join(", "))
+    };
+    format!(
+        "GEOMETRYCOLLECTION ({}, {}, {})",
+        multipolygon(triangles(poly)),
+        lines,
+        multipolygon(convex_parts(poly))
+    )
+}
+
+/// Writes the results in the given format.  Formats describing a single
+/// polygon need exactly one polygon; glTF, which needs a second file for
+/// its buffer, is written with write_gltf_files instead.
+pub fn write<W: Write>(
+    polys: &[Poly],
+    format: OutputFormat,
+    thickness: f32,
+    mut writer: W,
+) -> io::Result<()> {
+    if format.is_single_polygon() && polys.len() != 1 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "the {} output holds a single polygon, but the input has {}",
+                format.extension(),
+                polys.len()
+            ),
+        ));
+    }
+
+    match format {
+        OutputFormat::Json => {
+            let polygons: Vec<Value> = polys.iter().map(json_polygon).collect();
+            serde_json::to_writer_pretty(&mut writer, &json!({ "polygons": polygons }))?;
+            writeln!(writer)
+        }
+        OutputFormat::GeoJson => {
+            serde_json::to_writer(&mut writer, &geojson(polys))?;
+            writeln!(writer)
+        }
+        OutputFormat::Wkt => {
+            for poly in polys.iter() {
+                writeln!(writer, "{}", wkt(poly))?;
+            }
+            Ok(())
+        }
+        OutputFormat::Svg => writer.write_all(polys[0].to_svg(&SvgOptions::default()).as_bytes()),
+        OutputFormat::Obj => polys[0].mesh().write_obj(writer),
+        OutputFormat::Ply => polys[0].mesh().write_ply(writer, PlyFormat::Ascii),
+        OutputFormat::PlyBinary => polys[0]
+            .mesh()
+            .write_ply(writer, PlyFormat::BinaryLittleEndian),
+        OutputFormat::Stl => polys[0].mesh().write_stl(writer, thickness),
+        OutputFormat::Glb => polys[0].mesh().write_glb(writer),
+        OutputFormat::Gltf => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the gltf output needs an output file for its buffer",
+        )),
+        OutputFormat::Dxf => write_dxf_polys(polys, writer, &DxfExportOptions::default()),
+    }
+}
+
+/// Writes a .gltf document and, next to it, its .bin buffer.
+pub fn write_gltf_files(poly: &Poly, path: &Path) -> io::Result<()> {
+    let bin_path = path.with_extension("bin");
+    let bin_uri = bin_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("polygon.bin")
+        .to_string();
+    poly.mesh().write_gltf(
+        BufWriter::new(File::create(path)?),
+        BufWriter::new(File::create(&bin_path)?),
+        &bin_uri,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use polygon::test_util::beak_poly;
+
+    #[test]
+    fn test_json_output() {
+        let mut json = Vec::new();
+        write(&[beak_poly()], OutputFormat::Json, 1.0, &mut json).unwrap();
+        let value: Value = serde_json::from_slice(&json).unwrap();
+        let polygon = &value["polygons"][0];
+        assert_eq!(polygon["triangles"].as_array().unwrap().len(), 2);
+        assert_eq!(polygon["convex_parts"].as_array().unwrap().len(), 2);
+        // back in the coordinates of the input
+        assert_eq!(
+            polygon["essential_diagonals"][0],
+            json!([[10.0, 5.0], [0.0, 0.0]])
+        );
+    }
+
+    #[test]
+    fn test_wkt_and_geojson_output() {
+        let line = wkt(&beak_poly());
+        assert!(line.starts_with("GEOMETRYCOLLECTION (MULTIPOLYGON"));
+        assert!(line.contains("MULTILINESTRING ((10 5, 0 0))"));
+
+        let collection = geojson(&[beak_poly(), beak_poly()]);
+        // 2 triangles, 1 diagonal and 2 parts per polygon
+        assert_eq!(collection["features"].as_array().unwrap().len(), 10);
+    }
+
+    #[test]
+    fn test_single_polygon_formats() {
+        let polys = [beak_poly(), beak_poly()];
+        assert!(write(&polys, OutputFormat::Svg, 1.0, io::sink()).is_err());
+        assert!(write(&polys, OutputFormat::Dxf, 1.0, io::sink()).is_ok());
+        assert_eq!(
+            OutputFormat::from_path(Path::new("out.GLB")),
+            Some(OutputFormat::Glb)
+        );
+    }
+}
@@ -3,7 +3,10 @@ use eframe::{egui, epi};
 use egui::math::Pos2;
 
 use crate::draw::PolyDraw;
+use polygon::generate::{random_polygon, Method};
 use polygon::poly::Poly;
+use polygon::polygon_set::PolygonSet;
+use polygon::svg::SvgOptions;
 
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 #[cfg_attr(feature = "serde", serde(default))]
@@ -14,6 +17,9 @@ pub struct DecompApp {
     drawing_app: PolyDraw,
     triangulate: bool,
     decompose: bool,
+    random_method: Method,
+    random_vertices: usize,
+    random_seed: u64,
 }
 
 impl Default for DecompApp {
@@ -29,6 +35,9 @@ impl Default for DecompApp {
             drawing_app: PolyDraw::default(),
             triangulate: false,
             decompose: false,
+            random_method: Method::Star,
+            random_vertices: 20,
+            random_seed: 0,
         }
     }
 }
@@ -61,10 +70,15 @@ impl epi::App for DecompApp {
             poly_list,
             triangulate,
             decompose,
+            random_method,
+            random_vertices,
+            random_seed,
             ..
         } = self;
 
         let mut clear_poly = false;
+        let mut new_random_poly = false;
+        let mut new_ring = false;
         let drawing_stuff = &mut self.drawing_app;
 
         egui::SidePanel::left("side_panel")
@@ -98,6 +112,26 @@ impl epi::App for DecompApp {
                         });
                     ui.end_row();
 
+                    ui.label("random polygon");
+                    ui.horizontal(|ui| {
+                        egui::ComboBox::from_id_source("random_method")
+                            .selected_text(random_method.name())
+                            .show_ui(ui, |ui| {
+                                for method in Method::ALL.iter() {
+                                    ui.selectable_value(random_method, *method, method.name());
+                                }
+                            });
+                        ui.add(
+                            egui::DragValue::new(random_vertices)
+                                .clamp_range(3..=500)
+                                .suffix(" vertices"),
+                        );
+                        if ui.button("🎲").clicked() {
+                            new_random_poly = true;
+                        }
+                    });
+                    ui.end_row();
+
                     ui.label("undo draw");
                     if ui.button("⟲").clicked() {
                         let length = drawing_stuff.points.len();
@@ -115,6 +149,7 @@ impl epi::App for DecompApp {
                             drawing_stuff.polygon.essential_diagonals = vec![];
                             drawing_stuff.show_decomp = false;
                             drawing_stuff.show_essentials = false;
+                            drawing_stuff.clear_overlays();
                             *decompose = false;
                             *triangulate = false;
                         }
@@ -166,15 +201,128 @@ impl epi::App for DecompApp {
                     }
                 });
 
+                ui.separator();
+                egui::Grid::new("skeleton").min_col_width(0.0).show(ui, |ui| {
+                    ui.heading("skeleton");
+                    ui.end_row();
+                    ui.label("straight skeleton");
+                    if ui.button("show").clicked() && drawing_stuff.points.len() > 2 {
+                        drawing_stuff.skeleton = drawing_stuff.polygon.straight_skeleton();
+                    }
+                    ui.end_row();
+                    ui.label("medial axis");
+                    if ui.button("show").clicked() && drawing_stuff.points.len() > 2 {
+                        // sample the boundary a 200th of the polygon's size apart
+                        let first = drawing_stuff.points[0];
+                        let spacing = drawing_stuff
+                            .points
+                            .iter()
+                            .map(|p| (p.x - first.x).abs().max((p.y - first.y).abs()))
+                            .fold(0.0, f32::max)
+                            / 200.0;
+                        drawing_stuff.medial_axis = drawing_stuff.polygon.medial_axis(spacing);
+                    }
+                });
+
+                ui.separator();
+                egui::Grid::new("guards").min_col_width(0.0).show(ui, |ui| {
+                    ui.heading("art gallery");
+                    ui.end_row();
+                    ui.label("guards");
+                    if ui.button("show").clicked() && *triangulate {
+                        let polygon = &drawing_stuff.polygon;
+                        let guards = polygon.guards();
+                        // one visibility graph for all the guards
+                        let graph = polygon.visibility_graph();
+                        drawing_stuff.guard_regions = guards
+                            .guards
+                            .iter()
+                            .filter_map(|guard| graph.visible_region(polygon.vertices[*guard]))
+                            .map(|region| {
+                                region.iter().map(|p| Pos2::from([p[0], -p[1]])).collect()
+                            })
+                            .collect();
+                        drawing_stuff.guards = guards;
+                    }
+                });
+
+                ui.separator();
+                egui::Grid::new("set").min_col_width(0.0).show(ui, |ui| {
+                    ui.heading("polygon set");
+                    ui.end_row();
+                    ui.label("new polygon");
+                    if ui.button("➕").clicked() {
+                        if drawing_stuff.points.len() > 2 {
+                            new_ring = true;
+                        }
+                        else {
+                            println!("Need at least three points");
+                        }
+                    }
+                    ui.end_row();
+                    ui.label("decompose all");
+                    if ui.button("show").clicked() {
+                        // the current polygon joins the finished ones
+                        let mut polygons = drawing_stuff.rings.clone();
+                        if drawing_stuff.points.len() > 2 {
+                            polygons.push(drawing_stuff.points.clone());
+                        }
+                        let mut set = PolygonSet::new(
+                            polygons
+                                .iter()
+                                .map(|ring| ring.iter().map(|p| [p.x, -p.y]).collect())
+                                .collect(),
+                        );
+                        match set.decomposition() {
+                            Ok(()) => drawing_stuff.set_parts = set.convex_parts(),
+                            Err(err) => println!("{}", err),
+                        }
+                    }
+                });
+
+                ui.separator();
+                egui::Grid::new("path").min_col_width(0.0).show(ui, |ui| {
+                    ui.heading("shortest path");
+                    ui.end_row();
+                    ui.label("click start and goal");
+                    let response = ui.checkbox(&mut drawing_stuff.pick_path, "");
+                    if response.changed() {
+                        drawing_stuff.path_ends.clear();
+                        drawing_stuff.path.clear();
+                    }
+                });
+
+                ui.separator();
+                egui::Grid::new("export").min_col_width(0.0).show(ui, |ui| {
+                    ui.heading("export");
+                    ui.end_row();
+                    ui.label("export SVG");
+                    if ui.button("💾").clicked() {
+                        let options = SvgOptions {
+                            show_triangles: *triangulate,
+                            show_convex_parts: drawing_stuff.show_decomp,
+                            show_essentials: drawing_stuff.show_essentials,
+                            ..Default::default()
+                        };
+                        save_svg(&drawing_stuff.polygon.to_svg(&options));
+                    }
+                });
+
                 ui.separator();
                 egui::Grid::new("howto").min_col_width(0.0).show(ui, |ui| {
                     ui.heading("how to use");
                     ui.end_row();
-                    ui.label("1. draw or load polygon");
+                    ui.label("1. draw, load or generate polygon");
                     ui.end_row();
                     ui.label("2. show triangulation");
                     ui.end_row();
                     ui.label("3. show essential edges or convex parts");
+                    ui.end_row();
+                    ui.label("4. tick shortest path and click two points");
+                    ui.end_row();
+                    ui.label("5. show guards after the triangulation");
+                    ui.end_row();
+                    ui.label("6. add polygons to a set and decompose all");
                 });
 
                 ui.with_layout(egui::Layout::bottom_up(egui::Align::LEFT), |ui| {
@@ -211,6 +359,7 @@ impl epi::App for DecompApp {
                         for point in drawing_stuff.points.iter() {
                             drawing_stuff.polygon.vertices.push([point.x, -point.y]);
                         }
+                        drawing_stuff.clear_overlays();
                         drawing_stuff.ui_content(ui);
                     }
                     else {
@@ -261,15 +410,65 @@ impl epi::App for DecompApp {
 
         if clear_poly {
             drawing_stuff.points.clear();
+            drawing_stuff.rings.clear();
             *triangulate = false;
             *decompose = false;
             drawing_stuff.polygon = Poly::default();
             drawing_stuff.show_decomp = false;
             drawing_stuff.show_essentials = false;
+            drawing_stuff.clear_overlays();
         }
+
+        if new_ring {
+            let ring = std::mem::take(&mut drawing_stuff.points);
+            drawing_stuff.rings.push(ring);
+            *triangulate = false;
+            *decompose = false;
+            drawing_stuff.polygon = Poly::default();
+            drawing_stuff.show_decomp = false;
+            drawing_stuff.show_essentials = false;
+            drawing_stuff.clear_overlays();
+        }
+
+        if new_random_poly {
+            // a new seed for every click, so each click gives another polygon
+            let vertices = random_polygon(*random_method, *random_vertices, *random_seed);
+            *random_seed += 1;
+
+            *triangulate = false;
+            *decompose = false;
+            *selected_poly = "select a polygon".to_string();
+            drawing_stuff.polygon = Poly::default();
+            drawing_stuff.show_decomp = false;
+            drawing_stuff.show_essentials = false;
+            drawing_stuff.clear_overlays();
+            // the unit square of the generator, with y up, onto the canvas
+            drawing_stuff.points = vertices
+                .iter()
+                .map(|v| Pos2::from([350.0 + 400.0 * v[0], 100.0 + 400.0 * (1.0 - v[1])]))
+                .collect();
+            for point in drawing_stuff.points.iter() {
+                drawing_stuff.polygon.vertices.push([point.x, -point.y]);
+            }
+        }
+    }
+}
+
+/// Writes the SVG export next to the executable's working directory.
+/// There is no file system in the browser, so nothing is saved there.
+#[cfg(not(target_arch = "wasm32"))]
+fn save_svg(svg: &str) {
+    match std::fs::write("polydecomp.svg", svg) {
+        Ok(()) => println!("Saved polydecomp.svg"),
+        Err(err) => println!("Could not save polydecomp.svg: {}", err),
     }
 }
 
+#[cfg(target_arch = "wasm32")]
+fn save_svg(_svg: &str) {
+    println!("SVG export is only available in the native app");
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,6 +1,11 @@
 use eframe::egui::epaint::CircleShape;
 use eframe::egui::*;
+use polygon::guards::Guards;
 use polygon::poly::Poly;
+use polygon::polygon_set::Piece;
+use polygon::skeleton::Skeleton;
+use polygon::svg::{PART_PALETTE, TRIANGLE_PALETTE};
+use polygon::visibility::VisibilityGraph;
 use std::vec;
 
 // we follow the egui painting demo
@@ -10,6 +15,23 @@ pub struct PolyDraw {
     pub polygon: Poly,
     pub show_essentials: bool,
     pub show_decomp: bool,
+    /// Clicks pick the ends of a shortest path instead of adding vertices
+    pub pick_path: bool,
+    pub path_ends: Vec<Pos2>,
+    pub path: Vec<Pos2>,
+    /// Visibility graph of the polygon, built by the first path query
+    /// and kept for the next ones
+    pub visibility: Option<VisibilityGraph>,
+    pub skeleton: Skeleton,
+    pub medial_axis: Skeleton,
+    pub guards: Guards,
+    /// Region seen by every guard, star-shaped around the guard
+    pub guard_regions: Vec<Vec<Pos2>>,
+    /// Finished polygons of a scene, drawn before the current one
+    pub rings: Vec<Vec<Pos2>>,
+    /// Convex parts of the finished polygons and the current one,
+    /// decomposed as a PolygonSet
+    pub set_parts: Vec<Piece>,
 }
 
 impl Default for PolyDraw {
@@ -19,11 +41,35 @@ impl Default for PolyDraw {
             polygon: Poly::default(),
             show_essentials: false,
             show_decomp: false,
+            pick_path: false,
+            path_ends: Vec::new(),
+            path: Vec::new(),
+            visibility: None,
+            skeleton: Skeleton::default(),
+            medial_axis: Skeleton::default(),
+            guards: Guards::default(),
+            guard_regions: Vec::new(),
+            rings: Vec::new(),
+            set_parts: Vec::new(),
         }
     }
 }
 
 impl PolyDraw {
+    /// Drops the path and its visibility graph, the skeletons, the guards
+    /// and the decomposition of the scene, which belong to the polygons
+    /// they were computed for
+    pub fn clear_overlays(&mut self) {
+        self.path_ends.clear();
+        self.path.clear();
+        self.visibility = None;
+        self.skeleton = Skeleton::default();
+        self.medial_axis = Skeleton::default();
+        self.guards = Guards::default();
+        self.guard_regions.clear();
+        self.set_parts.clear();
+    }
+
     pub fn ui_content(&mut self, ui: &mut Ui) -> Response {
         let (response, painter) =
             ui.allocate_painter(ui.available_size_before_wrap(), Sense::click());
@@ -34,21 +80,60 @@ impl PolyDraw {
         let mut triangles_shapes: Vec<Shape> = vec![];
         let mut convex_shapes: Vec<Shape> = vec![];
         let mut essential_lines: Vec<Shape> = vec![];
+        let mut path_shapes: Vec<Shape> = vec![];
+        let mut skeleton_lines: Vec<Shape> = vec![];
+        let mut guard_shapes: Vec<Shape> = vec![];
+        let mut set_shapes: Vec<Shape> = vec![];
         // --------------------------------------------------------
 
+        // two clicks give the ends of a shortest path, a third starts over
+        if self.pick_path {
+            if let (true, Some(pointer_pos)) = (response.clicked(), response.interact_pointer_pos())
+            {
+                if self.path_ends.len() == 2 {
+                    self.path_ends.clear();
+                    self.path.clear();
+                }
+                self.path_ends.push(pointer_pos);
+                if let [start, goal] = self.path_ends[..] {
+                    let polygon = &self.polygon;
+                    let graph = self
+                        .visibility
+                        .get_or_insert_with(|| polygon.visibility_graph());
+                    // no path if a click is outside the polygon
+                    self.path = graph
+                        .shortest_path([start.x, -start.y], [goal.x, -goal.y])
+                        .unwrap_or_default()
+                        .iter()
+                        .map(|p| Pos2::from([p[0], -p[1]]))
+                        .collect();
+                }
+            }
+        }
         // poly vertices drawn by clicking on canvas
-        if let Some(mut pointer_pos) = response.interact_pointer_pos() {
+        else if let Some(mut pointer_pos) = response.interact_pointer_pos() {
             // truncating...otherwise get point repetition due to
             // too high precision
             pointer_pos = Pos2::from([
                 f32::trunc(pointer_pos.x * 10.0) / 10.0,
                 f32::trunc(pointer_pos.y * 10.0) / 10.0,
             ]);
+            // clicks near a vertex of a finished polygon snap to it, so
+            // polygons can share edges and be decomposed together
+            if let Some(vertex) = self
+                .rings
+                .iter()
+                .flatten()
+                .find(|vertex| vertex.distance(pointer_pos) < 8.0)
+            {
+                pointer_pos = *vertex;
+            }
 
             if !self.points.contains(&pointer_pos) {
                 self.points.push(pointer_pos);
                 let transformed_pos: [f32; 2] = [pointer_pos.x, -pointer_pos.y];
                 self.polygon.vertices.push(transformed_pos);
+                self.clear_overlays();
             }
         }
 
@@ -72,6 +157,42 @@ impl PolyDraw {
             })
         }
 
+        // finished polygons of the scene
+        for ring in self.rings.iter() {
+            for (idx, point) in ring.iter().enumerate() {
+                points_shapes.push(Shape::Circle(CircleShape {
+                    center: *point,
+                    radius: 3.0,
+                    fill: Color32::GRAY,
+                    stroke: Default::default(),
+                }));
+                lines_shapes.push(Shape::LineSegment {
+                    points: [*point, ring[(idx + 1) % ring.len()]],
+                    stroke: Stroke {
+                        width: 2.0,
+                        color: Color32::GRAY,
+                    },
+                })
+            }
+        }
+
+        // convex parts of the scene, coloured by the first polygon they
+        // come from, so parts across touching polygons stand out
+        for part in self.set_parts.iter() {
+            let points_for_shape = part
+                .vertices
+                .iter()
+                .map(|p| Pos2::from([p.x, -p.y]))
+                .collect();
+            let origin = part.origins.first().copied().unwrap_or_default();
+            let [r, g, b] = PART_PALETTE[origin % PART_PALETTE.len()];
+            set_shapes.push(Shape::convex_polygon(
+                points_for_shape,
+                Color32::from_rgb(r, g, b),
+                Stroke::new(1.0, Color32::DARK_GRAY),
+            ));
+        }
+
         // essential diagonals
         for essential in self.polygon.essential_diagonals.iter() {
             essential_lines.push(Shape::LineSegment {
@@ -103,14 +224,9 @@ impl PolyDraw {
                 ));
             };
 
-            match idx % 6 {
-                0 => colour_triangles(Color32::from_rgb(78, 91, 207)),
-                1 => colour_triangles(Color32::from_rgb(212, 158, 11)),
-                2 => colour_triangles(Color32::from_rgb(115, 23, 43)),
-                3 => colour_triangles(Color32::from_rgb(52, 133, 75)),
-                4 => colour_triangles(Color32::from_rgb(237, 137, 7)),
-                _ => colour_triangles(Color32::from_rgb(17, 143, 130)),
-            }
+            // same palette as the SVG export
+            let [r, g, b] = TRIANGLE_PALETTE[idx % TRIANGLE_PALETTE.len()];
+            colour_triangles(Color32::from_rgb(r, g, b));
         }
 
         // convex part of the polygon
@@ -128,19 +244,81 @@ impl PolyDraw {
                 ));
             };
 
-            match idx % 6 {
-                0 => colour_parts(Color32::from_rgb(99, 164, 186)),
-                1 => colour_parts(Color32::from_rgb(191, 128, 189)),
-                2 => colour_parts(Color32::from_rgb(143, 191, 128)),
-                3 => colour_parts(Color32::from_rgb(235, 233, 117)),
-                4 => colour_parts(Color32::from_rgb(227, 176, 132)),
-                _ => colour_parts(Color32::from_rgb(137, 124, 222)),
+            let [r, g, b] = PART_PALETTE[idx % PART_PALETTE.len()];
+            colour_parts(Color32::from_rgb(r, g, b));
+        }
+
+        // straight skeleton and medial axis, empty until computed
+        let overlays = [
+            (&self.skeleton, Color32::from_rgb(0, 150, 0)),
+            (&self.medial_axis, Color32::from_rgb(150, 0, 150)),
+        ];
+        for (skeleton, color) in overlays.iter() {
+            for arc in skeleton.arcs.iter() {
+                let [from, to] = [
+                    skeleton.nodes[arc[0]].position,
+                    skeleton.nodes[arc[1]].position,
+                ];
+                skeleton_lines.push(Shape::LineSegment {
+                    points: [Pos2::from([from[0], -from[1]]), Pos2::from([to[0], -to[1]])],
+                    stroke: Stroke {
+                        width: 1.5,
+                        color: *color,
+                    },
+                })
+            }
+        }
+
+        // 3-coloured vertices, with the guards and what they see on top
+        let colours = [Color32::RED, Color32::from_rgb(0, 170, 0), Color32::BLUE];
+        for region in self.guard_regions.iter() {
+            // the region is star-shaped around the guard, its first point
+            for idx in 1..region.len().saturating_sub(1) {
+                guard_shapes.push(Shape::convex_polygon(
+                    vec![region[0], region[idx], region[idx + 1]],
+                    Color32::from_rgba_unmultiplied(255, 200, 0, 40),
+                    Stroke::none(),
+                ));
             }
         }
+        for (idx, colour) in self.guards.colours.iter().enumerate() {
+            let vertex = self.polygon.vertices[idx];
+            let guard = *colour == self.guards.colour;
+            guard_shapes.push(Shape::Circle(CircleShape {
+                center: Pos2::from([vertex[0], -vertex[1]]),
+                radius: if guard { 8.0 } else { 5.0 },
+                fill: colours[*colour as usize % colours.len()],
+                stroke: if guard {
+                    Stroke::new(2.0, Color32::BLACK)
+                } else {
+                    Stroke::none()
+                },
+            }));
+        }
+
+        // shortest path between the picked points
+        for end in self.path_ends.iter() {
+            path_shapes.push(Shape::Circle(CircleShape {
+                center: *end,
+                radius: 5.0,
+                fill: Color32::BLUE,
+                stroke: Default::default(),
+            }));
+        }
+        for pair in self.path.windows(2) {
+            path_shapes.push(Shape::LineSegment {
+                points: [pair[0], pair[1]],
+                stroke: Stroke {
+                    width: 3.0,
+                    color: Color32::BLUE,
+                },
+            })
+        }
 
         // adding to the painter
 
         painter.extend(triangles_shapes);
+        painter.extend(set_shapes);
         if self.show_decomp {
             painter.extend(convex_shapes);
         }
@@ -148,8 +326,11 @@ impl PolyDraw {
             painter.extend(essential_lines);
         }
 
+        painter.extend(skeleton_lines);
         painter.extend(lines_shapes);
         painter.extend(points_shapes);
+        painter.extend(guard_shapes);
+        painter.extend(path_shapes);
 
         response
     }
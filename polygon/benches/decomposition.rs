@@ -0,0 +1,111 @@
+//! Benchmarks of the triangulation, labeling and gluing stages of the
+//! decomposition, and of point location, on generated polygons of
+//! increasing size and several shape classes.  Run with
+//! `cargo bench -p polygon --features bench`.  The throughput is
+//! reported in vertices, or queries, per second, so a stage that is
+//! slower than linear shows up as a throughput that drops with the
+//! size; the plots are in target/criterion/report.
+use criterion::{
+    criterion_group, criterion_main, AxisScale, BatchSize, BenchmarkId, Criterion,
+    PlotConfiguration, SamplingMode, Throughput,
+};
+use polygon::bench_util::{glued, labeled};
+use polygon::generate::{random_polygon, Method};
+use polygon::poly::Poly;
+
+const SHAPES: [Method; 4] = [Method::Convex, Method::Star, Method::Comb, Method::Spiral];
+
+/// Sizes benchmarked for a shape, three decades up to the largest one.
+/// The triangulation takes quadratic time on spirals, and the generated
+/// convex polygons stop being convex beyond a few thousand vertices.
+fn sizes(method: Method) -> [usize; 3] {
+    match method {
+        Method::Convex => [10, 100, 1_000],
+        Method::Spiral => [100, 1_000, 10_000],
+        _ => [1_000, 10_000, 100_000],
+    }
+}
+
+fn untriangulated(vertices: &[[f32; 2]]) -> Poly {
+    Poly {
+        vertices: vertices.to_vec(),
+        ..Default::default()
+    }
+}
+
+fn triangulated(vertices: &[[f32; 2]]) -> Poly {
+    let mut poly = untriangulated(vertices);
+    poly.triang();
+    poly
+}
+
+/// Benchmarks one stage in a group per stage.  setup prepares the
+/// input of the stage and is not timed.
+fn bench_stage<I, O, S, R>(c: &mut Criterion, stage: &str, setup: S, routine: R)
+where
+    S: Fn(&[[f32; 2]]) -> I,
+    R: Fn(I) -> O,
+{
+    let mut group = c.benchmark_group(stage);
+    group.sample_size(10);
+    group.sampling_mode(SamplingMode::Flat);
+    group.plot_config(PlotConfiguration::default().summary_scale(AxisScale::Logarithmic));
+    for method in SHAPES.iter() {
+        for size in sizes(*method) {
+            let vertices = random_polygon(*method, size, 0);
+            group.throughput(Throughput::Elements(vertices.len() as u64));
+            group.bench_with_input(
+                BenchmarkId::new(method.name(), size),
+                &vertices,
+                |b, vertices| b.iter_batched(|| setup(vertices), &routine, BatchSize::LargeInput),
+            );
+        }
+    }
+    group.finish();
+}
+
+fn triangulation(c: &mut Criterion) {
+    bench_stage(c, "triangulation", untriangulated, |mut poly| {
+        poly.triang();
+        poly
+    });
+}
+
+fn labeling(c: &mut Criterion) {
+    bench_stage(c, "labeling", triangulated, labeled);
+}
+
+fn gluing(c: &mut Criterion) {
+    bench_stage(
+        c,
+        "gluing",
+        |vertices| labeled(triangulated(vertices)),
+        glued,
+    );
+}
+
+/// Point location in the convex parts and triangles, a thousand queries
+/// on a grid over the unit square per iteration; the throughput should
+/// barely drop with the size
+fn locating(c: &mut Criterion) {
+    let mut group = c.benchmark_group("locating");
+    group.plot_config(PlotConfiguration::default().summary_scale(AxisScale::Logarithmic));
+    let queries: Vec<[f32; 2]> = (0..1000)
+        .map(|idx| [(idx % 32) as f32 / 31.0, (idx / 32) as f32 / 31.0])
+        .collect();
+    for method in SHAPES.iter() {
+        for size in sizes(*method) {
+            let mut poly = triangulated(&random_polygon(*method, size, 0));
+            poly.decomposition();
+            let locator = poly.point_locator();
+            group.throughput(Throughput::Elements(queries.len() as u64));
+            group.bench_function(BenchmarkId::new(method.name(), size), |b| {
+                b.iter(|| queries.iter().filter_map(|q| locator.locate(*q)).count())
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, triangulation, labeling, gluing, locating);
+criterion_main!(benches);
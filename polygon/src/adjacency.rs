@@ -0,0 +1,216 @@
+//! Adjacency of the convex parts and of the triangles: which of them
+//! share an edge, and which essential diagonal that edge lies on.  The
+//! triangles of a simple polygon form a tree, the dual tree of the
+//! triangulation.
+use crate::geometry::orientation;
+use crate::poly::Poly;
+use cgmath::Point2;
+use std::collections::HashMap;
+
+/// Part or triangle on the other side of a shared edge
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Neighbour {
+    /// Index of the neighbour in Poly::convex_parts or Poly::triangles
+    pub index: usize,
+    /// Ends of the shared edge, in the ccw order of the part itself
+    pub edge: [Point2<f32>; 2],
+    /// Index of the essential diagonal the edge lies on, None for an
+    /// inessential diagonal of the triangulation
+    pub diagonal: Option<usize>,
+}
+
+/// Neighbours of every convex part or every triangle
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Adjacency {
+    pub neighbours: Vec<Vec<Neighbour>>,
+}
+
+impl Poly {
+    /// Adjacency of the convex parts: two parts are neighbours if they
+    /// share an edge, which lies on an essential diagonal.
+    pub fn part_adjacency(&self) -> Adjacency {
+        self.adjacency(&self.convex_part_cycles())
+    }
+
+    /// Adjacency of the triangles, the dual tree of the triangulation.
+    /// Expects the triangles of triang.
+    pub fn triangle_adjacency(&self) -> Adjacency {
+        let cycles: Vec<Vec<Point2<f32>>> = self.triangles.iter().map(|t| t.to_vec()).collect();
+        self.adjacency(&cycles)
+    }
+
+    fn adjacency(&self, cycles: &[Vec<Point2<f32>>]) -> Adjacency {
+        let key = |p: Point2<f32>| [p.x.to_bits(), p.y.to_bits()];
+        let mut left_of: HashMap<[[u32; 2]; 2], usize> = HashMap::new();
+        for (idx, cycle) in cycles.iter().enumerate() {
+            for vertex in 0..cycle.len() {
+                let next = cycle[(vertex + 1) % cycle.len()];
+                left_of.insert([key(cycle[vertex]), key(next)], idx);
+            }
+        }
+        let mut diagonals = HashMap::new();
+        for (idx, diagonal) in self.essential_diagonals.iter().enumerate() {
+            let [from, to] = [diagonal[0], diagonal[1]];
+            let (from, to) = (Point2::new(from[0], -from[1]), Point2::new(to[0], -to[1]));
+            diagonals.insert([key(from), key(to)], idx);
+            diagonals.insert([key(to), key(from)], idx);
+        }
+
+        let neighbours = cycles
+            .iter()
+            .map(|cycle| {
+                (0..cycle.len())
+                    .filter_map(|vertex| {
+                        let (from, to) = (cycle[vertex], cycle[(vertex + 1) % cycle.len()]);
+                        left_of.get(&[key(to), key(from)]).map(|index| Neighbour {
+                            index: *index,
+                            edge: [from, to],
+                            diagonal: diagonals
+                                .get(&[key(from), key(to)])
+                                .copied()
+                                .or_else(|| self.diagonal_through(from, to)),
+                        })
+                    })
+                    .collect()
+            })
+            .collect();
+        Adjacency { neighbours }
+    }
+
+    /// Index of the essential diagonal containing the segment between
+    /// the points, for part edges that end at Steiner points on a
+    /// diagonal after refine_parts.
+    fn diagonal_through(&self, from: Point2<f32>, to: Point2<f32>) -> Option<usize> {
+        if self.steiner_points.is_empty() {
+            return None;
+        }
+        let point = |p: [f32; 2]| [p[0] as f64, -p[1] as f64];
+        let (from, to) = ([from.x as f64, from.y as f64], [to.x as f64, to.y as f64]);
+        self.essential_diagonals.iter().position(|diagonal| {
+            let (a, b) = (point(diagonal[0]), point(diagonal[1]));
+            let length = (b[0] - a[0]).hypot(b[1] - a[1]);
+            [from, to].iter().all(|p| {
+                let along = (p[0] - a[0]) * (b[0] - a[0]) + (p[1] - a[1]) * (b[1] - a[1]);
+                orientation(a, b, *p).abs() <= 1e-5 * length * length
+                    && along >= 0.0
+                    && along <= length * length
+            })
+        })
+    }
+}
+
+impl Adjacency {
+    /// Number of parts or triangles
+    pub fn len(&self) -> usize {
+        self.neighbours.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.neighbours.is_empty()
+    }
+
+    /// Every shared edge once, as (index, neighbour) with the smaller
+    /// index first
+    pub fn edges(&self) -> impl Iterator<Item = (usize, &Neighbour)> + '_ {
+        self.neighbours
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, neighbours)| {
+                neighbours
+                    .iter()
+                    .filter(move |neighbour| neighbour.index > idx)
+                    .map(move |neighbour| (idx, neighbour))
+            })
+    }
+
+    /// Undirected petgraph graph with a node per part or triangle,
+    /// weighted by its index, and an edge per shared edge, weighted by
+    /// the neighbour as seen from the node with the smaller index.
+    #[cfg(feature = "petgraph")]
+    pub fn to_graph(&self) -> petgraph::graph::UnGraph<usize, Neighbour> {
+        let mut graph = petgraph::graph::UnGraph::with_capacity(self.len(), self.len());
+        let nodes: Vec<_> = (0..self.len()).map(|idx| graph.add_node(idx)).collect();
+        for (idx, neighbour) in self.edges() {
+            graph.add_edge(nodes[idx], nodes[neighbour.index], *neighbour);
+        }
+        graph
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::generate::{random_polygon, Method};
+    use crate::refine::PartLimits;
+    use crate::test_util::decomposed;
+
+    #[test]
+    fn test_parts_of_an_l_shape() {
+        let l_shape = vec![
+            [0.0, 0.0],
+            [4.0, 0.0],
+            [4.0, 1.0],
+            [1.0, 1.0],
+            [1.0, 4.0],
+            [0.0, 4.0],
+        ];
+        let poly = decomposed(l_shape);
+        let adjacency = poly.part_adjacency();
+        assert_eq!(adjacency.len(), 2);
+        for (idx, neighbours) in adjacency.neighbours.iter().enumerate() {
+            assert_eq!(neighbours.len(), 1);
+            assert_eq!(neighbours[0].index, 1 - idx);
+            assert_eq!(neighbours[0].diagonal, Some(0));
+        }
+        let [from, to] = adjacency.neighbours[0][0].edge;
+        assert_eq!(adjacency.neighbours[1][0].edge, [to, from]);
+        assert_eq!(adjacency.edges().count(), 1);
+
+        // refined parts share pieces of the cuts
+        let mut poly = poly;
+        poly.refine_parts(&PartLimits {
+            max_area: 1.0,
+            ..Default::default()
+        });
+        let adjacency = poly.part_adjacency();
+        assert!(adjacency.len() > 2);
+        assert!(adjacency
+            .edges()
+            .all(|(_, neighbour)| neighbour.diagonal.is_some()));
+    }
+
+    #[test]
+    fn test_dual_tree() {
+        for method in Method::ALL.iter() {
+            for seed in 0..3 {
+                let poly = decomposed(random_polygon(*method, 50, seed));
+                let triangles = poly.triangle_adjacency();
+                assert_eq!(triangles.len(), poly.triangles.len());
+                assert_eq!(triangles.edges().count(), triangles.len() - 1);
+                // every essential diagonal separates two triangles
+                let essential = triangles
+                    .edges()
+                    .filter(|(_, neighbour)| neighbour.diagonal.is_some())
+                    .count();
+                assert_eq!(essential, poly.essential_diagonals.len());
+
+                // HM parts are glued along inessential diagonals only, so
+                // the parts form a tree as well
+                let parts = poly.part_adjacency();
+                assert_eq!(parts.edges().count(), poly.essential_diagonals.len());
+                assert!(parts
+                    .edges()
+                    .all(|(_, neighbour)| neighbour.diagonal.is_some()));
+            }
+        }
+    }
+
+    #[cfg(feature = "petgraph")]
+    #[test]
+    fn test_petgraph_export() {
+        let poly = decomposed(random_polygon(Method::Star, 40, 1));
+        let graph = poly.part_adjacency().to_graph();
+        assert_eq!(graph.node_count(), poly.convex_parts.len());
+        assert_eq!(graph.edge_count(), poly.essential_diagonals.len());
+        assert_eq!(petgraph::algo::connected_components(&graph), 1);
+    }
+}
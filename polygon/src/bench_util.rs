@@ -0,0 +1,25 @@
+//! The labeling and gluing stages of the decomposition one at a time,
+//! for the benchmarks.
+use crate::poly::{EdgeLabels, Poly};
+
+/// Polygon after the labeling stage, with the labels for the gluing
+pub struct Labeled {
+    poly: Poly,
+    edge_labels: EdgeLabels,
+}
+
+/// Labeling stage of Poly::decomposition on a triangulated polygon
+pub fn labeled(mut poly: Poly) -> Labeled {
+    let edge_labels = poly.label_diagonals();
+    Labeled { poly, edge_labels }
+}
+
+/// Gluing stage of Poly::decomposition
+pub fn glued(labeled: Labeled) -> Poly {
+    let Labeled {
+        mut poly,
+        edge_labels,
+    } = labeled;
+    poly.glue(&edge_labels);
+    poly
+}
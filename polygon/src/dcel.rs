@@ -0,0 +1,327 @@
+//! Doubly-connected edge list of the triangulation or the convex
+//! decomposition, owned and independent of spade, for tools that walk
+//! the topology: every edge is a pair of half-edges, one on either side,
+//! and the half-edges around every face and around the outside of the
+//! polygon are linked into cycles.
+use crate::geometry::doubled_area;
+use crate::poly::Poly;
+use cgmath::Point2;
+use std::collections::HashMap;
+
+/// Vertex of a DCEL
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Vertex {
+    pub position: [f32; 2],
+    /// A half-edge leaving the vertex
+    pub half_edge: usize,
+}
+
+/// Half-edge of a DCEL, running ccw around its face
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HalfEdge {
+    /// Vertex the half-edge starts at
+    pub origin: usize,
+    /// Half-edge of the same edge in the other direction
+    pub twin: usize,
+    /// Next and previous half-edge around the face
+    pub next: usize,
+    pub prev: usize,
+    /// Face on the left, None outside the polygon
+    pub face: Option<usize>,
+}
+
+/// Face of a DCEL, a triangle or a convex part
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Face {
+    /// A half-edge on the boundary of the face
+    pub half_edge: usize,
+}
+
+/// Doubly-connected edge list.  The vertices are the polygon vertices,
+/// numbered as in Poly::vertices, followed by the Steiner points of the
+/// parts.  The faces are numbered as in Poly::triangles or
+/// Poly::convex_parts.  The half-edges outside the polygon have no face
+/// and run cw around it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Dcel {
+    pub vertices: Vec<Vertex>,
+    pub half_edges: Vec<HalfEdge>,
+    pub faces: Vec<Face>,
+}
+
+impl Poly {
+    /// DCEL of the triangles of triang, with a face per triangle; empty
+    /// before triang
+    pub fn triangle_dcel(&self) -> Dcel {
+        let cycles: Vec<Vec<Point2<f32>>> = self.triangles.iter().map(|t| t.to_vec()).collect();
+        self.dcel(&cycles)
+    }
+
+    /// DCEL of the convex parts of decomposition or refine_parts, with a
+    /// face per part; empty before decomposition
+    pub fn part_dcel(&self) -> Dcel {
+        self.dcel(&self.convex_part_cycles())
+    }
+
+    fn dcel(&self, cycles: &[Vec<Point2<f32>>]) -> Dcel {
+        if cycles.is_empty() {
+            return Dcel::default();
+        }
+        let key = |p: [f32; 2]| [p[0].to_bits(), p[1].to_bits()];
+        let mut dcel = Dcel::default();
+        let mut index: HashMap<[u32; 2], usize> = HashMap::new();
+        for v in self.vertices.iter() {
+            index.entry(key(*v)).or_insert_with(|| {
+                dcel.vertices.push(Vertex {
+                    position: *v,
+                    half_edge: usize::MAX,
+                });
+                dcel.vertices.len() - 1
+            });
+        }
+
+        // every edge is made once, with a twin that stays outside
+        // until its face is found
+        let mut half_edge: HashMap<[usize; 2], usize> = HashMap::new();
+        for (face, cycle) in cycles.iter().enumerate() {
+            let mut vertices: Vec<usize> = cycle
+                .iter()
+                .map(|p| {
+                    *index.entry(key([p.x, p.y])).or_insert_with(|| {
+                        dcel.vertices.push(Vertex {
+                            position: [p.x, p.y],
+                            half_edge: usize::MAX,
+                        });
+                        dcel.vertices.len() - 1
+                    })
+                })
+                .collect();
+            let ring: Vec<[f32; 2]> = cycle.iter().map(|p| [p.x, p.y]).collect();
+            if doubled_area(&ring) < 0.0 {
+                vertices.reverse();
+            }
+
+            let length = vertices.len();
+            let edges: Vec<usize> = (0..length)
+                .map(|idx| {
+                    let (from, to) = (vertices[idx], vertices[(idx + 1) % length]);
+                    match half_edge.get(&[from, to]) {
+                        Some(edge) => *edge,
+                        None => {
+                            let edge = dcel.half_edges.len();
+                            for (origin, twin) in [(from, edge + 1), (to, edge)] {
+                                dcel.half_edges.push(HalfEdge {
+                                    origin,
+                                    twin,
+                                    next: usize::MAX,
+                                    prev: usize::MAX,
+                                    face: None,
+                                });
+                            }
+                            half_edge.insert([from, to], edge);
+                            half_edge.insert([to, from], edge + 1);
+                            edge
+                        }
+                    }
+                })
+                .collect();
+            for (idx, edge) in edges.iter().enumerate() {
+                let h = &mut dcel.half_edges[*edge];
+                h.face = Some(face);
+                h.next = edges[(idx + 1) % length];
+                h.prev = edges[(idx + length - 1) % length];
+            }
+            dcel.faces.push(Face {
+                half_edge: edges[0],
+            });
+        }
+
+        // the polygon is simple, so a single outside half-edge leaves
+        // every boundary vertex
+        let outside: HashMap<usize, usize> = (0..dcel.half_edges.len())
+            .filter(|edge| dcel.half_edges[*edge].face.is_none())
+            .map(|edge| (dcel.half_edges[edge].origin, edge))
+            .collect();
+        for edge in 0..dcel.half_edges.len() {
+            if dcel.half_edges[edge].face.is_none() {
+                let next = outside[&dcel.destination(edge)];
+                dcel.half_edges[edge].next = next;
+                dcel.half_edges[next].prev = edge;
+            }
+        }
+        for (edge, h) in dcel.half_edges.iter().enumerate() {
+            if dcel.vertices[h.origin].half_edge == usize::MAX {
+                dcel.vertices[h.origin].half_edge = edge;
+            }
+        }
+        dcel
+    }
+}
+
+impl Dcel {
+    /// Vertex the half-edge ends at
+    pub fn destination(&self, half_edge: usize) -> usize {
+        self.half_edges[self.half_edges[half_edge].twin].origin
+    }
+
+    /// Half-edges of the cycle through the half-edge, following next
+    pub fn cycle(&self, half_edge: usize) -> impl Iterator<Item = usize> + '_ {
+        let mut current = Some(half_edge);
+        std::iter::from_fn(move || {
+            let edge = current?;
+            let next = self.half_edges[edge].next;
+            current = (next != half_edge).then_some(next);
+            Some(edge)
+        })
+    }
+
+    /// Vertices of the face in ccw order
+    pub fn face_vertices(&self, face: usize) -> Vec<usize> {
+        self.cycle(self.faces[face].half_edge)
+            .map(|edge| self.half_edges[edge].origin)
+            .collect()
+    }
+
+    /// Half-edges leaving the vertex, in cw order
+    pub fn outgoing(&self, vertex: usize) -> impl Iterator<Item = usize> + '_ {
+        let first = self.vertices[vertex].half_edge;
+        let mut current = Some(first);
+        std::iter::from_fn(move || {
+            let edge = current?;
+            let next = self.half_edges[self.half_edges[edge].twin].next;
+            current = (next != first).then_some(next);
+            Some(edge)
+        })
+    }
+
+    /// A half-edge outside the polygon, whose cycle is the boundary in
+    /// cw order; None without faces
+    pub fn boundary(&self) -> Option<usize> {
+        self.half_edges.iter().position(|h| h.face.is_none())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{random_polygon, Method};
+    use crate::refine::PartLimits;
+
+    /// Checks the links of the DCEL and Euler's formula for a disc
+    fn check(dcel: &Dcel) {
+        for (edge, h) in dcel.half_edges.iter().enumerate() {
+            assert_eq!(dcel.half_edges[h.twin].twin, edge);
+            assert_ne!(h.twin, edge);
+            assert_eq!(dcel.half_edges[h.next].prev, edge);
+            assert_eq!(dcel.half_edges[h.prev].next, edge);
+            assert_eq!(dcel.half_edges[h.next].face, h.face);
+            assert_eq!(dcel.half_edges[h.next].origin, dcel.destination(edge));
+        }
+        for (vertex, v) in dcel.vertices.iter().enumerate() {
+            assert_eq!(dcel.half_edges[v.half_edge].origin, vertex);
+            assert!(dcel
+                .outgoing(vertex)
+                .all(|edge| dcel.half_edges[edge].origin == vertex));
+        }
+        let (v, e, f) = (
+            dcel.vertices.len() as i64,
+            dcel.half_edges.len() as i64 / 2,
+            dcel.faces.len() as i64,
+        );
+        assert_eq!(v - e + f, 1);
+    }
+
+    #[test]
+    fn test_square() {
+        let mut poly = Poly {
+            vertices: vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]],
+            ..Default::default()
+        };
+        assert_eq!(poly.triangle_dcel(), Dcel::default());
+        poly.triang();
+        poly.decomposition();
+
+        let triangles = poly.triangle_dcel();
+        check(&triangles);
+        assert_eq!((triangles.half_edges.len(), triangles.faces.len()), (10, 2));
+        // the diagonal is the one edge with faces on both sides, and
+        // three edges leave either end of it
+        let diagonal = triangles
+            .half_edges
+            .iter()
+            .position(|h| h.face.is_some() && triangles.half_edges[h.twin].face.is_some())
+            .unwrap();
+        for end in [
+            triangles.half_edges[diagonal].origin,
+            triangles.destination(diagonal),
+        ] {
+            assert_eq!(triangles.outgoing(end).count(), 3);
+        }
+
+        // the square is a single part
+        let parts = poly.part_dcel();
+        check(&parts);
+        assert_eq!(parts.face_vertices(0).len(), 4);
+        let boundary = parts.boundary().unwrap();
+        let cw: Vec<usize> = parts
+            .cycle(boundary)
+            .map(|edge| parts.half_edges[edge].origin)
+            .collect();
+        let mut ccw = parts.face_vertices(0);
+        ccw.reverse();
+        let at = ccw.iter().position(|v| *v == cw[0]).unwrap();
+        ccw.rotate_left(at);
+        assert_eq!(ccw, cw);
+    }
+
+    #[test]
+    fn test_triangles_and_parts() {
+        for method in Method::ALL.iter() {
+            for seed in 0..3 {
+                let mut poly = Poly {
+                    vertices: random_polygon(*method, 50, seed),
+                    ..Default::default()
+                };
+                poly.triang();
+                poly.decomposition();
+
+                let triangles = poly.triangle_dcel();
+                check(&triangles);
+                assert_eq!(triangles.vertices.len(), poly.vertices.len());
+                for (face, triangle) in poly.triangles.iter().enumerate() {
+                    let vertices = triangles.face_vertices(face);
+                    assert_eq!(vertices.len(), 3);
+                    assert!(vertices.iter().all(|v| {
+                        let [x, y] = triangles.vertices[*v].position;
+                        triangle.contains(&Point2::new(x, y))
+                    }));
+                }
+                // the boundary runs cw through every polygon vertex
+                let boundary = triangles.cycle(triangles.boundary().unwrap()).count();
+                assert_eq!(boundary, poly.vertices.len());
+
+                let parts = poly.part_dcel();
+                check(&parts);
+                assert_eq!(parts.faces.len(), poly.convex_parts.len());
+                let diagonals = parts
+                    .half_edges
+                    .iter()
+                    .filter(|h| h.face.is_some() && parts.half_edges[h.twin].face.is_some())
+                    .count();
+                assert_eq!(diagonals, 2 * poly.essential_diagonals.len());
+
+                // Steiner points come after the polygon vertices
+                poly.refine_parts(&PartLimits {
+                    max_area: 0.02,
+                    ..Default::default()
+                });
+                let refined = poly.part_dcel();
+                check(&refined);
+                assert_eq!(refined.vertices[0].position, poly.vertices[0]);
+                assert!(refined.vertices[poly.vertices.len()..]
+                    .iter()
+                    .all(|v| poly.steiner_points.contains(&v.position)));
+            }
+        }
+    }
+}
@@ -0,0 +1,410 @@
+//! Import of closed outlines from DXF drawings and export
+//! of the convex parts as closed POLYLINEs of an R12 drawing.
+use crate::error::{error, ParseError};
+use crate::geometry::distance;
+use crate::poly::Poly;
+use smart_default::SmartDefault;
+use std::f32::consts::PI;
+use std::io::{self, Write};
+
+#[derive(SmartDefault, Clone, Debug)]
+pub struct DxfImportOptions {
+    /// Only entities on this layer are imported; all layers if None
+    pub layer: Option<String>,
+    /// Bulge arcs are flattened into segments spanning at most this angle (radians)
+    #[default(PI / 16.0)]
+    pub max_arc_angle: f32,
+    /// Distance below which LINE endpoints are considered equal
+    #[default = 1e-4]
+    pub tolerance: f32,
+}
+
+#[derive(SmartDefault, Clone, Debug)]
+pub struct DxfExportOptions {
+    /// Layer of the polygon outline; the outline is not written if None
+    #[default(Some("POLYGON".to_string()))]
+    pub outline_layer: Option<String>,
+    #[default = "CONVEX_PARTS"]
+    pub part_layer: String,
+}
+
+/// Group code / value pair with the line number of the code.
+struct Group<'a> {
+    code: i32,
+    value: &'a str,
+    line: usize,
+}
+
+/// An entity of the ENTITIES section: its type and its groups.
+struct Entity<'a> {
+    kind: &'a str,
+    groups: Vec<Group<'a>>,
+}
+
+impl<'a> Entity<'a> {
+    fn layer(&self) -> &'a str {
+        self.groups
+            .iter()
+            .find(|g| g.code == 8)
+            .map_or("0", |g| g.value)
+    }
+
+    fn number(&self, code: i32) -> Result<Option<f32>, ParseError> {
+        match self.groups.iter().find(|g| g.code == code) {
+            Some(group) => parse_number(group).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn point(&self, x_code: i32) -> Result<[f32; 2], ParseError> {
+        let x = self.number(x_code)?;
+        let y = self.number(x_code + 10)?;
+        match (x, y) {
+            (Some(x), Some(y)) => Ok([x, y]),
+            _ => Err(error(
+                self.groups.first().map_or(0, |g| g.line),
+                &format!("{} without coordinates", self.kind),
+            )),
+        }
+    }
+
+    fn is_closed(&self) -> Result<bool, ParseError> {
+        Ok(matches!(self.number(70)?, Some(flags) if flags as i32 & 1 == 1))
+    }
+}
+
+fn parse_number(group: &Group<'_>) -> Result<f32, ParseError> {
+    group
+        .value
+        .parse()
+        .map_err(|_| error(group.line, &format!("invalid number {}", group.value)))
+}
+
+/// Splits the ENTITIES section (or the whole file if it has no sections)
+/// into entities.
+fn entities(text: &str) -> Result<Vec<Entity<'_>>, ParseError> {
+    let lines: Vec<&str> = text.lines().map(|l| l.trim()).collect();
+    if lines.len() % 2 == 1 {
+        return Err(error(lines.len(), "group code without value"));
+    }
+
+    let mut groups = Vec::with_capacity(lines.len() / 2);
+    for idx in (0..lines.len()).step_by(2) {
+        let code = lines[idx]
+            .parse()
+            .map_err(|_| error(idx + 1, &format!("invalid group code {}", lines[idx])))?;
+        groups.push(Group {
+            code,
+            value: lines[idx + 1],
+            line: idx + 1,
+        });
+    }
+
+    let has_sections = groups.iter().any(|g| g.code == 0 && g.value == "SECTION");
+    let mut in_entities = !has_sections;
+    let mut entities: Vec<Entity<'_>> = vec![];
+    for (idx, group) in groups.iter().enumerate() {
+        if group.code == 0 && group.value == "SECTION" {
+            in_entities =
+                matches!(groups.get(idx + 1), Some(g) if g.code == 2 && g.value == "ENTITIES");
+            continue;
+        }
+        if !in_entities || (group.code == 2 && group.value == "ENTITIES") {
+            continue;
+        }
+        if group.code == 0 {
+            if group.value == "ENDSEC" || group.value == "EOF" {
+                in_entities = !has_sections;
+                continue;
+            }
+            entities.push(Entity {
+                kind: group.value,
+                groups: vec![],
+            });
+        } else if let Some(entity) = entities.last_mut() {
+            entity.groups.push(Group {
+                code: group.code,
+                value: group.value,
+                line: group.line,
+            });
+        }
+    }
+    Ok(entities)
+}
+
+/// Appends the arc from `from` to `to` with the given bulge,
+/// excluding its end points, as a sequence of points.
+fn flatten_bulge(
+    ring: &mut Vec<[f32; 2]>,
+    from: [f32; 2],
+    to: [f32; 2],
+    bulge: f32,
+    max_angle: f32,
+) {
+    if bulge.abs() < 1e-9 {
+        return;
+    }
+    // the bulge is the tangent of a quarter of the included angle,
+    // positive if the arc goes ccw
+    let angle = 4.0 * bulge.atan();
+    let d = [to[0] - from[0], to[1] - from[1]];
+    let offset = (1.0 - bulge * bulge) / (4.0 * bulge);
+    let center = [
+        (from[0] + to[0]) / 2.0 - offset * d[1],
+        (from[1] + to[1]) / 2.0 + offset * d[0],
+    ];
+    let radius = ((from[0] - center[0]).powi(2) + (from[1] - center[1]).powi(2)).sqrt();
+    let start = (from[1] - center[1]).atan2(from[0] - center[0]);
+
+    let segments = (angle.abs() / max_angle.max(1e-3)).ceil().max(1.0) as usize;
+    for k in 1..segments {
+        let a = start + angle * k as f32 / segments as f32;
+        ring.push([center[0] + radius * a.cos(), center[1] + radius * a.sin()]);
+    }
+}
+
+/// Vertices and bulges to a ring of points, flattening the arcs.
+fn polyline_ring(points: &[([f32; 2], f32)], max_angle: f32) -> Vec<[f32; 2]> {
+    let mut ring = vec![];
+    for (idx, (point, bulge)) in points.iter().enumerate() {
+        ring.push(*point);
+        let next = points[(idx + 1) % points.len()].0;
+        flatten_bulge(&mut ring, *point, next, *bulge, max_angle);
+    }
+    ring
+}
+
+/// Chains LINE segments into closed loops; open chains are dropped.
+fn line_loops(mut segments: Vec<[[f32; 2]; 2]>, tolerance: f32) -> Vec<Vec<[f32; 2]>> {
+    let close = |a: [f32; 2], b: [f32; 2]| distance(a, b) <= f64::from(tolerance);
+    let mut loops = vec![];
+    while let Some(first) = segments.pop() {
+        let mut ring = vec![first[0]];
+        let mut end = first[1];
+        let mut closed = false;
+        loop {
+            if close(end, ring[0]) {
+                closed = true;
+                break;
+            }
+            ring.push(end);
+            let next = segments
+                .iter()
+                .position(|s| close(s[0], end) || close(s[1], end));
+            match next {
+                Some(idx) => {
+                    let segment = segments.swap_remove(idx);
+                    end = if close(segment[0], end) {
+                        segment[1]
+                    } else {
+                        segment[0]
+                    };
+                }
+                None => break,
+            }
+        }
+        if closed && ring.len() >= 3 {
+            loops.push(ring);
+        }
+    }
+    loops
+}
+
+/// Reads the closed LWPOLYLINEs and POLYLINEs and the closed loops of
+/// LINEs of a DXF drawing as polygons.  Open polylines are ignored.
+pub fn read_dxf(text: &str, options: &DxfImportOptions) -> Result<Vec<Poly>, ParseError> {
+    let entities = entities(text)?;
+    let on_layer = |entity: &Entity<'_>| match &options.layer {
+        Some(layer) => entity.layer() == layer,
+        None => true,
+    };
+
+    let mut rings: Vec<Vec<[f32; 2]>> = vec![];
+    let mut lines: Vec<[[f32; 2]; 2]> = vec![];
+
+    let mut idx = 0;
+    while idx < entities.len() {
+        let entity = &entities[idx];
+        idx += 1;
+        match entity.kind {
+            "LWPOLYLINE" if on_layer(entity) => {
+                if !entity.is_closed()? {
+                    continue;
+                }
+                // a bulge (42) refers to the vertex (10, 20) preceding it
+                let mut points: Vec<([f32; 2], f32)> = vec![];
+                let mut x = None;
+                for group in entity.groups.iter() {
+                    match group.code {
+                        10 => x = Some(parse_number(group)?),
+                        20 => {
+                            let x = x.take().ok_or_else(|| {
+                                error(group.line, "LWPOLYLINE vertex without x coordinate")
+                            })?;
+                            points.push(([x, parse_number(group)?], 0.0));
+                        }
+                        42 => {
+                            if let Some(last) = points.last_mut() {
+                                last.1 = parse_number(group)?;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                rings.push(polyline_ring(&points, options.max_arc_angle));
+            }
+            "POLYLINE" => {
+                // the vertices follow as VERTEX entities until SEQEND
+                let mut points: Vec<([f32; 2], f32)> = vec![];
+                while idx < entities.len() && entities[idx].kind == "VERTEX" {
+                    let vertex = &entities[idx];
+                    points.push((vertex.point(10)?, vertex.number(42)?.unwrap_or(0.0)));
+                    idx += 1;
+                }
+                if idx < entities.len() && entities[idx].kind == "SEQEND" {
+                    idx += 1;
+                }
+                if on_layer(entity) && entity.is_closed()? {
+                    rings.push(polyline_ring(&points, options.max_arc_angle));
+                }
+            }
+            "LINE" if on_layer(entity) => {
+                lines.push([entity.point(10)?, entity.point(11)?]);
+            }
+            _ => {}
+        }
+    }
+    rings.extend(line_loops(lines, options.tolerance));
+    let close = |a: [f32; 2], b: [f32; 2]| distance(a, b) <= f64::from(options.tolerance);
+
+    Ok(rings
+        .into_iter()
+        .map(|mut ring| {
+            // drop repeated points, including a closing point equal to the first one
+            ring.dedup_by(|a, b| close(*a, *b));
+            if ring.len() > 1 && close(ring[0], ring[ring.len() - 1]) {
+                ring.pop();
+            }
+            ring
+        })
+        .filter(|ring| ring.len() >= 3)
+        .map(|vertices| Poly {
+            vertices,
+            ..Default::default()
+        })
+        .collect())
+}
+
+/// Writes a closed R12 POLYLINE: the entity with the vertices-follow
+/// flag, a VERTEX per point and the closing SEQEND.
+fn write_polyline<W: Write>(writer: &mut W, layer: &str, points: &[[f32; 2]]) -> io::Result<()> {
+    writeln!(
+        writer,
+        "0\nPOLYLINE\n8\n{}\n66\n1\n10\n0.0\n20\n0.0\n30\n0.0\n70\n1",
+        layer
+    )?;
+    for p in points.iter() {
+        writeln!(
+            writer,
+            "0\nVERTEX\n8\n{}\n10\n{}\n20\n{}\n30\n0.0",
+            layer, p[0], p[1]
+        )?;
+    }
+    writeln!(writer, "0\nSEQEND\n8\n{}", layer)
+}
+
+/// Writes the outlines and convex parts of several polygons
+/// into one DXF drawing.  The drawing declares R12 (AC1009) in its
+/// header, the last version whose entities need no handles, so it only
+/// holds a HEADER and an ENTITIES section.
+pub fn write_dxf_polys<W: Write>(
+    polys: &[Poly],
+    mut writer: W,
+    options: &DxfExportOptions,
+) -> io::Result<()> {
+    writeln!(
+        writer,
+        "0\nSECTION\n2\nHEADER\n9\n$ACADVER\n1\nAC1009\n0\nENDSEC"
+    )?;
+    writeln!(writer, "0\nSECTION\n2\nENTITIES")?;
+    for poly in polys.iter() {
+        if let Some(layer) = &options.outline_layer {
+            write_polyline(&mut writer, layer, &poly.vertices)?;
+        }
+        for part in poly.convex_part_cycles().iter() {
+            let points: Vec<[f32; 2]> = part.iter().map(|p| [p.x, p.y]).collect();
+            write_polyline(&mut writer, &options.part_layer, &points)?;
+        }
+    }
+    writeln!(writer, "0\nENDSEC\n0\nEOF")
+}
+
+impl Poly {
+    /// Writes the polygon outline and its convex parts as closed
+    /// POLYLINEs of a minimal R12 DXF drawing.
+    pub fn write_dxf<W: Write>(&self, writer: W, options: &DxfExportOptions) -> io::Result<()> {
+        write_dxf_polys(std::slice::from_ref(self), writer, options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::beak_poly;
+
+    #[test]
+    fn test_lwpolyline_with_bulge() {
+        // unit square whose top edge is replaced by a half circle
+        let text = "0\nSECTION\n2\nENTITIES\n\
+                    0\nLWPOLYLINE\n8\nOUTLINE\n90\n4\n70\n1\n\
+                    10\n0.0\n20\n0.0\n10\n2.0\n20\n0.0\n10\n2.0\n20\n1.0\n42\n1.0\n10\n0.0\n20\n1.0\n\
+                    0\nLWPOLYLINE\n8\nOTHER\n90\n3\n70\n1\n10\n0\n20\n0\n10\n1\n20\n0\n10\n0\n20\n1\n\
+                    0\nENDSEC\n0\nEOF\n";
+        let options = DxfImportOptions {
+            layer: Some("OUTLINE".to_string()),
+            max_arc_angle: PI / 4.0,
+            ..Default::default()
+        };
+        let polys = read_dxf(text, &options).unwrap();
+        assert_eq!(polys.len(), 1);
+        // 4 vertices + 3 points on the arc
+        let vertices = &polys[0].vertices;
+        assert_eq!(vertices.len(), 7);
+        // the arc bulges upwards to y = 2
+        let top = vertices[4];
+        assert!((top[0] - 1.0).abs() < 1e-5 && (top[1] - 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_line_loop_and_polyline() {
+        let text = "0\nLINE\n8\n0\n10\n0\n20\n0\n11\n4\n21\n0\n\
+                    0\nLINE\n8\n0\n10\n0\n20\n3\n11\n4\n21\n0\n\
+                    0\nLINE\n8\n0\n10\n0\n20\n3\n11\n0\n21\n0\n\
+                    0\nPOLYLINE\n8\n0\n70\n1\n\
+                    0\nVERTEX\n10\n10\n20\n10\n0\nVERTEX\n10\n12\n20\n10\n0\nVERTEX\n10\n11\n20\n12\n\
+                    0\nSEQEND\n";
+        let polys = read_dxf(text, &DxfImportOptions::default()).unwrap();
+        assert_eq!(polys.len(), 2);
+        assert!(polys.iter().all(|p| p.vertices.len() == 3));
+    }
+
+    #[test]
+    fn test_export_and_import_parts() {
+        let mut dxf = Vec::new();
+        beak_poly()
+            .write_dxf(&mut dxf, &DxfExportOptions::default())
+            .unwrap();
+        let dxf = String::from_utf8(dxf).unwrap();
+        assert!(dxf.starts_with("0\nSECTION\n2\nHEADER\n9\n$ACADVER\n1\nAC1009\n"));
+        assert!(!dxf.contains("LWPOLYLINE"));
+        // the outline and two parts
+        assert_eq!(dxf.matches("SEQEND").count(), 3);
+
+        let options = DxfImportOptions {
+            layer: Some("CONVEX_PARTS".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(read_dxf(&dxf, &options).unwrap().len(), 2);
+        assert!(read_dxf("0\nLINE\n10\nx\n", &DxfImportOptions::default()).is_err());
+    }
+}
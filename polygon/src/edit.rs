@@ -0,0 +1,833 @@
+//! Editing a triangulated polygon in place.  Inserting, moving and
+//! removing a vertex redo the labeling and gluing of the decomposition
+//! only for the convex parts affected by the edit.
+//!
+//! The constrained Delaunay triangulation is edited locally as well.  A
+//! vertex that moves within the triangles around it keeps them, as long
+//! as their edges stay Delaunay.  Otherwise the edit removes a vertex
+//! from the triangulation and inserts the vertices again; spade cannot
+//! remove a single constraint, so an insertion removes a neighbour of
+//! the new vertex, which takes the edge between the neighbours along.
+//! That costs time quadratic in the degree of the removed vertex, plus
+//! linear time to shift the handles of the positions.  spade only
+//! removes vertices that are off the convex hull and whose hole it can
+//! fill from a fan, and does not check the edges around the hole; the
+//! triangulation is built again, in O(n log n) time, for the other
+//! edits, about a third of them on random polygons.
+//!
+//! Positions refer to the vertices field, which triang reverses if the
+//! vertices were given in cw order.  Edits keep the orientation: ccw
+//! once triangulated, the given one before.
+use crate::error::PolyError;
+use crate::geometry::{orientation, segments_cross, segments_intersect};
+use crate::poly::{Poly, ESSENTIAL, NON_ESSENTIAL, OUTSIDE, POLY_EDGE};
+use cgmath::Point2;
+use spade::delaunay::{FixedFaceHandle, FixedVertexHandle};
+use spade::kernels::{DelaunayKernel, FloatKernel};
+use spade::primitives::SimpleEdge;
+use std::collections::HashMap;
+
+/// Convex parts replaced by an edit.  The other parts keep their
+/// vertices, although their indices may shift.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PartChanges {
+    /// Indices in convex_parts before the edit of the replaced parts
+    pub removed: Vec<usize>,
+    /// Indices in convex_parts after the edit of the new parts
+    pub added: Vec<usize>,
+}
+
+impl Poly {
+    /// Inserts a vertex before the one at the given position, or after
+    /// the last one if the position is the number of vertices.
+    ///
+    /// # Panics
+    /// Panics if the position is greater than the number of vertices.
+    pub fn insert_vertex(
+        &mut self,
+        position: usize,
+        point: [f32; 2],
+    ) -> Result<PartChanges, PolyError> {
+        assert!(position <= self.vertices.len(), "position out of bounds");
+        if !point[0].is_finite() || !point[1].is_finite() {
+            return Err(PolyError::NonFiniteVertex(position));
+        }
+        if self.vertices.contains(&point) {
+            return Err(PolyError::DuplicateVertex(position));
+        }
+
+        let triangulated = self.triangulated();
+        let ccw = self.ccw(triangulated);
+        self.vertices.insert(position, point);
+        let length = self.vertices.len();
+        let previous = (position + length - 1) % length;
+        if let Err(error) = self.check_edges(&[previous, position], ccw) {
+            self.vertices.remove(position);
+            return Err(error);
+        }
+        if !triangulated {
+            return Ok(PartChanges::default());
+        }
+
+        let next = (position + 1) % length;
+        let before_previous = (previous + length - 1) % length;
+        self.handles.insert(position, usize::MAX);
+        for shifted in self.positions.iter_mut().filter(|p| **p >= position) {
+            *shifted += 1;
+        }
+        // the edge between the neighbours goes along with the
+        // constraints of either of them
+        let local = self.retriangulate(
+            self.handles[previous],
+            &[previous, position],
+            &[before_previous, previous, position],
+        ) || self.retriangulate(
+            self.handles[next],
+            &[position, next],
+            &[previous, position, next],
+        );
+        if !local {
+            self.initialise_triangulation();
+        }
+        Ok(self.update_decomposition(&[previous, position, next]))
+    }
+
+    /// Moves the vertex at the given position.
+    ///
+    /// # Panics
+    /// Panics if there is no vertex at the position.
+    pub fn move_vertex(
+        &mut self,
+        position: usize,
+        point: [f32; 2],
+    ) -> Result<PartChanges, PolyError> {
+        if !point[0].is_finite() || !point[1].is_finite() {
+            return Err(PolyError::NonFiniteVertex(position));
+        }
+        let old_point = self.vertices[position];
+        if old_point == point {
+            return Ok(PartChanges::default());
+        }
+        if self.vertices.contains(&point) {
+            return Err(PolyError::DuplicateVertex(position));
+        }
+
+        let triangulated = self.triangulated();
+        let ccw = self.ccw(triangulated);
+        self.vertices[position] = point;
+        let length = self.vertices.len();
+        let previous = (position + length - 1) % length;
+        if let Err(error) = self.check_edges(&[previous, position], ccw) {
+            self.vertices[position] = old_point;
+            return Err(error);
+        }
+        if !triangulated {
+            return Ok(PartChanges::default());
+        }
+
+        let next = (position + 1) % length;
+        if !self.move_in_triangulation(position, point)
+            && !self.retriangulate(self.handles[position], &[position], &[previous, position])
+        {
+            self.initialise_triangulation();
+        }
+        Ok(self.update_decomposition(&[previous, position, next]))
+    }
+
+    /// Removes the vertex at the given position.
+    ///
+    /// # Panics
+    /// Panics if there is no vertex at the position.
+    pub fn remove_vertex(&mut self, position: usize) -> Result<PartChanges, PolyError> {
+        let length = self.vertices.len() - 1;
+        if length < 3 {
+            return Err(PolyError::TooFewVertices(length));
+        }
+
+        let triangulated = self.triangulated();
+        let ccw = self.ccw(triangulated);
+        let old_point = self.vertices.remove(position);
+        let previous = (position + length - 1) % length;
+        if let Err(error) = self.check_edges(&[previous], ccw) {
+            self.vertices.insert(position, old_point);
+            return Err(error);
+        }
+        if !triangulated {
+            return Ok(PartChanges::default());
+        }
+
+        let next = position % length;
+        let handle = self.handles.remove(position);
+        for shifted in self.positions.iter_mut().filter(|p| **p > position) {
+            *shifted -= 1;
+        }
+        if !self.retriangulate(handle, &[], &[previous]) {
+            self.initialise_triangulation();
+        }
+        Ok(self.update_decomposition(&[previous, next]))
+    }
+
+    /// True if triang ran on the current vertices, i.e. before the edit
+    fn triangulated(&self) -> bool {
+        self.triangulation.num_vertices() > 0
+            && self.triangulation.num_vertices() == self.vertices.len()
+    }
+
+    /// True if the vertices must stay in ccw order: triang puts them in
+    /// that order, before it the edits keep the given one
+    fn ccw(&self, triangulated: bool) -> bool {
+        triangulated || self.doubled_area() > 0.0
+    }
+
+    /// Edits the triangulation locally: removes the vertex with the
+    /// given handle along with its constraints, inserts the vertices at
+    /// the given positions and adds the polygon edges starting at the
+    /// given positions as constraints.  Returns false, with the
+    /// triangulation unchanged, if spade cannot remove the vertex, see
+    /// fan_hole.
+    ///
+    /// spade does not check the edges around the hole of a removed
+    /// vertex, which its dropped constraints may expose to other
+    /// vertices.  The triangulation is built again if one of them is
+    /// not Delaunay in the end.
+    fn retriangulate(
+        &mut self,
+        removed: FixedVertexHandle,
+        inserted: &[usize],
+        starts: &[usize],
+    ) -> bool {
+        if !self.fan_hole(removed) {
+            return false;
+        }
+        let neighbours = self.remove_handle(removed);
+        for &position in inserted {
+            self.insert_handle(position);
+        }
+        let length = self.vertices.len();
+        let mut around = neighbours;
+        for &start in starts {
+            let (from, to) = (self.handles[start], self.handles[(start + 1) % length]);
+            self.triangulation.add_constraint(from, to);
+            around.extend([from, to]);
+        }
+        if !around.iter().all(|handle| self.legal_around(*handle)) {
+            self.initialise_triangulation();
+        }
+        true
+    }
+
+    /// True if spade can remove the vertex: it is off the convex hull,
+    /// where removing it can loop forever, and the fan from its first
+    /// neighbour, which spade fills its hole with before flipping the
+    /// illegal edges, has no inverted triangles.
+    fn fan_hole(&self, handle: FixedVertexHandle) -> bool {
+        let infinite = self.triangulation.infinite_face().fix();
+        let vertex = self.triangulation.vertex(handle);
+        if vertex
+            .ccw_out_edges()
+            .any(|spoke| spoke.face().fix() == infinite)
+        {
+            return false;
+        }
+        let neighbours: Vec<Point2<f32>> =
+            vertex.ccw_out_edges().map(|spoke| *spoke.to()).collect();
+        let length = neighbours.len();
+        (1..length - 1).all(|idx| {
+            let edge = SimpleEdge::new(neighbours[0], neighbours[idx]);
+            FloatKernel::side_query(&edge, &neighbours[idx + 1]).is_on_left_side()
+        })
+    }
+
+    /// Removes the vertex with the given handle from the triangulation
+    /// along with its constraints and returns the handles of its
+    /// neighbours.  spade moves the vertex with the last handle to the
+    /// freed one.  The position of the removed vertex keeps its stale
+    /// handle.
+    fn remove_handle(&mut self, handle: FixedVertexHandle) -> Vec<FixedVertexHandle> {
+        let last = self.positions.len() - 1;
+        let neighbours = self
+            .triangulation
+            .vertex(handle)
+            .ccw_out_edges()
+            .map(|edge| match edge.to().fix() {
+                neighbour if neighbour == last => handle,
+                neighbour => neighbour,
+            })
+            .collect();
+        self.triangulation.remove(handle);
+        self.positions.swap_remove(handle);
+        if handle < self.positions.len() {
+            self.handles[self.positions[handle]] = handle;
+        }
+        neighbours
+    }
+
+    /// Inserts the vertex at the given position into the triangulation,
+    /// without constraints
+    fn insert_handle(&mut self, position: usize) {
+        let [x, y] = self.vertices[position];
+        let handle = self.triangulation.insert(Point2::new(x, y));
+        debug_assert_eq!(handle, self.positions.len());
+        self.positions.push(position);
+        self.handles[position] = handle;
+    }
+
+    /// Moves the vertex at the given position in the triangulation
+    /// without building it again.  The triangles around the vertex stay
+    /// as they are, so this is only done if the vertex is off the convex
+    /// hull, the triangles keep their ccw orientation and their edges
+    /// stay Delaunay.  Returns false, with the triangulation unchanged,
+    /// otherwise.
+    fn move_in_triangulation(&mut self, position: usize, point: [f32; 2]) -> bool {
+        let handle = self.handles[position];
+        let old_point = *self.triangulation.vertex(handle);
+        *self.triangulation.vertex_mut(handle) = Point2::new(point[0], point[1]);
+        let moved = self.delaunay_around(handle);
+        if !moved {
+            *self.triangulation.vertex_mut(handle) = old_point;
+        }
+        moved
+    }
+
+    /// True if the vertex is off the convex hull and the triangles
+    /// around it are in ccw order with locally Delaunay edges
+    fn delaunay_around(&self, handle: FixedVertexHandle) -> bool {
+        let infinite = self.triangulation.infinite_face().fix();
+        self.triangulation
+            .vertex(handle)
+            .ccw_out_edges()
+            .all(|spoke| spoke.face().fix() != infinite)
+            && self.legal_around(handle)
+    }
+
+    /// True if the triangles around the vertex are in ccw order with
+    /// locally Delaunay edges
+    fn legal_around(&self, handle: FixedVertexHandle) -> bool {
+        let infinite = self.triangulation.infinite_face().fix();
+        for spoke in self.triangulation.vertex(handle).ccw_out_edges() {
+            let face = spoke.face();
+            if face.fix() == infinite {
+                continue;
+            }
+            let edge = SimpleEdge::new(*spoke.from(), *spoke.to());
+            if !FloatKernel::side_query(&edge, &*spoke.o_next().to()).is_on_left_side() {
+                return false;
+            }
+            // the edge is illegal if the vertex across it lies in the
+            // circumcircle of the triangle, given in cw order
+            for edge in face.adjacent_edges() {
+                if edge.sym().face().fix() == infinite
+                    || self.triangulation.is_constraint_edge(edge.fix())
+                {
+                    continue;
+                }
+                let (a, b) = (*edge.from(), *edge.to());
+                let (c, d) = (*edge.o_next().to(), *edge.sym().o_next().to());
+                if FloatKernel::contained_in_circumference(&a, &c, &b, &d) {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// Checks that the edges starting at the given positions meet the
+    /// other edges only at their common vertices and that the polygon
+    /// is in ccw order if ccw is true, in cw order otherwise.
+    fn check_edges(&self, starts: &[usize], ccw: bool) -> Result<(), PolyError> {
+        let length = self.vertices.len();
+        let point = |idx: usize| {
+            let v = self.vertices[idx % length];
+            [v[0] as f64, v[1] as f64]
+        };
+        // adjacent edges ab and bc overlap if c lies on the ray from b through a
+        let overlapping = |a: [f64; 2], b: [f64; 2], c: [f64; 2]| {
+            orientation(a, b, c) == 0.0
+                && (a[0] - b[0]) * (c[0] - b[0]) + (a[1] - b[1]) * (c[1] - b[1]) > 0.0
+        };
+
+        for &start in starts {
+            let (a, b) = (point(start), point(start + 1));
+            for other in 0..length {
+                let (c, d) = (point(other), point(other + 1));
+                if other == start {
+                    continue;
+                } else if other == (start + 1) % length {
+                    if overlapping(a, b, d) {
+                        return Err(PolyError::TouchingEdges);
+                    }
+                } else if (other + 1) % length == start {
+                    if overlapping(b, a, c) {
+                        return Err(PolyError::TouchingEdges);
+                    }
+                } else if segments_intersect(a, b, c, d) {
+                    return Err(if segments_cross(a, b, c, d) {
+                        PolyError::IntersectingEdges(start)
+                    } else {
+                        PolyError::TouchingEdges
+                    });
+                }
+            }
+        }
+
+        let area = self.doubled_area();
+        if area == 0.0 {
+            Err(PolyError::ZeroArea)
+        } else if ccw && area < 0.0 {
+            Err(PolyError::Clockwise)
+        } else if !ccw && area > 0.0 {
+            Err(PolyError::Counterclockwise)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Collects the triangles of the edited triangulation and, if the
+    /// polygon was decomposed, updates the convex parts.  The angles at
+    /// the vertices at the changed positions may have changed.
+    ///
+    /// The parts that contain a changed vertex or whose triangles
+    /// changed form the region that is decomposed again.  The region
+    /// grows by a neighbouring part whenever the diagonal between them
+    /// is no longer essential.
+    fn update_decomposition(&mut self, changed: &[usize]) -> PartChanges {
+        self.collect_triangles();
+        if self.convex_parts.is_empty() {
+            return PartChanges::default();
+        }
+
+        let changed: Vec<FixedVertexHandle> = changed.iter().map(|p| self.handles[*p]).collect();
+        let key = |v: [f32; 2]| [(v[0] + 0.0).to_bits(), (v[1] + 0.0).to_bits()];
+        let handle_of: HashMap<[u32; 2], FixedVertexHandle> = self
+            .vertices
+            .iter()
+            .zip(self.handles.iter())
+            .map(|(v, handle)| (key(*v), *handle))
+            .collect();
+
+        // handles of the vertices of the old parts, None for a part
+        // with a vertex that was moved or removed or a Steiner point, so
+        // the refined parts are all replaced along with their points
+        let old_parts = std::mem::take(&mut self.convex_parts);
+        self.steiner_points.clear();
+        let cycles: Vec<Option<Vec<usize>>> = old_parts
+            .iter()
+            .map(|part| {
+                part.iter()
+                    .step_by(2)
+                    .map(|p| handle_of.get(&key([p.x, p.y])).cloned())
+                    .collect()
+            })
+            .collect();
+        let mut affected: Vec<bool> = cycles
+            .iter()
+            .map(|cycle| match cycle {
+                Some(cycle) => cycle.iter().any(|p| changed.contains(p)),
+                None => true,
+            })
+            .collect();
+
+        let num_faces = self.triangulation.num_faces();
+        let (region, edge_labels, essentials, region_parts) = loop {
+            // faces of the parts that are still tiled by their own triangles
+            let mut owner: Vec<Option<usize>> = vec![None; num_faces];
+            let mut mark: Vec<usize> = vec![0; self.vertices.len()];
+            for (idx, cycle) in cycles.iter().enumerate() {
+                if affected[idx] {
+                    continue;
+                }
+                let cycle = cycle.as_ref().unwrap();
+                let faces = self.faces_within(cycle, idx + 1, &mut mark);
+                if faces.len() + 2 == cycle.len() {
+                    for face in faces {
+                        owner[face] = Some(idx);
+                    }
+                } else {
+                    affected[idx] = true;
+                }
+            }
+
+            let region: Vec<FixedFaceHandle> = self
+                .triangulation
+                .triangles()
+                .map(|face| face.fix())
+                .filter(|face| owner[*face].is_none() && self.inside(*face))
+                .collect();
+            let mut in_region = vec![false; num_faces];
+            for face in region.iter() {
+                in_region[*face] = true;
+            }
+
+            // the boundary of the region acts as the polygon boundary
+            let mut edge_labels = vec![OUTSIDE; 2 * self.triangulation.num_edges()];
+            let mut vertices: Vec<FixedVertexHandle> = vec![];
+            for face in region.iter() {
+                for edge in self.triangulation.face(*face).adjacent_edges() {
+                    let label = if self.triangulation.is_constraint_edge(edge.fix())
+                        || !in_region[edge.sym().face().fix()]
+                    {
+                        POLY_EDGE
+                    } else {
+                        NON_ESSENTIAL
+                    };
+                    edge_labels[edge.fix()] = label;
+                    edge_labels[edge.sym().fix()] = label;
+                    vertices.push(edge.from().fix());
+                }
+            }
+            vertices.sort_unstable();
+            vertices.dedup();
+
+            let essentials = self.find_essentials(&vertices, &mut edge_labels);
+            self.remove_inessential(&vertices, &mut edge_labels);
+            let region_parts = self.glue_faces(&region, &edge_labels);
+
+            // diagonals on the boundary of the region that are no longer essential
+            let mut part_of_edge: HashMap<[usize; 2], usize> = HashMap::new();
+            for (idx, part) in region_parts.iter().enumerate() {
+                for edge in part.iter() {
+                    part_of_edge.insert(*edge, idx);
+                }
+            }
+            let mut grown = false;
+            for face in region.iter() {
+                for edge in self.triangulation.face(*face).adjacent_edges() {
+                    if self.triangulation.is_constraint_edge(edge.fix()) {
+                        continue;
+                    }
+                    let neighbour = match owner[edge.sym().face().fix()] {
+                        Some(neighbour) if !affected[neighbour] => neighbour,
+                        _ => continue,
+                    };
+                    let (x, y) = (edge.from().fix(), edge.to().fix());
+                    let part = &region_parts[part_of_edge[&[x, y]]];
+                    let cycle = cycles[neighbour].as_ref().unwrap();
+                    if self.removable([x, y], part, cycle) {
+                        affected[neighbour] = true;
+                        grown = true;
+                    }
+                }
+            }
+            if !grown {
+                break (in_region, edge_labels, essentials, region_parts);
+            }
+        };
+
+        // essential diagonals: the old ones outside the region and the new ones
+        let old_diagonals = std::mem::take(&mut self.essential_diagonals);
+        for diagonal in old_diagonals {
+            let ends: Option<Vec<FixedVertexHandle>> = diagonal
+                .iter()
+                .map(|p| handle_of.get(&key([p[0], -p[1]])).cloned())
+                .collect();
+            // the diagonal may have become a polygon edge
+            let edge = ends.and_then(|ends| {
+                self.triangulation
+                    .get_edge_from_neighbors(ends[0], ends[1])
+                    .filter(|edge| !self.triangulation.is_constraint_edge(edge.fix()))
+                    .map(|edge| (edge.face().fix(), edge.sym().face().fix()))
+            });
+            if let Some((left, right)) = edge {
+                if !region[left] || !region[right] {
+                    self.essential_diagonals.push(diagonal);
+                }
+            }
+        }
+        for fixed_edge in essentials {
+            if edge_labels[fixed_edge] == ESSENTIAL {
+                let edge = self.triangulation.edge(fixed_edge);
+                let (from, to) = (*edge.from(), *edge.to());
+                self.essential_diagonals
+                    .push(vec![[from.x, -from.y], [to.x, -to.y]]);
+            }
+        }
+
+        // merge the kept and the new parts in canonical order; the
+        // flag marks the new ones
+        let mut parts: Vec<([usize; 2], bool, Vec<Point2<f32>>)> = vec![];
+        let mut changes = PartChanges::default();
+        for (idx, part) in old_parts.into_iter().enumerate() {
+            if affected[idx] {
+                changes.removed.push(idx);
+            } else {
+                let cycle = cycles[idx].as_ref().unwrap();
+                let first = [self.positions[cycle[0]], self.positions[cycle[1]]];
+                parts.push((first, false, part));
+            }
+        }
+        for part in region_parts.iter() {
+            let first = [self.positions[part[0][0]], self.positions[part[0][1]]];
+            parts.push((first, true, self.vertex_ordering(part)));
+        }
+        parts.sort_by_key(|part| part.0);
+        for (idx, (_, new, part)) in parts.into_iter().enumerate() {
+            if new {
+                changes.added.push(idx);
+            }
+            self.convex_parts.push(part);
+        }
+        changes
+    }
+
+    /// Faces of the triangulation whose vertices all belong to the
+    /// cycle of handles.  mark must hold no id for the cycle yet.
+    fn faces_within(&self, cycle: &[usize], id: usize, mark: &mut [usize]) -> Vec<FixedFaceHandle> {
+        for handle in cycle.iter() {
+            mark[*handle] = id;
+        }
+        let mut faces = vec![];
+        for &handle in cycle.iter() {
+            for edge in self.triangulation.vertex(handle).ccw_out_edges() {
+                let face = edge.face();
+                if face.fix() == self.triangulation.infinite_face().fix() {
+                    continue;
+                }
+                // every face is found from each of its vertices, keep it once
+                let corners: Vec<FixedVertexHandle> =
+                    face.adjacent_edges().map(|e| e.from().fix()).collect();
+                if corners.iter().all(|v| mark[*v] == id) && corners.iter().all(|v| *v >= handle) {
+                    faces.push(face.fix());
+                }
+            }
+        }
+        faces
+    }
+
+    /// True if the part of the region on the left of the edge xy and
+    /// the old part on its right form a convex polygon together.
+    fn removable(&self, edge: [usize; 2], part: &[[usize; 2]], cycle: &[usize]) -> bool {
+        let [x, y] = edge;
+        let point = |handle: usize| *self.triangulation.vertex(handle);
+        let part_previous = part.iter().find(|e| e[1] == x).unwrap()[0];
+        let part_next = part.iter().find(|e| e[0] == y).unwrap()[1];
+        let at = cycle.iter().position(|p| *p == x).unwrap();
+        let cycle_next = cycle[(at + 1) % cycle.len()];
+        let cycle_previous = cycle[(at + cycle.len() - 2) % cycle.len()];
+        debug_assert_eq!(cycle[(at + cycle.len() - 1) % cycle.len()], y);
+
+        self.convex_angle(point(x), point(part_previous), point(cycle_next))
+            && self.convex_angle(point(y), point(cycle_previous), point(part_next))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{random_polygon, Method};
+    use crate::test_util::decomposed;
+    use rand::{Rng, SeedableRng};
+    use rand_pcg::Pcg64;
+
+    /// Triangles as sorted triples of vertex coordinates
+    fn triangle_set(poly: &Poly) -> Vec<[[u32; 2]; 3]> {
+        let mut triangles: Vec<[[u32; 2]; 3]> = poly
+            .triangles
+            .iter()
+            .map(|t| {
+                let mut corners = [0, 1, 2].map(|idx| [t[idx].x.to_bits(), t[idx].y.to_bits()]);
+                corners.sort_unstable();
+                corners
+            })
+            .collect();
+        triangles.sort_unstable();
+        triangles
+    }
+
+    #[test]
+    fn test_edits_keep_a_valid_decomposition() {
+        let mut rng = Pcg64::seed_from_u64(1);
+        for method in [Method::Star, Method::SpacePartition, Method::Comb] {
+            let mut poly = decomposed(random_polygon(method, 40, 2));
+            let mut applied = 0;
+            for _ in 0..200 {
+                let before = poly.convex_parts.clone();
+                let position = rng.gen_range(0..poly.vertices.len());
+                let point = [rng.gen_range(0.0..1.0), rng.gen_range(0.0..1.0)];
+                let result = match rng.gen_range(0..3) {
+                    0 => poly.insert_vertex(position, point),
+                    1 => poly.move_vertex(position, point),
+                    _ => poly.remove_vertex(position),
+                };
+                let changes = match result {
+                    Ok(changes) => changes,
+                    Err(_) => {
+                        assert_eq!(poly.convex_parts, before);
+                        continue;
+                    }
+                };
+                applied += 1;
+
+                assert_eq!(poly.check_simple(), Ok(()));
+                assert_eq!(poly.triangles.len(), poly.vertices.len() - 2);
+                assert_eq!(poly.check_decomposition(), Ok(()), "{}", method);
+
+                // the parts that were not replaced are kept as they were
+                let kept: Vec<&Vec<Point2<f32>>> = (0..before.len())
+                    .filter(|idx| !changes.removed.contains(idx))
+                    .map(|idx| &before[idx])
+                    .collect();
+                let unchanged: Vec<&Vec<Point2<f32>>> = (0..poly.convex_parts.len())
+                    .filter(|idx| !changes.added.contains(idx))
+                    .map(|idx| &poly.convex_parts[idx])
+                    .collect();
+                assert_eq!(kept, unchanged);
+            }
+            assert!(applied > 50, "{} {}", method, applied);
+        }
+    }
+
+    #[test]
+    fn test_moves_match_a_new_triangulation() {
+        // the notch vertex is off the convex hull and can move within its
+        // triangles, the corners of the square are on the hull
+        let notched = vec![[0.0, 0.0], [2.0, 1.0], [4.0, 0.0], [4.0, 4.0], [0.0, 4.0]];
+        assert!(decomposed(notched.clone()).move_in_triangulation(1, [2.0, 1.5]));
+        assert!(!decomposed(notched).move_in_triangulation(0, [0.5, 0.0]));
+
+        let mut rng = Pcg64::seed_from_u64(3);
+        let mut poly = decomposed(random_polygon(Method::SpacePartition, 60, 5));
+        let mut moved = 0;
+        for _ in 0..200 {
+            let position = rng.gen_range(0..poly.vertices.len());
+            let [x, y] = poly.vertices[position];
+            let point = [
+                x + rng.gen_range(-0.01..0.01),
+                y + rng.gen_range(-0.01..0.01),
+            ];
+            if poly.move_vertex(position, point).is_err() {
+                continue;
+            }
+            moved += 1;
+            let rebuilt = decomposed(poly.vertices.clone());
+            assert_eq!(triangle_set(&poly), triangle_set(&rebuilt));
+            assert_eq!(poly.check_decomposition(), Ok(()));
+        }
+        assert!(moved > 100, "{}", moved);
+    }
+
+    #[test]
+    fn test_edits_match_a_new_triangulation() {
+        let mut rng = Pcg64::seed_from_u64(4);
+        let mut poly = decomposed(random_polygon(Method::Star, 30, 6));
+        let (mut applied, mut local) = (0, 0);
+        for _ in 0..300 {
+            let position = rng.gen_range(0..poly.vertices.len());
+            let point = [rng.gen_range(0.0..1.0), rng.gen_range(0.0..1.0)];
+            let result = match rng.gen_range(0..3) {
+                0 => poly.insert_vertex(position, point),
+                1 => poly.move_vertex(position, point),
+                _ => poly.remove_vertex(position),
+            };
+            if result.is_err() {
+                continue;
+            }
+            applied += 1;
+            if poly.handles.iter().enumerate().any(|(p, h)| p != *h) {
+                local += 1;
+            }
+            let rebuilt = decomposed(poly.vertices.clone());
+            assert_eq!(triangle_set(&poly), triangle_set(&rebuilt));
+            assert_eq!(poly.triangulation.num_constraints(), poly.vertices.len());
+            assert_eq!(poly.check_decomposition(), Ok(()));
+        }
+        assert!(applied > 50, "{}", applied);
+        // a local edit leaves the handles out of order, a new
+        // triangulation numbers them in order
+        assert!(local > 20, "{}", local);
+
+        // the triangulation of a triangle loses all its triangles while
+        // a vertex is out
+        let triangle = vec![[0.0, 0.0], [4.0, 0.0], [0.0, 4.0]];
+        let mut poly = decomposed(triangle.clone());
+        poly.insert_vertex(1, [2.0, 1.0]).unwrap();
+        assert_eq!(
+            triangle_set(&poly),
+            triangle_set(&decomposed(poly.vertices.clone()))
+        );
+        poly.remove_vertex(1).unwrap();
+        assert_eq!(triangle_set(&poly), triangle_set(&decomposed(triangle)));
+        poly.move_vertex(0, [-1.0, -1.0]).unwrap();
+        assert_eq!(
+            triangle_set(&poly),
+            triangle_set(&decomposed(poly.vertices.clone()))
+        );
+        assert_eq!(poly.check_decomposition(), Ok(()));
+    }
+
+    #[test]
+    fn test_edit_matches_decomposition_of_a_convex_polygon() {
+        let square = vec![[0.0, 0.0], [4.0, 0.0], [4.0, 4.0], [0.0, 4.0]];
+        let mut poly = decomposed(square);
+        assert_eq!(poly.convex_parts.len(), 1);
+
+        // a notch in the bottom edge makes the square non-convex
+        let changes = poly.insert_vertex(1, [2.0, 1.0]).unwrap();
+        assert_eq!(changes.removed, vec![0]);
+        assert_eq!(poly.convex_parts.len(), 2);
+        assert_eq!(changes.added, vec![0, 1]);
+        assert_eq!(poly.essential_diagonals.len(), 1);
+
+        // and flattening it again leaves a single part
+        poly.move_vertex(1, [2.0, -1.0]).unwrap();
+        assert_eq!(poly.convex_parts.len(), 1);
+        assert!(poly.essential_diagonals.is_empty());
+        poly.remove_vertex(1).unwrap();
+        assert_eq!(poly.convex_parts.len(), 1);
+        assert_eq!(poly.check_decomposition(), Ok(()));
+    }
+
+    #[test]
+    fn test_rejected_edits() {
+        let square = vec![[0.0, 0.0], [4.0, 0.0], [4.0, 4.0], [0.0, 4.0]];
+        let mut poly = decomposed(square.clone());
+
+        assert_eq!(
+            poly.move_vertex(0, [5.0, 2.0]),
+            Err(PolyError::IntersectingEdges(3))
+        );
+        assert_eq!(
+            poly.insert_vertex(1, [4.0, 4.0]),
+            Err(PolyError::DuplicateVertex(1))
+        );
+        assert_eq!(
+            poly.insert_vertex(1, [4.0, 2.0]),
+            Err(PolyError::TouchingEdges)
+        );
+        assert_eq!(poly.move_vertex(2, [-2.0, -2.0]), Err(PolyError::Clockwise));
+        assert_eq!(poly.vertices, square);
+
+        poly.remove_vertex(0).unwrap();
+        assert_eq!(poly.remove_vertex(0), Err(PolyError::TooFewVertices(2)));
+    }
+
+    #[test]
+    fn test_edits_keep_cw_order_before_triang() {
+        let square = vec![[0.0, 0.0], [0.0, 4.0], [4.0, 4.0], [4.0, 0.0]];
+        let mut poly = Poly {
+            vertices: square,
+            ..Default::default()
+        };
+
+        // a notch in the left edge
+        assert_eq!(
+            poly.insert_vertex(1, [1.0, 2.0]),
+            Ok(PartChanges::default())
+        );
+        poly.move_vertex(1, [2.0, 2.0]).unwrap();
+        poly.remove_vertex(0).unwrap();
+        assert!(poly.doubled_area() < 0.0);
+        assert_eq!(
+            poly.move_vertex(0, [8.0, 8.0]),
+            Err(PolyError::Counterclockwise)
+        );
+        assert_eq!(
+            poly.vertices,
+            vec![[2.0, 2.0], [0.0, 4.0], [4.0, 4.0], [4.0, 0.0]]
+        );
+
+        // triang reverses the vertices, the edits keep them in ccw order then
+        poly.triang();
+        assert!(poly.doubled_area() > 0.0);
+        assert!(poly.insert_vertex(0, [3.0, 3.0]).is_ok());
+    }
+}
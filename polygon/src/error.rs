@@ -0,0 +1,160 @@
+use std::fmt;
+
+/// Error of the readers of the file formats supported by the crate.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    /// 1-based line number, 0 if the error is not tied to a line
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.line > 0 {
+            write!(f, "line {}: {}", self.line, self.message)
+        } else {
+            write!(f, "{}", self.message)
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// ParseError at the 1-based line, 0 if it is not tied to a line
+pub fn error(line: usize, message: &str) -> ParseError {
+    ParseError {
+        line,
+        message: message.to_string(),
+    }
+}
+
+/// Reason why the vertices of a Poly do not describe a simple polygon.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PolyError {
+    /// Fewer than three vertices
+    TooFewVertices(usize),
+    /// Vertex with a NaN or infinite coordinate
+    NonFiniteVertex(usize),
+    /// Vertex equal to an earlier one
+    DuplicateVertex(usize),
+    /// The edge starting at this vertex crosses an earlier edge
+    IntersectingEdges(usize),
+    /// A vertex lies on an edge or two edges overlap
+    TouchingEdges,
+    /// All vertices lie on a line
+    ZeroArea,
+    /// An edit turns the vertices of a triangulated Poly, or of one given
+    /// in ccw order, into cw order; only reported by the edits of a Poly
+    Clockwise,
+    /// An edit turns the vertices of a Poly that was given in cw order
+    /// and not triangulated yet into ccw order
+    Counterclockwise,
+}
+
+impl fmt::Display for PolyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolyError::TooFewVertices(n) => {
+                write!(f, "a polygon needs at least three vertices, got {}", n)
+            }
+            PolyError::NonFiniteVertex(idx) => {
+                write!(f, "vertex {} has a non-finite coordinate", idx)
+            }
+            PolyError::DuplicateVertex(idx) => write!(f, "vertex {} is a duplicate", idx),
+            PolyError::IntersectingEdges(idx) => write!(
+                f,
+                "the edge starting at vertex {} intersects another edge",
+                idx
+            ),
+            PolyError::TouchingEdges => {
+                write!(f, "a vertex lies on an edge or two edges overlap")
+            }
+            PolyError::ZeroArea => write!(f, "all vertices lie on a line"),
+            PolyError::Clockwise => write!(f, "the vertices are in clockwise order"),
+            PolyError::Counterclockwise => {
+                write!(f, "the vertices are in counterclockwise order")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PolyError {}
+
+/// Way in which the convex parts of a Poly fail to decompose it.
+/// Parts and their vertices are numbered as in Poly::convex_part_cycles.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DecompositionError {
+    /// There are no convex parts, e.g. the decomposition was not run
+    NoParts,
+    /// The part has fewer than three vertices
+    DegeneratePart(usize),
+    /// The vertices of the part are in cw order
+    ClockwisePart(usize),
+    /// The part has a reflex angle at this vertex
+    ReflexVertex { part: usize, vertex: usize },
+    /// This vertex of the part is neither a vertex of the polygon nor
+    /// a Steiner point
+    ForeignVertex { part: usize, vertex: usize },
+    /// The edge of the part starting at this vertex leaves the polygon
+    EdgeOutside { part: usize, vertex: usize },
+    /// The two parts overlap
+    Overlap(usize, usize),
+    /// The total area of the parts differs from the area of the polygon
+    AreaMismatch { polygon: f64, parts: f64 },
+}
+
+impl fmt::Display for DecompositionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecompositionError::NoParts => write!(f, "there are no convex parts"),
+            DecompositionError::DegeneratePart(part) => {
+                write!(f, "part {} has fewer than three vertices", part)
+            }
+            DecompositionError::ClockwisePart(part) => {
+                write!(f, "the vertices of part {} are in clockwise order", part)
+            }
+            DecompositionError::ReflexVertex { part, vertex } => {
+                write!(f, "part {} has a reflex angle at its vertex {}", part, vertex)
+            }
+            DecompositionError::ForeignVertex { part, vertex } => write!(
+                f,
+                "vertex {} of part {} is neither a vertex of the polygon nor a Steiner point",
+                vertex, part
+            ),
+            DecompositionError::EdgeOutside { part, vertex } => write!(
+                f,
+                "the edge of part {} starting at its vertex {} leaves the polygon",
+                part, vertex
+            ),
+            DecompositionError::Overlap(a, b) => write!(f, "parts {} and {} overlap", a, b),
+            DecompositionError::AreaMismatch { polygon, parts } => write!(
+                f,
+                "the parts have a total area of {}, the polygon {}",
+                parts, polygon
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DecompositionError {}
+
+/// Reason why the polygons of a PolygonSet cannot be decomposed.
+/// Polygons are numbered as in PolygonSet::polygons.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SetError {
+    /// The polygon is not simple
+    Polygon(usize, PolyError),
+    /// The interiors of the two polygons overlap
+    Overlap(usize, usize),
+}
+
+impl fmt::Display for SetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SetError::Polygon(idx, err) => write!(f, "polygon {}: {}", idx, err),
+            SetError::Overlap(a, b) => write!(f, "polygons {} and {} overlap", a, b),
+        }
+    }
+}
+
+impl std::error::Error for SetError {}
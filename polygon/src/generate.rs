@@ -0,0 +1,567 @@
+//! Random simple polygons for tests, benchmarks and the demo app.
+//! The generators are seeded, so the same method, size and seed always
+//! give the same polygon.  The vertices are in ccw order and fit in the
+//! unit square.
+use crate::geometry::{doubled_area, orientation, segments_intersect};
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64;
+use std::collections::{HashMap, HashSet};
+use std::f64::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Method {
+    /// Points at increasing angles on an ellipse.  Beyond a few
+    /// thousand vertices f32 rounding makes some of them collinear
+    /// or slightly reflex.
+    Convex,
+    /// Random radii at increasing angles around the centre
+    Star,
+    /// Random points connected in random order, then untangled by
+    /// reversing the chain between crossing edges
+    TwoOpt,
+    /// Random points chained by recursively splitting the plane
+    /// (Auer and Held)
+    SpacePartition,
+    /// Boundary of the grid cells visited by a random walk, on a grid
+    /// with nearly even spacing
+    GridWalk,
+    /// Boundary of a randomly grown set of grid cells, with random
+    /// column widths and row heights
+    Orthogonal,
+    /// Spiral arm of random width
+    Spiral,
+    /// Comb with teeth of random width and height
+    Comb,
+}
+
+impl Method {
+    pub const ALL: [Method; 8] = [
+        Method::Convex,
+        Method::Star,
+        Method::TwoOpt,
+        Method::SpacePartition,
+        Method::GridWalk,
+        Method::Orthogonal,
+        Method::Spiral,
+        Method::Comb,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Method::Convex => "convex",
+            Method::Star => "star",
+            Method::TwoOpt => "two-opt",
+            Method::SpacePartition => "space-partition",
+            Method::GridWalk => "grid-walk",
+            Method::Orthogonal => "orthogonal",
+            Method::Spiral => "spiral",
+            Method::Comb => "comb",
+        }
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Method {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_lowercase();
+        Method::ALL
+            .iter()
+            .find(|method| method.name() == s)
+            .cloned()
+            .ok_or_else(|| {
+                let names: Vec<&str> = Method::ALL.iter().map(|m| m.name()).collect();
+                format!(
+                    "unknown polygon generator {}, expected one of {}",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// Returns a random simple polygon with about the given number of vertices.
+/// Convex, star, two-opt and space partitioning give exactly that many (at least 3),
+/// spirals round down to an even number (at least 6) and combs to a multiple
+/// of 4 (at least 4); the grid methods stop growing once they have at least
+/// that many vertices.
+pub fn random_polygon(method: Method, vertices: usize, seed: u64) -> Vec<[f32; 2]> {
+    let mut rng = Pcg64::seed_from_u64(seed);
+    let points = match method {
+        Method::Convex => convex(vertices.max(3), &mut rng),
+        Method::Star => star(vertices.max(3), &mut rng),
+        Method::TwoOpt => two_opt(vertices.max(3), &mut rng),
+        Method::SpacePartition => space_partition(vertices.max(3), &mut rng),
+        Method::GridWalk => grid_polygon(vertices, true, &mut rng),
+        Method::Orthogonal => grid_polygon(vertices, false, &mut rng),
+        Method::Spiral => spiral(vertices.max(6) / 2, &mut rng),
+        Method::Comb => comb((vertices / 4).max(1), &mut rng),
+    };
+
+    let mut ring: Vec<[f32; 2]> = points.iter().map(|p| [p[0] as f32, p[1] as f32]).collect();
+    if doubled_area(&ring) < 0.0 {
+        ring.reverse();
+    }
+    ring
+}
+
+/// Distinct random points of the unit square.  The coordinates are
+/// multiples of 2^-20, so they are exact in f32 and the orientation
+/// tests on them are exact in f64.
+fn random_points(count: usize, rng: &mut Pcg64) -> Vec<[f64; 2]> {
+    const STEPS: u32 = 1 << 20;
+    let mut seen = HashSet::new();
+    let mut points = Vec::with_capacity(count);
+    while points.len() < count {
+        let point = (rng.gen_range(0..=STEPS), rng.gen_range(0..=STEPS));
+        if seen.insert(point) {
+            points.push([point.0 as f64 / STEPS as f64, point.1 as f64 / STEPS as f64]);
+        }
+    }
+    points
+}
+
+fn convex(count: usize, rng: &mut Pcg64) -> Vec<[f64; 2]> {
+    (0..count)
+        .map(|idx| {
+            let angle = 2.0 * PI * (idx as f64 + rng.gen_range(0.0..0.5)) / count as f64;
+            [0.5 + 0.45 * angle.cos(), 0.5 + 0.3 * angle.sin()]
+        })
+        .collect()
+}
+
+fn star(count: usize, rng: &mut Pcg64) -> Vec<[f64; 2]> {
+    (0..count)
+        .map(|idx| {
+            // strictly increasing angles, at most 3/4 of a half turn apart for 4 or more vertices
+            let angle = 2.0 * PI * (idx as f64 + rng.gen_range(0.0..0.5)) / count as f64;
+            let radius = rng.gen_range(0.1..0.5);
+            [0.5 + radius * angle.cos(), 0.5 + radius * angle.sin()]
+        })
+        .collect()
+}
+
+/// 2-opt moves shorten the polygon, so the untangling terminates.
+/// Every pass is quadratic in the number of vertices, which makes this
+/// the slowest method; it is meant for up to a few hundred vertices.
+fn two_opt(count: usize, rng: &mut Pcg64) -> Vec<[f64; 2]> {
+    let points = random_points(count, rng);
+    let mut order: Vec<usize> = (0..count).collect();
+    order.shuffle(rng);
+
+    loop {
+        let mut untangled = true;
+        for i in 0..count {
+            for j in i + 2..count {
+                if i == 0 && j == count - 1 {
+                    continue; // adjacent edges
+                }
+                let a = points[order[i]];
+                let b = points[order[i + 1]];
+                let c = points[order[j]];
+                let d = points[order[(j + 1) % count]];
+                if segments_intersect(a, b, c, d) {
+                    order[i + 1..=j].reverse();
+                    untangled = false;
+                }
+            }
+        }
+        if untangled {
+            break;
+        }
+    }
+    order.iter().map(|idx| points[*idx]).collect()
+}
+
+fn space_partition(count: usize, rng: &mut Pcg64) -> Vec<[f64; 2]> {
+    let points = random_points(count, rng);
+    // the points are random, so the first two are as good as any
+    let (a, b) = (0, 1);
+    let (left, right): (Vec<usize>, Vec<usize>) =
+        (2..count).partition(|idx| orientation(points[a], points[b], points[*idx]) > 0.0);
+
+    let mut order = vec![a];
+    chain(&points, a, b, right, rng, &mut order);
+    order.push(b);
+    chain(&points, b, a, left, rng, &mut order);
+    order.iter().map(|idx| points[*idx]).collect()
+}
+
+/// Appends a chain from a to c through all the given points, which lie in
+/// a convex region having a and c on its boundary.  A random point d of the
+/// region and a random point r of ac split the region along the line dr,
+/// and the chains from a to d and from d to c are built in the two halves.
+fn chain(
+    points: &[[f64; 2]],
+    a: usize,
+    c: usize,
+    inside: Vec<usize>,
+    rng: &mut Pcg64,
+    order: &mut Vec<usize>,
+) {
+    enum Task {
+        Chain(usize, usize, Vec<usize>),
+        Emit(usize),
+    }
+
+    // explicit stack, the recursion can be as deep as the number of points
+    let mut tasks = vec![Task::Chain(a, c, inside)];
+    while let Some(task) = tasks.pop() {
+        let (a, c, inside) = match task {
+            Task::Emit(idx) => {
+                order.push(idx);
+                continue;
+            }
+            Task::Chain(a, c, inside) => (a, c, inside),
+        };
+        if inside.is_empty() {
+            continue;
+        }
+
+        let d = inside[rng.gen_range(0..inside.len())];
+        let t = rng.gen_range(0.0..1.0);
+        let r = [
+            points[a][0] + t * (points[c][0] - points[a][0]),
+            points[a][1] + t * (points[c][1] - points[a][1]),
+        ];
+        let side_of_a = orientation(points[d], r, points[a]) > 0.0;
+        let (near_a, near_c): (Vec<usize>, Vec<usize>) = inside
+            .into_iter()
+            .filter(|idx| *idx != d)
+            .partition(|idx| (orientation(points[d], r, points[*idx]) > 0.0) == side_of_a);
+
+        // popped in reverse order
+        tasks.push(Task::Chain(d, c, near_c));
+        tasks.push(Task::Emit(d));
+        tasks.push(Task::Chain(a, d, near_a));
+    }
+}
+
+/// Edge and corner neighbours of a cell, in ccw order starting east.
+const RING: [(i32, i32); 8] = [
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+];
+
+/// A cell can be added to a set of cells whose union is a disk if the union
+/// stays a disk, i.e. if the cell boundary meets the set in a single arc
+/// containing at least one full edge.
+fn can_add(cells: &HashSet<(i32, i32)>, cell: (i32, i32)) -> bool {
+    let mut occupied = [false; 8];
+    for (k, (dx, dy)) in RING.iter().enumerate() {
+        occupied[k] = cells.contains(&(cell.0 + dx, cell.1 + dy));
+    }
+    if !occupied.iter().step_by(2).any(|o| *o) {
+        return false;
+    }
+    // even pieces are the edges of the cell, odd pieces its corners
+    let mut pieces = occupied;
+    for k in (1..8).step_by(2) {
+        pieces[k] = occupied[k - 1] || occupied[k] || occupied[(k + 1) % 8];
+    }
+    let arcs = (0..8)
+        .filter(|k| pieces[*k] && !pieces[(k + 7) % 8])
+        .count();
+    arcs == 1
+}
+
+/// A grid point is a vertex of the boundary if one or three of its four
+/// cells are in the set.
+fn is_corner(cells: &HashSet<(i32, i32)>, point: (i32, i32)) -> bool {
+    let count = [(-1, -1), (0, -1), (-1, 0), (0, 0)]
+        .iter()
+        .filter(|(dx, dy)| cells.contains(&(point.0 + dx, point.1 + dy)))
+        .count();
+    count == 1 || count == 3
+}
+
+/// Grows a set of grid cells whose union is a disk until its boundary
+/// has at least the given number of vertices.  The cells are added along
+/// a random walk that tends to go straight and backtracks when it cannot
+/// go on, which gives winding corridors, or at random places of the
+/// boundary, preferably at its tips, which gives branching blobs.
+fn grow_cells(vertices: usize, walk: bool, rng: &mut Pcg64) -> HashSet<(i32, i32)> {
+    let mut cells = HashSet::new();
+    cells.insert((0, 0));
+    let mut corners = 4;
+    let corners_of = |cell: (i32, i32)| {
+        [
+            cell,
+            (cell.0 + 1, cell.1),
+            (cell.0, cell.1 + 1),
+            (cell.0 + 1, cell.1 + 1),
+        ]
+    };
+    let step = |cell: (i32, i32), direction: usize| {
+        let (dx, dy) = RING[2 * direction];
+        (cell.0 + dx, cell.1 + dy)
+    };
+
+    let mut path = vec![(0, 0)];
+    let mut direction = 0;
+    let mut frontier: Vec<(i32, i32)> = (0..4).map(|d| step((0, 0), d)).collect();
+    while corners < vertices {
+        let cell = if walk {
+            // the east end of the set can always grow
+            let walker = match path.last() {
+                Some(walker) => *walker,
+                None => *cells.iter().max().unwrap(),
+            };
+            let directions: Vec<usize> = (0..4)
+                .filter(|d| {
+                    let next = step(walker, *d);
+                    !cells.contains(&next) && can_add(&cells, next)
+                })
+                .collect();
+            if directions.is_empty() {
+                path.pop();
+                continue;
+            }
+            if !directions.contains(&direction) || rng.gen_bool(0.3) {
+                direction = *directions.choose(rng).unwrap();
+            }
+            step(walker, direction)
+        } else {
+            if frontier.is_empty() {
+                break;
+            }
+            let cell = frontier.swap_remove(rng.gen_range(0..frontier.len()));
+            // mostly grow at the tips, so that the boundary, rather than
+            // the area, grows with the number of cells
+            let neighbours = (0..4).filter(|d| cells.contains(&step(cell, *d))).count();
+            if neighbours > 1 && rng.gen_bool(0.8) {
+                frontier.push(cell);
+                continue;
+            }
+            cell
+        };
+
+        if cells.contains(&cell) || !can_add(&cells, cell) {
+            continue;
+        }
+        for point in corners_of(cell).iter() {
+            if is_corner(&cells, *point) {
+                corners -= 1;
+            }
+        }
+        cells.insert(cell);
+        for point in corners_of(cell).iter() {
+            if is_corner(&cells, *point) {
+                corners += 1;
+            }
+        }
+        if walk {
+            path.push(cell);
+        } else {
+            frontier.extend((0..4).map(|d| step(cell, d)));
+        }
+    }
+    cells
+}
+
+/// Vertices of the boundary of a disk made of grid cells, in ccw order.
+fn cell_boundary(cells: &HashSet<(i32, i32)>) -> Vec<(i32, i32)> {
+    // ccw boundary edges, the cells are on their left
+    let mut next: HashMap<(i32, i32), (i32, i32)> = HashMap::new();
+    for &(x, y) in cells.iter() {
+        if !cells.contains(&(x, y - 1)) {
+            next.insert((x, y), (x + 1, y));
+        }
+        if !cells.contains(&(x + 1, y)) {
+            next.insert((x + 1, y), (x + 1, y + 1));
+        }
+        if !cells.contains(&(x, y + 1)) {
+            next.insert((x + 1, y + 1), (x, y + 1));
+        }
+        if !cells.contains(&(x - 1, y)) {
+            next.insert((x, y + 1), (x, y));
+        }
+    }
+
+    let start = *next.keys().min().unwrap();
+    let mut boundary = vec![];
+    let mut point = start;
+    loop {
+        let after = next[&point];
+        if is_corner(cells, point) {
+            boundary.push(point);
+        }
+        point = after;
+        if point == start {
+            break;
+        }
+    }
+    boundary
+}
+
+fn grid_polygon(vertices: usize, walk: bool, rng: &mut Pcg64) -> Vec<[f64; 2]> {
+    let cells = grow_cells(vertices, walk, rng);
+    let boundary = cell_boundary(&cells);
+
+    let min_x = boundary.iter().map(|p| p.0).min().unwrap();
+    let max_x = boundary.iter().map(|p| p.0).max().unwrap();
+    let min_y = boundary.iter().map(|p| p.1).min().unwrap();
+    let max_y = boundary.iter().map(|p| p.1).max().unwrap();
+
+    // positions of the grid lines, nearly evenly spaced for the walk.
+    // A regular lattice has many cocircular vertices, on which spade's
+    // constrained triangulation does not always terminate.
+    let spacing = if walk { 0.9..1.1 } else { 0.5..1.5 };
+    let mut grid_lines = |count: i32| {
+        let mut position: f64 = 0.0;
+        let mut positions = vec![position];
+        for _ in 0..count {
+            position += rng.gen_range(spacing.clone());
+            positions.push(position);
+        }
+        positions
+    };
+    let xs = grid_lines(max_x - min_x);
+    let ys = grid_lines(max_y - min_y);
+    let size = xs[xs.len() - 1].max(ys[ys.len() - 1]);
+
+    boundary
+        .iter()
+        .map(|p| {
+            [
+                xs[(p.0 - min_x) as usize] / size,
+                ys[(p.1 - min_y) as usize] / size,
+            ]
+        })
+        .collect()
+}
+
+/// An arm between two Archimedean spirals, sampled at the given number
+/// of angles.  The arm is narrower than the distance between its turns,
+/// and there are enough samples per turn for the chords to stay clear
+/// of the next turn.
+fn spiral(samples: usize, rng: &mut Pcg64) -> Vec<[f64; 2]> {
+    let turns = (samples as f64).sqrt() / 4.0;
+    let turns = turns.min((samples - 1) as f64 / 8.0);
+    let max_angle = 2.0 * PI * turns;
+    let start_radius = rng.gen_range(0.5..1.0);
+    let width = rng.gen_range(0.4..0.6);
+    let rotation = rng.gen_range(0.0..2.0 * PI);
+    let max_radius = start_radius + turns + width;
+
+    let point = |angle: f64, radius: f64| {
+        let angle = angle + rotation;
+        [
+            0.5 + 0.5 * radius * angle.cos() / max_radius,
+            0.5 + 0.5 * radius * angle.sin() / max_radius,
+        ]
+    };
+    // the radius grows by 1 per turn
+    let inner = |idx: usize| {
+        let angle = max_angle * idx as f64 / (samples - 1) as f64;
+        (angle, start_radius + angle / (2.0 * PI))
+    };
+
+    let mut ring: Vec<[f64; 2]> = (0..samples)
+        .map(|idx| {
+            let (angle, radius) = inner(idx);
+            point(angle, radius)
+        })
+        .collect();
+    ring.extend((0..samples).rev().map(|idx| {
+        let (angle, radius) = inner(idx);
+        point(angle, radius + width)
+    }));
+    ring
+}
+
+/// A base with teeth on top, alternating with gaps of random widths.
+fn comb(teeth: usize, rng: &mut Pcg64) -> Vec<[f64; 2]> {
+    let base = 0.15;
+    let widths: Vec<f64> = (0..2 * teeth - 1)
+        .map(|_| rng.gen_range(0.5..1.5))
+        .collect();
+    let total: f64 = widths.iter().sum();
+    let mut edges = vec![0.0];
+    for width in widths.iter() {
+        edges.push(edges[edges.len() - 1] + width / total);
+    }
+    edges[2 * teeth - 1] = 1.0;
+
+    let mut ring = vec![[0.0, 0.0], [1.0, 0.0]];
+    for tooth in (0..teeth).rev() {
+        let left = edges[2 * tooth];
+        let right = edges[2 * tooth + 1];
+        let height = rng.gen_range(0.4..1.0);
+        ring.push([right, height]);
+        ring.push([left, height]);
+        if tooth > 0 {
+            ring.push([left, base]);
+            ring.push([edges[2 * tooth - 1], base]);
+        }
+    }
+    ring
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poly::Poly;
+
+    #[test]
+    fn test_random_polygons_are_simple() {
+        for method in Method::ALL.iter() {
+            for seed in 0..10 {
+                let vertices = random_polygon(*method, 40, seed);
+                assert!(vertices.len() >= 36, "{} {}", method, seed);
+                assert!(vertices
+                    .iter()
+                    .all(|v| (0.0..=1.0).contains(&v[0]) && (0.0..=1.0).contains(&v[1])));
+
+                let mut poly = Poly {
+                    vertices: vertices.clone(),
+                    ..Default::default()
+                };
+                assert_eq!(poly.check_simple(), Ok(()), "{} {}", method, seed);
+                poly.triang();
+                assert!(!poly.changed_orientation, "{} {}", method, seed);
+                assert_eq!(
+                    poly.triangles.len(),
+                    vertices.len() - 2,
+                    "{} {}",
+                    method,
+                    seed
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_reproducible() {
+        for method in Method::ALL.iter() {
+            assert_eq!(
+                random_polygon(*method, 30, 7),
+                random_polygon(*method, 30, 7)
+            );
+            assert_ne!(
+                random_polygon(*method, 30, 7),
+                random_polygon(*method, 30, 8)
+            );
+            assert_eq!(method.name().parse::<Method>(), Ok(*method));
+        }
+        assert_eq!(random_polygon(Method::Star, 25, 0).len(), 25);
+        assert_eq!(random_polygon(Method::Spiral, 25, 0).len(), 24);
+        assert_eq!(random_polygon(Method::Comb, 25, 0).len(), 24);
+        assert_eq!(random_polygon(Method::TwoOpt, 3, 0).len(), 3);
+    }
+}
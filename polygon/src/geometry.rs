@@ -0,0 +1,136 @@
+//! Small geometric predicates shared by the modules of the crate.
+use cgmath::Point2;
+use std::cmp::Ordering;
+
+/// Entry of the open list of a best-first search in a BinaryHeap,
+/// ordered by smallest cost first
+pub(crate) struct Open {
+    pub(crate) cost: f64,
+    pub(crate) index: usize,
+}
+
+impl PartialEq for Open {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Open {}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Open {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+/// Twice the signed area of the triangle (o, a, b).
+/// Positive if the triangle is in ccw order.
+pub(crate) fn cross(o: Point2<f32>, a: Point2<f32>, b: Point2<f32>) -> f32 {
+    (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x)
+}
+
+/// Returns true if the point lies inside or on the boundary
+/// of the convex ccw cycle.
+pub(crate) fn convex_contains(cycle: &[Point2<f32>], point: Point2<f32>) -> bool {
+    (0..cycle.len()).all(|idx| cross(cycle[idx], cycle[(idx + 1) % cycle.len()], point) >= 0.0)
+}
+
+pub(crate) fn centroid(triangle: &[Point2<f32>; 3]) -> Point2<f32> {
+    Point2::new(
+        (triangle[0].x + triangle[1].x + triangle[2].x) / 3.0,
+        (triangle[0].y + triangle[1].y + triangle[2].y) / 3.0,
+    )
+}
+
+/// Euclidean distance between two points, in double precision.
+pub(crate) fn distance<T: Into<f64> + Copy>(a: [T; 2], b: [T; 2]) -> f64 {
+    (b[0].into() - a[0].into()).hypot(b[1].into() - a[1].into())
+}
+
+/// Twice the signed area of the cycle by the shoelace formula, in double
+/// precision.  Positive if the cycle is in ccw order.
+pub(crate) fn doubled_area<T: Into<f64> + Copy>(cycle: &[[T; 2]]) -> f64 {
+    (0..cycle.len())
+        .map(|idx| {
+            let (a, b) = (cycle[idx], cycle[(idx + 1) % cycle.len()]);
+            a[0].into() * b[1].into() - a[1].into() * b[0].into()
+        })
+        .sum()
+}
+
+/// Exact hash key of a point, so that equal coordinates meet in a map.
+pub(crate) fn key(point: [f64; 2]) -> [u64; 2] {
+    [point[0].to_bits(), point[1].to_bits()]
+}
+
+/// Twice the signed area of the triangle (a, b, c) in double precision.
+pub(crate) fn orientation(a: [f64; 2], b: [f64; 2], c: [f64; 2]) -> f64 {
+    (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+}
+
+/// True if p lies in the bounding box of the segment ab; together with a
+/// zero orientation this puts p on the segment.
+pub(crate) fn on_segment(a: [f64; 2], b: [f64; 2], p: [f64; 2]) -> bool {
+    p[0] >= a[0].min(b[0])
+        && p[0] <= a[0].max(b[0])
+        && p[1] >= a[1].min(b[1])
+        && p[1] <= a[1].max(b[1])
+}
+
+/// Two convex ccw cycles overlap unless an edge of one of them has the
+/// other cycle on its outer side, up to the tolerance on the doubled
+/// triangle areas.
+pub(crate) fn convex_overlap(a: &[[f64; 2]], b: &[[f64; 2]], tolerance: f64) -> bool {
+    let separated = |a: &[[f64; 2]], b: &[[f64; 2]]| {
+        (0..a.len()).any(|idx| {
+            let next = a[(idx + 1) % a.len()];
+            b.iter().all(|p| orientation(a[idx], next, *p) <= tolerance)
+        })
+    };
+    !separated(a, b) && !separated(b, a)
+}
+
+/// True if the segments ab and cd cross at a point inside both of them
+pub(crate) fn segments_cross(a: [f64; 2], b: [f64; 2], c: [f64; 2], d: [f64; 2]) -> bool {
+    orientation(a, b, c) * orientation(a, b, d) < 0.0
+        && orientation(c, d, a) * orientation(c, d, b) < 0.0
+}
+
+/// True if the point lies inside the closed cycle by the even-odd rule.
+/// A point on the boundary counts as inside if boundary is set.
+pub(crate) fn ring_contains(cycle: &[[f64; 2]], point: [f64; 2], boundary: bool) -> bool {
+    let mut inside = false;
+    for idx in 0..cycle.len() {
+        let (a, b) = (cycle[idx], cycle[(idx + 1) % cycle.len()]);
+        if orientation(a, b, point) == 0.0 && on_segment(a, b, point) {
+            return boundary;
+        }
+        if (a[1] > point[1]) != (b[1] > point[1])
+            && point[0] < a[0] + (b[0] - a[0]) * (point[1] - a[1]) / (b[1] - a[1])
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// True if the closed segments ab and cd have a point in common
+pub(crate) fn segments_intersect(a: [f64; 2], b: [f64; 2], c: [f64; 2], d: [f64; 2]) -> bool {
+    if segments_cross(a, b, c, d) {
+        return true;
+    }
+    let o1 = orientation(a, b, c);
+    let o2 = orientation(a, b, d);
+    let o3 = orientation(c, d, a);
+    let o4 = orientation(c, d, b);
+    (o1 == 0.0 && on_segment(a, b, c))
+        || (o2 == 0.0 && on_segment(a, b, d))
+        || (o3 == 0.0 && on_segment(c, d, a))
+        || (o4 == 0.0 && on_segment(c, d, b))
+}
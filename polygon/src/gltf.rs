@@ -0,0 +1,219 @@
+use crate::mesh::Mesh;
+use std::io::{self, Write};
+
+// glTF constants
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const TRIANGLES: u32 = 4;
+
+const GLB_MAGIC: u32 = 0x4654_6C67;
+const GLB_JSON_CHUNK: u32 = 0x4E4F_534A;
+const GLB_BIN_CHUNK: u32 = 0x004E_4942;
+
+impl Mesh {
+    /// Binary buffer of the glTF export: the positions (z = 0)
+    /// followed by the indices of every convex part in turn.
+    pub fn gltf_buffer(&self) -> Vec<u8> {
+        let mut buffer: Vec<u8> = Vec::new();
+        for p in self.positions.iter() {
+            buffer.extend_from_slice(&p[0].to_le_bytes());
+            buffer.extend_from_slice(&p[1].to_le_bytes());
+            buffer.extend_from_slice(&0.0f32.to_le_bytes());
+        }
+        for (_, group) in self.groups().iter() {
+            for triangle in group.iter() {
+                for vertex in self.indices[*triangle].iter() {
+                    buffer.extend_from_slice(&vertex.to_le_bytes());
+                }
+            }
+        }
+        buffer
+    }
+
+    /// glTF 2.0 JSON document describing the mesh.  Every convex part
+    /// is a primitive of its own, sharing the POSITION accessor, and
+    /// carries its part index in the primitive extras.
+    /// The buffer is referenced by bin_uri, percent-encoded, or is the
+    /// GLB binary chunk if None.  A mesh without triangles has no
+    /// primitive and gives an invalid document; write_gltf and write_glb
+    /// reject it.
+    pub fn gltf_json(&self, bin_uri: Option<&str>) -> String {
+        let positions_length = self.positions.len() * 12;
+        let buffer_length = positions_length + self.indices.len() * 12;
+
+        let mut min = [f32::INFINITY, f32::INFINITY];
+        let mut max = [f32::NEG_INFINITY, f32::NEG_INFINITY];
+        for p in self.positions.iter() {
+            min = [min[0].min(p[0]), min[1].min(p[1])];
+            max = [max[0].max(p[0]), max[1].max(p[1])];
+        }
+        if self.positions.is_empty() {
+            min = [0.0, 0.0];
+            max = [0.0, 0.0];
+        }
+
+        let mut accessors = vec![format!(
+            r#"{{"bufferView":0,"componentType":{},"count":{},"type":"VEC3","min":[{},{},0],"max":[{},{},0]}}"#,
+            FLOAT,
+            self.positions.len(),
+            min[0],
+            min[1],
+            max[0],
+            max[1]
+        )];
+        let mut primitives = vec![];
+        let mut byte_offset = 0;
+        for (part, group) in self.groups().iter() {
+            accessors.push(format!(
+                r#"{{"bufferView":1,"byteOffset":{},"componentType":{},"count":{},"type":"SCALAR"}}"#,
+                byte_offset,
+                UNSIGNED_INT,
+                group.len() * 3
+            ));
+            let extras = match part {
+                Some(part) => format!(r#","extras":{{"part":{}}}"#, part),
+                None => String::new(),
+            };
+            primitives.push(format!(
+                r#"{{"attributes":{{"POSITION":0}},"indices":{},"mode":{}{}}}"#,
+                accessors.len() - 1,
+                TRIANGLES,
+                extras
+            ));
+            byte_offset += group.len() * 12;
+        }
+
+        let uri = match bin_uri {
+            Some(uri) => format!(r#""uri":"{}","#, percent_encode(uri)),
+            None => String::new(),
+        };
+
+        format!(
+            concat!(
+                r#"{{"asset":{{"version":"2.0","generator":"polydecomp"}},"#,
+                r#""scene":0,"scenes":[{{"nodes":[0]}}],"#,
+                r#""nodes":[{{"mesh":0,"name":"polygon"}}],"#,
+                r#""meshes":[{{"name":"polygon","primitives":[{}]}}],"#,
+                r#""accessors":[{}],"#,
+                r#""bufferViews":[{{"buffer":0,"byteOffset":0,"byteLength":{},"target":{}}},"#,
+                r#"{{"buffer":0,"byteOffset":{},"byteLength":{},"target":{}}}],"#,
+                r#""buffers":[{{{}"byteLength":{}}}]}}"#
+            ),
+            primitives.join(","),
+            accessors.join(","),
+            positions_length,
+            ARRAY_BUFFER,
+            positions_length,
+            buffer_length - positions_length,
+            ELEMENT_ARRAY_BUFFER,
+            uri,
+            buffer_length
+        )
+    }
+
+    /// Writes the .gltf document and its .bin buffer,
+    /// bin_uri being the path of the buffer relative to the document.
+    pub fn write_gltf<W: Write, B: Write>(
+        &self,
+        mut json_writer: W,
+        mut bin_writer: B,
+        bin_uri: &str,
+    ) -> io::Result<()> {
+        self.check_not_empty()?;
+        json_writer.write_all(self.gltf_json(Some(bin_uri)).as_bytes())?;
+        bin_writer.write_all(&self.gltf_buffer())
+    }
+
+    /// Writes the mesh as a single binary .glb file.
+    pub fn write_glb<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        self.check_not_empty()?;
+        // chunks have to be 4 byte aligned: JSON padded with spaces, BIN with zeros
+        let mut json = self.gltf_json(None).into_bytes();
+        json.resize(json.len() + (4 - json.len() % 4) % 4, b' ');
+        let mut bin = self.gltf_buffer();
+        bin.resize(bin.len() + (4 - bin.len() % 4) % 4, 0);
+
+        let total_length = 12 + 8 + json.len() + 8 + bin.len();
+        writer.write_all(&GLB_MAGIC.to_le_bytes())?;
+        writer.write_all(&2u32.to_le_bytes())?;
+        writer.write_all(&(total_length as u32).to_le_bytes())?;
+
+        writer.write_all(&(json.len() as u32).to_le_bytes())?;
+        writer.write_all(&GLB_JSON_CHUNK.to_le_bytes())?;
+        writer.write_all(&json)?;
+
+        writer.write_all(&(bin.len() as u32).to_le_bytes())?;
+        writer.write_all(&GLB_BIN_CHUNK.to_le_bytes())?;
+        writer.write_all(&bin)
+    }
+
+    /// glTF requires a primitive in every mesh and a non-empty buffer
+    /// behind every accessor
+    fn check_not_empty(&self) -> io::Result<()> {
+        if self.indices.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "glTF export needs at least one triangle",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Percent-encodes everything but the unreserved characters and the
+/// path separator, which also keeps the uri a valid JSON string
+fn percent_encode(uri: &str) -> String {
+    uri.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::poly::Poly;
+    use crate::test_util::beak_poly;
+
+    #[test]
+    fn test_gltf_primitives_per_part() {
+        let mesh = beak_poly().mesh();
+        let json = mesh.gltf_json(Some("beak.bin"));
+        assert_eq!(json.matches(r#""extras":{"part":"#).count(), 2);
+        assert!(json.contains(r#""uri":"beak.bin""#));
+        // 4 vertices * 12 bytes + 2 triangles * 12 bytes
+        assert!(json.contains(r#""byteLength":72}"#));
+        assert_eq!(mesh.gltf_buffer().len(), 72);
+    }
+
+    #[test]
+    fn test_empty_mesh_and_uri() {
+        let empty = Poly::default().mesh();
+        assert!(empty.write_glb(Vec::new()).is_err());
+        assert!(empty.write_gltf(Vec::new(), Vec::new(), "a.bin").is_err());
+
+        let json = beak_poly().mesh().gltf_json(Some(r#"my "beak".bin"#));
+        assert!(json.contains(r#""uri":"my%20%22beak%22.bin""#));
+    }
+
+    #[test]
+    fn test_glb_layout() {
+        let mesh = beak_poly().mesh();
+        let mut glb = Vec::new();
+        mesh.write_glb(&mut glb).unwrap();
+        assert_eq!(&glb[0..4], b"glTF");
+        assert_eq!(
+            u32::from_le_bytes([glb[8], glb[9], glb[10], glb[11]]) as usize,
+            glb.len()
+        );
+        let json_length = u32::from_le_bytes([glb[12], glb[13], glb[14], glb[15]]) as usize;
+        assert_eq!(json_length % 4, 0);
+        assert_eq!(&glb[16..20], b"JSON");
+        assert_eq!(&glb[24 + json_length..28 + json_length], b"BIN\0");
+    }
+}
@@ -0,0 +1,137 @@
+//! Art gallery guards after Fisk's proof: the vertices of a triangulated
+//! polygon can be coloured with three colours so that every triangle has
+//! one vertex of each colour, so the vertices of any one colour see the
+//! whole polygon.  The smallest colour class has at most floor(n/3)
+//! vertices.
+use crate::poly::Poly;
+use cgmath::Point2;
+use std::collections::HashMap;
+
+/// 3-colouring of the polygon vertices and the guards it gives
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Guards {
+    /// Colour, 0, 1 or 2, of every vertex, in the order of Poly::vertices
+    pub colours: Vec<u8>,
+    /// The colour of the guards, the colour of the fewest vertices
+    pub colour: u8,
+    /// Indices of the guarding vertices in Poly::vertices
+    pub guards: Vec<usize>,
+}
+
+impl Poly {
+    /// Colours the vertices by walking the dual tree of the
+    /// triangulation, then places guards on the vertices of the least
+    /// used colour.  Expects the triangles of triang, and is empty
+    /// without them.
+    pub fn guards(&self) -> Guards {
+        if self.triangles.is_empty() {
+            return Guards::default();
+        }
+        let key = |p: Point2<f32>| [p.x.to_bits(), p.y.to_bits()];
+        let index: HashMap<[u32; 2], usize> = self
+            .vertices
+            .iter()
+            .enumerate()
+            .map(|(idx, v)| ([v[0].to_bits(), v[1].to_bits()], idx))
+            .collect();
+        let corners: Vec<[usize; 3]> = self
+            .triangles
+            .iter()
+            .map(|t| [index[&key(t[0])], index[&key(t[1])], index[&key(t[2])]])
+            .collect();
+
+        // the first triangle takes all three colours; every neighbour
+        // shares two vertices with it and gives its third the colour left
+        const NONE: u8 = u8::MAX;
+        let mut colours = vec![NONE; self.vertices.len()];
+        for (colour, vertex) in corners[0].iter().enumerate() {
+            colours[*vertex] = colour as u8;
+        }
+        let adjacency = self.triangle_adjacency();
+        let mut seen = vec![false; corners.len()];
+        let mut stack = vec![0];
+        seen[0] = true;
+        while let Some(triangle) = stack.pop() {
+            for neighbour in adjacency.neighbours[triangle].iter() {
+                if seen[neighbour.index] {
+                    continue;
+                }
+                seen[neighbour.index] = true;
+                let corner = corners[neighbour.index];
+                let used: u8 = corner
+                    .iter()
+                    .filter(|vertex| colours[**vertex] != NONE)
+                    .map(|vertex| colours[*vertex])
+                    .sum();
+                for vertex in corner.iter() {
+                    if colours[*vertex] == NONE {
+                        colours[*vertex] = 3 - used;
+                    }
+                }
+                stack.push(neighbour.index);
+            }
+        }
+
+        let mut counts = [0; 3];
+        for colour in colours.iter().filter(|colour| **colour != NONE) {
+            counts[*colour as usize] += 1;
+        }
+        let colour = (0..3).min_by_key(|colour| counts[*colour]).unwrap() as u8;
+        let guards = (0..colours.len())
+            .filter(|idx| colours[*idx] == colour)
+            .collect();
+        Guards {
+            colours,
+            colour,
+            guards,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{random_polygon, Method};
+    use crate::geometry::centroid;
+
+    #[test]
+    fn test_guards_see_every_triangle() {
+        for method in Method::ALL.iter() {
+            for seed in 0..3 {
+                let mut poly = Poly {
+                    vertices: random_polygon(*method, 60, seed),
+                    ..Default::default()
+                };
+                poly.triang();
+                let guards = poly.guards();
+                assert!(guards.guards.len() <= poly.vertices.len() / 3);
+
+                let index =
+                    |p: Point2<f32>| poly.vertices.iter().position(|v| *v == [p.x, p.y]).unwrap();
+                let graph = poly.visibility_graph();
+                for triangle in poly.triangles.iter() {
+                    let mut colours: Vec<u8> =
+                        triangle.iter().map(|p| guards.colours[index(*p)]).collect();
+                    colours.sort_unstable();
+                    assert_eq!(colours, vec![0, 1, 2], "{} {}", method, seed);
+
+                    let center = centroid(triangle);
+                    let center = [center.x as f64, center.y as f64];
+                    assert!(guards.guards.iter().any(|guard| {
+                        let v = poly.vertices[*guard];
+                        graph.visible([v[0] as f64, v[1] as f64], center)
+                    }));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_no_triangles_no_guards() {
+        let poly = Poly {
+            vertices: vec![[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]],
+            ..Default::default()
+        };
+        assert_eq!(poly.guards(), Guards::default());
+    }
+}
@@ -1 +1,28 @@
+pub mod adjacency;
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+pub mod bench_util;
+pub mod dcel;
+pub mod dxf;
+pub mod edit;
+pub mod error;
+pub mod generate;
+mod geometry;
+pub mod gltf;
+pub mod guards;
+pub mod locate;
+pub mod mesh;
+pub mod min_weight;
+pub mod navmesh;
+pub mod offset;
 pub mod poly;
+pub mod polygon_set;
+pub mod refine;
+pub mod skeleton;
+pub mod svg;
+#[cfg(any(test, feature = "test-util"))]
+#[doc(hidden)]
+pub mod test_util;
+pub mod triangle_format;
+pub mod validate;
+pub mod visibility;
@@ -0,0 +1,515 @@
+//! Point location: which convex part and which triangle contain a point.
+//! Both are found in a trapezoidal map of the edges of the parts and of
+//! the triangles, built by inserting the edges in random order.  Its
+//! search structure answers a query in O(log n) expected time and takes
+//! O(n) expected space, whatever the shape of the pieces.
+//!
+//! Parts and triangles are closed, so a point on an edge or a vertex
+//! lies in all the pieces around it; it is given to the one with the
+//! smallest index.  Points are ordered by x and then by y, which stands
+//! in for the general position the map assumes: a vertical edge runs
+//! from its lower end to its upper end and has its left side above it.
+use crate::geometry::{key, orientation};
+use crate::poly::Poly;
+use cgmath::Point2;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rand_pcg::Pcg64;
+use std::collections::HashMap;
+
+/// Convex part and triangle containing a point
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Location {
+    /// Index in Poly::convex_parts
+    pub part: usize,
+    /// Index in Poly::triangles
+    pub triangle: usize,
+}
+
+/// Edge of the map, from its smaller end to its larger one
+#[derive(Clone, Debug)]
+struct Segment {
+    left: [f64; 2],
+    right: [f64; 2],
+    /// Pieces on either side, None outside the polygon
+    above: Option<usize>,
+    below: Option<usize>,
+}
+
+/// Trapezoid of the map, between two segments and the vertical lines
+/// through two segment ends.  Its neighbours are the trapezoids across
+/// its vertical sides, above and below the end the side goes through.
+#[derive(Clone, Debug, Default)]
+struct Trapezoid {
+    top: Option<usize>,
+    bottom: Option<usize>,
+    left: [f64; 2],
+    right: [f64; 2],
+    upper_left: Option<usize>,
+    lower_left: Option<usize>,
+    upper_right: Option<usize>,
+    lower_right: Option<usize>,
+    /// Leaf of the trapezoid in the search structure
+    node: usize,
+}
+
+/// Node of the search structure
+#[derive(Clone, Copy, Debug)]
+enum Node {
+    /// Point and the nodes left and right of it
+    X([f64; 2], usize, usize),
+    /// Segment and the nodes above and below it
+    Y(usize, usize, usize),
+    Leaf(usize),
+}
+
+fn less(a: [f64; 2], b: [f64; 2]) -> bool {
+    a[0] < b[0] || (a[0] == b[0] && a[1] < b[1])
+}
+
+/// Trapezoidal map of the edges of a set of convex pieces that meet
+/// along whole edges
+#[derive(Clone, Debug, Default)]
+pub(crate) struct TrapezoidMap {
+    segments: Vec<Segment>,
+    trapezoids: Vec<Trapezoid>,
+    nodes: Vec<Node>,
+    /// Smallest index of a piece at every vertex
+    vertices: HashMap<[u64; 2], usize>,
+}
+
+impl TrapezoidMap {
+    /// Builds the map of the pieces, given as cycles of either
+    /// orientation
+    pub(crate) fn new(cycles: &[Vec<Point2<f32>>]) -> TrapezoidMap {
+        let mut map = TrapezoidMap::default();
+        let mut min = [f64::INFINITY; 2];
+        let mut max = [f64::NEG_INFINITY; 2];
+        let mut edges: HashMap<[[u64; 2]; 2], usize> = HashMap::new();
+        for (idx, cycle) in cycles.iter().enumerate() {
+            let cycle: Vec<[f64; 2]> = cycle.iter().map(|p| [p.x as f64, p.y as f64]).collect();
+            let doubled_area: f64 = (0..cycle.len())
+                .map(|vertex| {
+                    let (a, b) = (cycle[vertex], cycle[(vertex + 1) % cycle.len()]);
+                    a[0] * b[1] - a[1] * b[0]
+                })
+                .sum();
+            for vertex in 0..cycle.len() {
+                let (from, to) = (cycle[vertex], cycle[(vertex + 1) % cycle.len()]);
+                for axis in 0..2 {
+                    min[axis] = min[axis].min(from[axis]);
+                    max[axis] = max[axis].max(from[axis]);
+                }
+                let smallest = map.vertices.entry(key(from)).or_insert(idx);
+                *smallest = (*smallest).min(idx);
+
+                // a ccw piece lies on the left of its edges
+                let (left, right) = if less(from, to) {
+                    (from, to)
+                } else {
+                    (to, from)
+                };
+                let above = less(from, to) == (doubled_area > 0.0);
+                let segment = *edges.entry([key(left), key(right)]).or_insert_with(|| {
+                    map.segments.push(Segment {
+                        left,
+                        right,
+                        above: None,
+                        below: None,
+                    });
+                    map.segments.len() - 1
+                });
+                let side = if above {
+                    &mut map.segments[segment].above
+                } else {
+                    &mut map.segments[segment].below
+                };
+                *side = Some(idx);
+            }
+        }
+        if map.segments.is_empty() {
+            return map;
+        }
+
+        let frame = map.trapezoid(
+            None,
+            None,
+            [min[0] - 1.0, min[1] - 1.0],
+            [max[0] + 1.0, max[1] + 1.0],
+        );
+        debug_assert_eq!(map.trapezoids[frame].node, 0);
+        // a fixed seed keeps the structure, and so the queries, the same
+        // from run to run
+        let mut order: Vec<usize> = (0..map.segments.len()).collect();
+        order.shuffle(&mut Pcg64::seed_from_u64(0));
+        for segment in order {
+            map.insert(segment);
+        }
+        map
+    }
+
+    /// Adds a trapezoid without neighbours and its leaf
+    fn trapezoid(
+        &mut self,
+        top: Option<usize>,
+        bottom: Option<usize>,
+        left: [f64; 2],
+        right: [f64; 2],
+    ) -> usize {
+        let idx = self.trapezoids.len();
+        self.nodes.push(Node::Leaf(idx));
+        self.trapezoids.push(Trapezoid {
+            top,
+            bottom,
+            left,
+            right,
+            node: self.nodes.len() - 1,
+            ..Default::default()
+        });
+        idx
+    }
+
+    /// Points the links of the neighbour that lead to old to new
+    fn relink(&mut self, neighbour: Option<usize>, old: usize, new: usize) {
+        if let Some(neighbour) = neighbour {
+            let t = &mut self.trapezoids[neighbour];
+            for link in [
+                &mut t.upper_left,
+                &mut t.lower_left,
+                &mut t.upper_right,
+                &mut t.lower_right,
+            ] {
+                if *link == Some(old) {
+                    *link = Some(new);
+                }
+            }
+        }
+    }
+
+    /// Trapezoid in which the segment from p to q starts
+    fn find(&self, p: [f64; 2], q: [f64; 2]) -> usize {
+        let mut node = 0;
+        loop {
+            match self.nodes[node] {
+                Node::X(point, left, right) => node = if less(p, point) { left } else { right },
+                Node::Y(segment, above, below) => {
+                    let Segment { left, right, .. } = self.segments[segment];
+                    // segments that start together are told apart by
+                    // their other ends
+                    let mut side = orientation(left, right, p);
+                    if side == 0.0 {
+                        side = orientation(left, right, q);
+                    }
+                    node = if side > 0.0 { above } else { below };
+                }
+                Node::Leaf(trapezoid) => return trapezoid,
+            }
+        }
+    }
+
+    fn insert(&mut self, s: usize) {
+        let (p, q) = (self.segments[s].left, self.segments[s].right);
+        let mut crossed = vec![self.find(p, q)];
+        loop {
+            let last = &self.trapezoids[*crossed.last().unwrap()];
+            if !less(last.right, q) {
+                break;
+            }
+            let next = if orientation(p, q, last.right) > 0.0 {
+                last.lower_right
+            } else {
+                last.upper_right
+            };
+            match next {
+                Some(next) => crossed.push(next),
+                None => break,
+            }
+        }
+
+        // the crossed trapezoids split into pieces above and below the
+        // segment; a wall through an end above the segment parts the
+        // pieces above and is cut off below, and the other way round
+        let first = self.trapezoids[crossed[0]].clone();
+        let mut upper = self.trapezoid(first.top, Some(s), p, q);
+        let mut lower = self.trapezoid(Some(s), first.bottom, p, q);
+        let (mut uppers, mut lowers) = (vec![], vec![]);
+        for (j, d) in crossed.iter().enumerate() {
+            uppers.push(upper);
+            lowers.push(lower);
+            if j + 1 == crossed.len() {
+                break;
+            }
+            let next = crossed[j + 1];
+            let (dt, nt) = (self.trapezoids[*d].clone(), self.trapezoids[next].clone());
+            let wall = dt.right;
+            if orientation(p, q, wall) > 0.0 {
+                let new = self.trapezoid(nt.top, Some(s), wall, q);
+                self.trapezoids[upper].right = wall;
+                self.trapezoids[upper].upper_right = dt.upper_right;
+                self.trapezoids[upper].lower_right = Some(new);
+                self.relink(dt.upper_right, *d, upper);
+                self.trapezoids[new].upper_left = nt.upper_left;
+                self.trapezoids[new].lower_left = Some(upper);
+                self.relink(nt.upper_left, next, new);
+                upper = new;
+            } else {
+                let new = self.trapezoid(Some(s), nt.bottom, wall, q);
+                self.trapezoids[lower].right = wall;
+                self.trapezoids[lower].upper_right = Some(new);
+                self.trapezoids[lower].lower_right = dt.lower_right;
+                self.relink(dt.lower_right, *d, lower);
+                self.trapezoids[new].upper_left = Some(lower);
+                self.trapezoids[new].lower_left = nt.lower_left;
+                self.relink(nt.lower_left, next, new);
+                lower = new;
+            }
+        }
+
+        // the rest of the first and the last trapezoid beyond the ends
+        let (top, bottom) = (uppers[0], lowers[0]);
+        let before = less(first.left, p).then(|| {
+            let a = self.trapezoid(first.top, first.bottom, first.left, p);
+            self.trapezoids[a].upper_left = first.upper_left;
+            self.trapezoids[a].lower_left = first.lower_left;
+            self.trapezoids[a].upper_right = Some(top);
+            self.trapezoids[a].lower_right = Some(bottom);
+            a
+        });
+        match before {
+            Some(a) => {
+                self.relink(first.upper_left, crossed[0], a);
+                self.relink(first.lower_left, crossed[0], a);
+                self.trapezoids[top].upper_left = Some(a);
+                self.trapezoids[bottom].lower_left = Some(a);
+            }
+            None => {
+                self.trapezoids[top].upper_left = first.upper_left;
+                self.trapezoids[bottom].lower_left = first.lower_left;
+                self.relink(first.upper_left, crossed[0], top);
+                self.relink(first.lower_left, crossed[0], bottom);
+            }
+        }
+        let end = *crossed.last().unwrap();
+        let last = self.trapezoids[end].clone();
+        let after = less(q, last.right).then(|| {
+            let c = self.trapezoid(last.top, last.bottom, q, last.right);
+            self.trapezoids[c].upper_left = Some(upper);
+            self.trapezoids[c].lower_left = Some(lower);
+            self.trapezoids[c].upper_right = last.upper_right;
+            self.trapezoids[c].lower_right = last.lower_right;
+            c
+        });
+        match after {
+            Some(c) => {
+                self.relink(last.upper_right, end, c);
+                self.relink(last.lower_right, end, c);
+                self.trapezoids[upper].upper_right = Some(c);
+                self.trapezoids[lower].lower_right = Some(c);
+            }
+            None => {
+                self.trapezoids[upper].upper_right = last.upper_right;
+                self.trapezoids[lower].lower_right = last.lower_right;
+                self.relink(last.upper_right, end, upper);
+                self.relink(last.lower_right, end, lower);
+            }
+        }
+
+        // the leaves of the crossed trapezoids become the search nodes
+        // of their pieces
+        for (j, d) in crossed.iter().enumerate() {
+            let leaf = |map: &TrapezoidMap, t: usize| map.trapezoids[t].node;
+            let mut node = Node::Y(s, leaf(self, uppers[j]), leaf(self, lowers[j]));
+            if let (true, Some(c)) = (j + 1 == crossed.len(), after) {
+                self.nodes.push(node);
+                node = Node::X(q, self.nodes.len() - 1, leaf(self, c));
+            }
+            if let (0, Some(a)) = (j, before) {
+                self.nodes.push(node);
+                node = Node::X(p, leaf(self, a), self.nodes.len() - 1);
+            }
+            let old = self.trapezoids[*d].node;
+            self.nodes[old] = node;
+        }
+    }
+
+    /// Smallest index of a piece containing the point
+    pub(crate) fn locate(&self, point: [f64; 2]) -> Option<usize> {
+        if self.segments.is_empty() {
+            return None;
+        }
+        if let Some(piece) = self.vertices.get(&key(point)) {
+            return Some(*piece);
+        }
+        let mut node = 0;
+        loop {
+            match self.nodes[node] {
+                Node::X(p, left, right) => node = if less(point, p) { left } else { right },
+                Node::Y(segment, above, below) => {
+                    let segment = &self.segments[segment];
+                    let side = orientation(segment.left, segment.right, point);
+                    if side == 0.0 {
+                        return match (segment.above, segment.below) {
+                            (Some(a), Some(b)) => Some(a.min(b)),
+                            (a, b) => a.or(b),
+                        };
+                    }
+                    node = if side > 0.0 { above } else { below };
+                }
+                Node::Leaf(trapezoid) => {
+                    let top = self.trapezoids[trapezoid].top?;
+                    return self.segments[top].below;
+                }
+            }
+        }
+    }
+}
+
+/// Point location over the convex parts and the triangles of a polygon
+#[derive(Clone, Debug, Default)]
+pub struct PointLocator {
+    parts: TrapezoidMap,
+    triangles: TrapezoidMap,
+}
+
+impl Poly {
+    /// Builds the point location structure of the convex parts and the
+    /// triangles, in O(n log n) expected time.  Expects the triangles of
+    /// triang and the parts of decomposition or refine_parts; without
+    /// parts, part and locate find nothing.
+    pub fn point_locator(&self) -> PointLocator {
+        let triangles: Vec<Vec<Point2<f32>>> = self.triangles.iter().map(|t| t.to_vec()).collect();
+        PointLocator {
+            parts: TrapezoidMap::new(&self.convex_part_cycles()),
+            triangles: TrapezoidMap::new(&triangles),
+        }
+    }
+}
+
+impl PointLocator {
+    /// Index of the convex part containing the point, None if it lies
+    /// outside the polygon
+    pub fn part(&self, point: [f32; 2]) -> Option<usize> {
+        self.parts.locate([point[0] as f64, point[1] as f64])
+    }
+
+    /// Index of the triangle containing the point, None if it lies
+    /// outside the polygon
+    pub fn triangle(&self, point: [f32; 2]) -> Option<usize> {
+        self.triangles.locate([point[0] as f64, point[1] as f64])
+    }
+
+    /// Convex part and triangle containing the point, None if it lies
+    /// outside the polygon
+    pub fn locate(&self, point: [f32; 2]) -> Option<Location> {
+        Some(Location {
+            part: self.part(point)?,
+            triangle: self.triangle(point)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{random_polygon, Method};
+    use crate::geometry::ring_contains;
+    use crate::refine::PartLimits;
+    use crate::test_util::decomposed;
+    use rand::Rng;
+
+    fn cycle(points: &[Point2<f32>]) -> Vec<[f64; 2]> {
+        points.iter().map(|p| [p.x as f64, p.y as f64]).collect()
+    }
+
+    /// True if the point lies in the closed cycle.  Refined parts are
+    /// not quite convex at Steiner points, which are rounded off the cuts.
+    fn contains(cycle: &[[f64; 2]], point: [f64; 2]) -> bool {
+        ring_contains(cycle, point, true)
+    }
+
+    #[test]
+    fn test_boundary_points() {
+        let l_shape = vec![
+            [0.0, 0.0],
+            [4.0, 0.0],
+            [4.0, 1.0],
+            [1.0, 1.0],
+            [1.0, 4.0],
+            [0.0, 4.0],
+        ];
+        let poly = decomposed(l_shape);
+        let locator = poly.point_locator();
+        // points on the diagonal between the parts and at its ends go to
+        // the first part, whatever the query order
+        let diagonal = poly.part_adjacency().neighbours[0][0].edge;
+        let [from, to] = [diagonal[0], diagonal[1]];
+        let middle = [(from.x + to.x) / 2.0, (from.y + to.y) / 2.0];
+        for point in [[from.x, from.y], [to.x, to.y], middle] {
+            assert_eq!(locator.part(point), Some(0));
+        }
+        // the polygon is closed
+        assert!(locator.locate([0.0, 0.0]).is_some());
+        assert!(locator.locate([2.0, 1.0]).is_some());
+        assert_eq!(locator.locate([2.0, 2.0]), None);
+        assert_eq!(locator.locate([-1.0, 0.0]), None);
+
+        // without a decomposition only the triangles are there
+        let mut poly = Poly {
+            vertices: poly.vertices.clone(),
+            ..Default::default()
+        };
+        poly.triang();
+        let locator = poly.point_locator();
+        assert!(locator.triangle([0.5, 0.5]).is_some());
+        assert_eq!(locator.locate([0.5, 0.5]), None);
+    }
+
+    #[test]
+    fn test_agrees_with_a_scan() {
+        let mut rng = Pcg64::seed_from_u64(0);
+        for method in Method::ALL.iter() {
+            for seed in 0..3 {
+                let mut poly = decomposed(random_polygon(*method, 200, seed));
+                // refined parts meet at Steiner points
+                if seed == 2 {
+                    poly.refine_parts(&PartLimits {
+                        max_area: 0.01,
+                        ..Default::default()
+                    });
+                }
+                let locator = poly.point_locator();
+                let parts: Vec<Vec<[f64; 2]>> =
+                    poly.convex_part_cycles().iter().map(|c| cycle(c)).collect();
+                let triangles: Vec<Vec<[f64; 2]>> =
+                    poly.triangles.iter().map(|t| cycle(t)).collect();
+                let scan = |cycles: &[Vec<[f64; 2]>], p: [f32; 2]| {
+                    cycles
+                        .iter()
+                        .position(|c| contains(c, [p[0] as f64, p[1] as f64]))
+                };
+
+                // random points, and the vertices and middles of the edges,
+                // which lie on boundaries or next to them
+                let mut points: Vec<[f32; 2]> = (0..500)
+                    .map(|_| [rng.gen_range(-0.1..1.1), rng.gen_range(-0.1..1.1)])
+                    .collect();
+                points.extend(poly.vertices.iter());
+                for t in poly.triangles.iter() {
+                    for (a, b) in [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])] {
+                        points.push([(a.x + b.x) / 2.0, (a.y + b.y) / 2.0]);
+                    }
+                }
+                for point in points {
+                    assert_eq!(locator.part(point), scan(&parts, point));
+                    assert_eq!(locator.triangle(point), scan(&triangles, point));
+                    if let Some(location) = locator.locate(point) {
+                        assert!(contains(
+                            &parts[location.part],
+                            [point[0] as f64, point[1] as f64]
+                        ));
+                    }
+                }
+            }
+        }
+    }
+}
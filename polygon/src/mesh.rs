@@ -0,0 +1,341 @@
+use crate::geometry::centroid;
+use crate::locate::TrapezoidMap;
+use crate::poly::Poly;
+use cgmath::Point2;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+
+/// Indexed triangle mesh of a triangulated polygon.
+/// Vertices are shared between triangles; the first vertices
+/// are the polygon vertices in the order of Poly::vertices.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Mesh {
+    pub positions: Vec<[f32; 2]>,
+    /// Triangles as ccw triples of indices into positions
+    pub indices: Vec<[u32; 3]>,
+    /// Index of the convex part containing each triangle, None if no
+    /// part contains it.  Empty if the decomposition has not been
+    /// computed.
+    pub triangle_parts: Vec<Option<usize>>,
+}
+
+/// Encoding of the PLY export
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+}
+
+impl Poly {
+    /// Collects the triangles of the triangulation into an indexed mesh.
+    /// Run triang (and optionally decomposition) first.
+    pub fn mesh(&self) -> Mesh {
+        let mut mesh = Mesh::default();
+        let mut index_of: HashMap<[u32; 2], u32> = HashMap::new();
+
+        let mut vertex_index = |mesh: &mut Mesh, point: Point2<f32>| -> u32 {
+            *index_of
+                .entry([point.x.to_bits(), point.y.to_bits()])
+                .or_insert_with(|| {
+                    mesh.positions.push([point.x, point.y]);
+                    (mesh.positions.len() - 1) as u32
+                })
+        };
+
+        for v in self.vertices.iter() {
+            vertex_index(&mut mesh, Point2::new(v[0], v[1]));
+        }
+        for triangle in self.triangles.iter() {
+            let a = vertex_index(&mut mesh, triangle[0]);
+            let b = vertex_index(&mut mesh, triangle[1]);
+            let c = vertex_index(&mut mesh, triangle[2]);
+            mesh.indices.push([a, b, c]);
+        }
+
+        // a triangle belongs to the convex part containing its centroid,
+        // found in the trapezoidal map of the parts
+        let parts = self.convex_part_cycles();
+        if !parts.is_empty() {
+            let map = TrapezoidMap::new(&parts);
+            mesh.triangle_parts = self
+                .triangles
+                .iter()
+                .map(|triangle| {
+                    let center = centroid(triangle);
+                    map.locate([center.x as f64, center.y as f64])
+                })
+                .collect();
+        }
+
+        mesh
+    }
+}
+
+impl Mesh {
+    /// Non-empty groups of triangles, one per convex part in the order
+    /// of the parts, with the index of the part.  The triangles of no
+    /// part, all of them if there is no decomposition, come last in a
+    /// group without index.
+    pub fn groups(&self) -> Vec<(Option<usize>, Vec<usize>)> {
+        let part_number = self
+            .triangle_parts
+            .iter()
+            .flatten()
+            .max()
+            .map_or(0, |max| max + 1);
+        let mut groups: Vec<(Option<usize>, Vec<usize>)> =
+            (0..part_number).map(|part| (Some(part), vec![])).collect();
+        let mut unassigned = vec![];
+        for triangle in 0..self.indices.len() {
+            match self.part_of(triangle) {
+                Some(part) => groups[part].1.push(triangle),
+                None => unassigned.push(triangle),
+            }
+        }
+        groups.push((None, unassigned));
+        groups.retain(|(_, triangles)| !triangles.is_empty());
+        groups
+    }
+
+    /// Convex part of the triangle, None without decomposition
+    pub(crate) fn part_of(&self, triangle: usize) -> Option<usize> {
+        self.triangle_parts.get(triangle).copied().flatten()
+    }
+
+    /// Wavefront OBJ with one group per convex part.
+    /// The mesh lies in the z = 0 plane.
+    pub fn write_obj<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "# polydecomp triangulation")?;
+        writeln!(writer, "o polygon")?;
+        for p in self.positions.iter() {
+            writeln!(writer, "v {} {} 0", p[0], p[1])?;
+        }
+        writeln!(writer, "vn 0 0 1")?;
+
+        let groups = self.groups();
+        for (part, triangles) in groups.iter() {
+            match part {
+                Some(part) => writeln!(writer, "g part_{}", part)?,
+                None => writeln!(writer, "g triangles")?,
+            }
+            // OBJ indices start at 1
+            for triangle in triangles.iter() {
+                let [a, b, c] = self.indices[*triangle];
+                writeln!(writer, "f {}//1 {}//1 {}//1", a + 1, b + 1, c + 1)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// PLY with a "part" property on every face identifying its convex part
+    /// (-1 if there is no decomposition or no part contains the face).
+    pub fn write_ply<W: Write>(&self, mut writer: W, format: PlyFormat) -> io::Result<()> {
+        writeln!(writer, "ply")?;
+        match format {
+            PlyFormat::Ascii => writeln!(writer, "format ascii 1.0")?,
+            PlyFormat::BinaryLittleEndian => writeln!(writer, "format binary_little_endian 1.0")?,
+        }
+        writeln!(writer, "comment polydecomp triangulation")?;
+        writeln!(writer, "element vertex {}", self.positions.len())?;
+        writeln!(writer, "property float x")?;
+        writeln!(writer, "property float y")?;
+        writeln!(writer, "property float z")?;
+        writeln!(writer, "element face {}", self.indices.len())?;
+        writeln!(writer, "property list uchar uint vertex_indices")?;
+        writeln!(writer, "property int part")?;
+        writeln!(writer, "end_header")?;
+
+        let part_of =
+            |triangle: usize| -> i32 { self.part_of(triangle).map_or(-1, |part| part as i32) };
+
+        match format {
+            PlyFormat::Ascii => {
+                for p in self.positions.iter() {
+                    writeln!(writer, "{} {} 0", p[0], p[1])?;
+                }
+                for (idx, [a, b, c]) in self.indices.iter().enumerate() {
+                    writeln!(writer, "3 {} {} {} {}", a, b, c, part_of(idx))?;
+                }
+            }
+            PlyFormat::BinaryLittleEndian => {
+                for p in self.positions.iter() {
+                    writer.write_all(&p[0].to_le_bytes())?;
+                    writer.write_all(&p[1].to_le_bytes())?;
+                    writer.write_all(&0.0f32.to_le_bytes())?;
+                }
+                for (idx, triangle) in self.indices.iter().enumerate() {
+                    writer.write_all(&[3u8])?;
+                    for vertex in triangle.iter() {
+                        writer.write_all(&vertex.to_le_bytes())?;
+                    }
+                    writer.write_all(&part_of(idx).to_le_bytes())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// ASCII STL of the mesh extruded from z = 0 to z = thickness.
+    /// Every convex part is written as its own closed solid; the
+    /// triangles of no part, the whole polygon without decomposition,
+    /// are a single solid.
+    pub fn write_stl<W: Write>(&self, mut writer: W, thickness: f32) -> io::Result<()> {
+        let groups = self.groups();
+        for (part, triangles) in groups.iter() {
+            let name = match part {
+                Some(part) => format!("part_{}", part),
+                None => "polygon".to_string(),
+            };
+            writeln!(writer, "solid {}", name)?;
+
+            let point = |idx: u32, z: f32| -> [f32; 3] {
+                let p = self.positions[idx as usize];
+                [p[0], p[1], z]
+            };
+
+            // top and bottom caps, the bottom one reversed to face down
+            for triangle in triangles.iter() {
+                let [a, b, c] = self.indices[*triangle];
+                write_facet(
+                    &mut writer,
+                    [
+                        point(a, thickness),
+                        point(b, thickness),
+                        point(c, thickness),
+                    ],
+                )?;
+                write_facet(&mut writer, [point(a, 0.0), point(c, 0.0), point(b, 0.0)])?;
+            }
+
+            // side walls along the boundary of the group, i.e. along the
+            // directed edges whose reverse is not in the group
+            let mut directed_edges: Vec<[u32; 2]> = vec![];
+            for triangle in triangles.iter() {
+                let [a, b, c] = self.indices[*triangle];
+                directed_edges.extend_from_slice(&[[a, b], [b, c], [c, a]]);
+            }
+            let edge_set: HashSet<[u32; 2]> = directed_edges.iter().cloned().collect();
+            for [a, b] in directed_edges.iter() {
+                if edge_set.contains(&[*b, *a]) {
+                    continue;
+                }
+                write_facet(
+                    &mut writer,
+                    [point(*a, 0.0), point(*b, 0.0), point(*b, thickness)],
+                )?;
+                write_facet(
+                    &mut writer,
+                    [point(*a, 0.0), point(*b, thickness), point(*a, thickness)],
+                )?;
+            }
+
+            writeln!(writer, "endsolid {}", name)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_facet<W: Write>(writer: &mut W, points: [[f32; 3]; 3]) -> io::Result<()> {
+    let u = [
+        points[1][0] - points[0][0],
+        points[1][1] - points[0][1],
+        points[1][2] - points[0][2],
+    ];
+    let v = [
+        points[2][0] - points[0][0],
+        points[2][1] - points[0][1],
+        points[2][2] - points[0][2],
+    ];
+    let mut normal = [
+        u[1] * v[2] - u[2] * v[1],
+        u[2] * v[0] - u[0] * v[2],
+        u[0] * v[1] - u[1] * v[0],
+    ];
+    let length = (normal[0] * normal[0] + normal[1] * normal[1] + normal[2] * normal[2]).sqrt();
+    if length > 0.0 {
+        normal = [normal[0] / length, normal[1] / length, normal[2] / length];
+    }
+
+    writeln!(
+        writer,
+        "  facet normal {} {} {}",
+        normal[0], normal[1], normal[2]
+    )?;
+    writeln!(writer, "    outer loop")?;
+    for p in points.iter() {
+        writeln!(writer, "      vertex {} {} {}", p[0], p[1], p[2])?;
+    }
+    writeln!(writer, "    endloop")?;
+    writeln!(writer, "  endfacet")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::beak_poly;
+
+    #[test]
+    fn test_shared_vertices() {
+        let mesh = beak_poly().mesh();
+        assert_eq!(mesh.positions.len(), 4);
+        assert_eq!(mesh.indices.len(), 2);
+        let mut parts = mesh.triangle_parts.clone();
+        parts.sort_unstable();
+        assert_eq!(parts, vec![Some(0), Some(1)]);
+    }
+
+    #[test]
+    fn test_triangle_outside_the_parts() {
+        // with a part missing, its triangle is grouped on its own
+        let mut polygon = beak_poly();
+        polygon.convex_parts.truncate(1);
+        let mesh = polygon.mesh();
+        let groups = mesh.groups();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].0, Some(0));
+        assert_eq!(groups[1].0, None);
+
+        let mut ply = Vec::new();
+        mesh.write_ply(&mut ply, PlyFormat::Ascii).unwrap();
+        let ply = String::from_utf8(ply).unwrap();
+        assert!(ply
+            .lines()
+            .any(|l| l.starts_with("3 ") && l.ends_with(" -1")));
+    }
+
+    #[test]
+    fn test_obj_and_ply() {
+        let mesh = beak_poly().mesh();
+
+        let mut obj = Vec::new();
+        mesh.write_obj(&mut obj).unwrap();
+        let obj = String::from_utf8(obj).unwrap();
+        assert_eq!(obj.lines().filter(|l| l.starts_with("v ")).count(), 4);
+        assert_eq!(obj.lines().filter(|l| l.starts_with("g part_")).count(), 2);
+
+        let mut ply = Vec::new();
+        mesh.write_ply(&mut ply, PlyFormat::Ascii).unwrap();
+        let ply = String::from_utf8(ply).unwrap();
+        assert!(ply.contains("element face 2"));
+
+        // header + 4 vertices * 3 floats + 2 faces * (1 + 3 * 4 + 4) bytes
+        let mut binary = Vec::new();
+        mesh.write_ply(&mut binary, PlyFormat::BinaryLittleEndian)
+            .unwrap();
+        let header_length = ply.find("end_header\n").unwrap() + "end_header\n".len();
+        let binary_header = ply[..header_length].replace("ascii", "binary_little_endian");
+        assert_eq!(binary.len(), binary_header.len() + 4 * 12 + 2 * 17);
+    }
+
+    #[test]
+    fn test_stl_extrusion() {
+        let mesh = beak_poly().mesh();
+        let mut stl = Vec::new();
+        mesh.write_stl(&mut stl, 1.0).unwrap();
+        let stl = String::from_utf8(stl).unwrap();
+        // two triangular prisms: 2 caps + 3 walls of 2 facets each
+        assert_eq!(stl.matches("solid part_").count(), 4);
+        assert_eq!(stl.matches("facet normal").count(), 2 * 8);
+    }
+}
@@ -0,0 +1,404 @@
+//! Minimum-weight convex partition: the convex decomposition whose
+//! diagonals, between polygon vertices only, have the smallest total
+//! length, e.g. the shortest cuts of a sheet.
+use crate::geometry::{distance, orientation};
+use crate::poly::Poly;
+use crate::validate::{edge_inside, EdgeGrid};
+use cgmath::Point2;
+
+/// Marks a chain that starts at the anchor
+const NO_CHAIN: usize = usize::MAX;
+
+/// Chains of the dynamic programming that start at the same anchor
+/// vertex and, for a reflex anchor, with the same edge.
+struct Run {
+    /// Second vertex of the chains if the anchor is reflex
+    first: Option<usize>,
+    /// Cost of the best chain ending with each chord, by chord id
+    /// relative to the first chord of the anchor
+    cost: Vec<f64>,
+    /// Chord before the last one in the best chain
+    previous: Vec<usize>,
+}
+
+/// The vertices of a ccw outline joined by an edge or a diagonal, for
+/// the increasing chains of the parts.
+struct Chords {
+    /// Vertices d > c with a chord cd, sorted, for every vertex c
+    next: Vec<Vec<usize>>,
+    /// Vertices c < d with a chord cd, sorted, for every vertex d
+    previous: Vec<Vec<usize>>,
+    /// Ids of the chords cd in the order of previous, for every vertex d
+    previous_ids: Vec<Vec<usize>>,
+    /// Id of the first chord starting at every vertex
+    start: Vec<usize>,
+    /// End points of every chord, by id
+    ends: Vec<[usize; 2]>,
+}
+
+impl Chords {
+    fn new(outline: &[[f64; 2]]) -> Chords {
+        let length = outline.len();
+        let grid = EdgeGrid::new(outline);
+        let next: Vec<Vec<usize>> = (0..length)
+            .map(|c| {
+                (c + 1..length)
+                    .filter(|d| edge_inside(outline, &grid, c, *d))
+                    .collect()
+            })
+            .collect();
+        let mut start = vec![0];
+        for ends in next.iter() {
+            start.push(start.last().unwrap() + ends.len());
+        }
+        let mut previous = vec![vec![]; length];
+        let mut previous_ids = vec![vec![]; length];
+        for (c, ends) in next.iter().enumerate() {
+            for (idx, &d) in ends.iter().enumerate() {
+                previous[d].push(c);
+                previous_ids[d].push(start[c] + idx);
+            }
+        }
+        let ends = (0..length)
+            .flat_map(|c| next[c].iter().map(move |d| [c, *d]))
+            .collect();
+        Chords {
+            next,
+            previous,
+            previous_ids,
+            start,
+            ends,
+        }
+    }
+
+    fn id(&self, c: usize, d: usize) -> usize {
+        self.start[c] + self.next[c].binary_search(&d).unwrap()
+    }
+
+    fn count(&self) -> usize {
+        *self.start.last().unwrap()
+    }
+}
+
+/// True if the chain turns left or goes straight on at b
+fn turns_left(a: [f64; 2], b: [f64; 2], c: [f64; 2]) -> bool {
+    let turn = orientation(a, b, c);
+    turn > 0.0
+        || (turn == 0.0 && (b[0] - a[0]) * (c[0] - b[0]) + (b[1] - a[1]) * (c[1] - b[1]) > 0.0)
+}
+
+/// Partition of a ccw outline with a reflex vertex by the dynamic
+/// programming of min_weight_decomposition.  Returns the total length
+/// of the diagonals, the parts as chains of increasing vertices and the
+/// diagonals.
+fn partition(outline: &[[f64; 2]], reflex: &[bool]) -> (f64, Vec<Vec<usize>>, Vec<[usize; 2]>) {
+    let length = outline.len();
+    let chords = Chords::new(outline);
+
+    // cost and chain of the best part on the left of each diagonal,
+    // by chord id, and of the part with the edge from n-1 to 0
+    let mut best = vec![f64::INFINITY; chords.count()];
+    let mut chains: Vec<Vec<usize>> = vec![vec![]; chords.count()];
+    let mut root = (f64::INFINITY, vec![]);
+    // the runs are grown one after the other in the same vectors
+    let mut run = Run {
+        first: None,
+        cost: vec![],
+        previous: vec![],
+    };
+
+    for a in (0..length).rev() {
+        // a run of a reflex anchor closes chains only at vertices after
+        // its second one, so with the runs in the order of their second
+        // vertex the best part on the left of ab is known by the time
+        // the run starting with ab begins
+        let firsts: Vec<Option<usize>> = if reflex[a] {
+            chords.next[a].iter().map(|c| Some(*c)).collect()
+        } else {
+            vec![None]
+        };
+        let offset = chords.start[a];
+        for first in firsts {
+            run.first = first;
+            run.cost.clear();
+            run.cost.resize(chords.count() - offset, f64::INFINITY);
+            run.previous.clear();
+            run.previous.resize(chords.count() - offset, NO_CHAIN);
+            // the chains go on from the anchor to vertices from low on
+            let low = first.unwrap_or(a + 1);
+
+            for b in low..length {
+                // extend the chains by the chords ending at b
+                let from_low = chords.previous[b].partition_point(|c| *c < low);
+                for (&c, &id) in chords.previous[b][from_low..]
+                    .iter()
+                    .zip(chords.previous_ids[b][from_low..].iter())
+                {
+                    let id = id - offset;
+                    let weight = if b == c + 1 {
+                        0.0
+                    } else {
+                        distance(outline[c], outline[b]) + best[id + offset]
+                    };
+                    // only the chains of a reflex anchor's run through its
+                    // second vertex come straight from the anchor
+                    let lowest = if run.first.is_none() || c == low {
+                        a
+                    } else {
+                        low
+                    };
+                    let from_lowest = chords.previous[c].partition_point(|p| *p < lowest);
+                    for (&p, &before) in chords.previous[c][from_lowest..]
+                        .iter()
+                        .zip(chords.previous_ids[c][from_lowest..].iter())
+                    {
+                        let before = before - offset;
+                        let cost = run.cost[before] + weight;
+                        if cost < run.cost[id] && turns_left(outline[p], outline[c], outline[b]) {
+                            run.cost[id] = cost;
+                            run.previous[id] = before;
+                        }
+                    }
+                }
+
+                // close the chains ending at b by the diagonal ab, if that
+                // improves on the runs before
+                let is_root = a == 0 && b == length - 1;
+                let closes = b > a + 1
+                    && (is_root || chords.next[a].binary_search(&b).is_ok())
+                    && run.first.map_or(true, |first| {
+                        turns_left(outline[b], outline[a], outline[first])
+                    });
+                if closes {
+                    let mut closing = if is_root {
+                        (root.0, NO_CHAIN)
+                    } else {
+                        (best[chords.id(a, b)], NO_CHAIN)
+                    };
+                    for (&c, &id) in chords.previous[b][from_low..]
+                        .iter()
+                        .zip(chords.previous_ids[b][from_low..].iter())
+                    {
+                        let id = id - offset;
+                        if run.cost[id] < closing.0
+                            && turns_left(outline[c], outline[b], outline[a])
+                        {
+                            closing = (run.cost[id], id);
+                        }
+                    }
+
+                    let (cost, mut id) = closing;
+                    if id != NO_CHAIN {
+                        let mut chain = vec![b];
+                        while id != NO_CHAIN {
+                            chain.push(chords.ends[id + offset][0]);
+                            id = run.previous[id];
+                        }
+                        chain.reverse();
+                        if is_root {
+                            root = (cost, chain);
+                        } else {
+                            let id = chords.id(a, b);
+                            best[id] = cost;
+                            chains[id] = chain;
+                        }
+                    }
+                }
+
+                // chains starting with the chord ab
+                if run.first.map_or(true, |first| first == b) {
+                    if let Ok(id) = chords.next[a].binary_search(&b) {
+                        run.cost[id] = if b == a + 1 {
+                            0.0
+                        } else {
+                            distance(outline[a], outline[b]) + best[offset + id]
+                        };
+                    }
+                }
+            }
+        }
+    }
+
+    // the parts, from the root down the diagonals of their chains
+    let mut parts = vec![];
+    let mut diagonals = vec![];
+    let mut todo = vec![root.1];
+    while let Some(chain) = todo.pop() {
+        for pair in chain.windows(2) {
+            if pair[1] > pair[0] + 1 {
+                diagonals.push([pair[0], pair[1]]);
+                todo.push(std::mem::take(&mut chains[chords.id(pair[0], pair[1])]));
+            }
+        }
+        parts.push(chain);
+    }
+    (root.0, parts, diagonals)
+}
+
+impl Poly {
+    /// Convex decomposition minimising the total length of the diagonals
+    /// instead of the number of parts.  Stores the parts and the
+    /// diagonals in convex_parts and essential_diagonals, in the format
+    /// and the order of decomposition, and returns the total length of
+    /// the diagonals.  Expects the vertices in ccw order, as left by triang.
+    ///
+    /// Dynamic programming over the diagonals: the part on the left of a
+    /// diagonal ab, a < b, is a convex chain a, c1, ..., b of edges and
+    /// diagonals, and the polygon between each diagonal of the chain and
+    /// the boundary is partitioned in the same way.  The chains are grown
+    /// from every anchor a at once; only at a reflex anchor does the
+    /// angle at a depend on c1, which then gets chains of its own.
+    ///
+    /// Growing the chains of an anchor tries every pair of chords that
+    /// meet at a vertex after it, once for a convex anchor and once for
+    /// every c1 of a reflex one.  That takes O(n^4) time for polygons
+    /// whose vertices see most of the others, and up to O(n^5) if many
+    /// of those are reflex, e.g. a convex polygon with every other vertex
+    /// pushed in slightly, where 200 vertices take several seconds.
+    /// Polygons with few long diagonals are far faster: random polygons
+    /// of a thousand vertices take a few seconds.  A convex polygon is
+    /// returned as a single part right away.  The memory is linear in
+    /// the number of chords.
+    pub fn min_weight_decomposition(&mut self) -> f64 {
+        let outline: Vec<[f64; 2]> = self
+            .vertices
+            .iter()
+            .map(|v| [v[0] as f64, v[1] as f64])
+            .collect();
+        let length = outline.len();
+        let reflex: Vec<bool> = (0..length)
+            .map(|a| {
+                orientation(
+                    outline[(a + length - 1) % length],
+                    outline[a],
+                    outline[(a + 1) % length],
+                ) < 0.0
+            })
+            .collect();
+        let (cut, mut parts, mut diagonals) = if reflex.contains(&true) {
+            partition(&outline, &reflex)
+        } else {
+            (0.0, vec![(0..length).collect()], vec![])
+        };
+
+        self.convex_parts.clear();
+        self.essential_diagonals.clear();
+        self.steiner_points.clear();
+        // the chains start at their smallest vertex, like the parts of glue
+        parts.sort_by_key(|part| (part[0], part[1]));
+        for part in parts {
+            let mut convex_part = vec![];
+            for (idx, vertex) in part.iter().enumerate() {
+                for &position in [*vertex, part[(idx + 1) % part.len()]].iter() {
+                    let [x, y] = self.vertices[position];
+                    convex_part.push(Point2::new(x, y));
+                }
+            }
+            self.convex_parts.push(convex_part);
+        }
+        diagonals.sort_unstable();
+        for [c, d] in diagonals {
+            let (from, to) = (self.vertices[c], self.vertices[d]);
+            self.essential_diagonals
+                .push(vec![[from[0], -from[1]], [to[0], -to[1]]]);
+        }
+        cut
+    }
+
+    /// Total length of the essential diagonals, i.e. of the cuts that
+    /// separate the convex parts.
+    pub fn cut_length(&self) -> f64 {
+        self.essential_diagonals
+            .iter()
+            .map(|diagonal| {
+                let [from, to] = [diagonal[0], diagonal[1]];
+                distance(
+                    [from[0] as f64, from[1] as f64],
+                    [to[0] as f64, to[1] as f64],
+                )
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{random_polygon, Method};
+
+    fn partitioned(vertices: Vec<[f32; 2]>) -> (Poly, f64) {
+        let mut poly = Poly {
+            vertices,
+            ..Default::default()
+        };
+        poly.triang();
+        let cut = poly.min_weight_decomposition();
+        (poly, cut)
+    }
+
+    #[test]
+    fn test_shortest_cut_of_an_l_shape() {
+        let l_shape = vec![
+            [0.0, 0.0],
+            [4.0, 0.0],
+            [4.0, 1.0],
+            [1.0, 1.0],
+            [1.0, 4.0],
+            [0.0, 4.0],
+        ];
+        let (poly, cut) = partitioned(l_shape);
+        assert!((cut - 2f64.sqrt()).abs() < 1e-9);
+        assert_eq!(
+            poly.essential_diagonals,
+            vec![vec![[0.0, 0.0], [1.0, -1.0]]]
+        );
+        assert_eq!(poly.convex_parts.len(), 2);
+        assert_eq!(poly.check_decomposition(), Ok(()));
+
+        let square = vec![[0.0, 0.0], [4.0, 0.0], [4.0, 4.0], [0.0, 4.0]];
+        let (poly, cut) = partitioned(square);
+        assert_eq!(cut, 0.0);
+        assert_eq!(poly.convex_parts.len(), 1);
+
+        // convex polygons skip the dynamic programming, which would
+        // take hours at this size
+        let circle: Vec<[f32; 2]> = (0..2000)
+            .map(|idx| {
+                let angle = idx as f32 / 2000.0 * std::f32::consts::TAU;
+                [angle.cos(), angle.sin()]
+            })
+            .collect();
+        let (poly, cut) = partitioned(circle);
+        assert_eq!(cut, 0.0);
+        assert_eq!(poly.convex_parts.len(), 1);
+        assert!(poly.essential_diagonals.is_empty());
+    }
+
+    #[test]
+    fn test_cuts_no_longer_than_hertel_mehlhorn() {
+        for method in Method::ALL.iter() {
+            for seed in 0..3 {
+                let (poly, cut) = partitioned(random_polygon(*method, 30, seed));
+                assert_eq!(poly.check_decomposition(), Ok(()), "{} {}", method, seed);
+                assert!((cut - poly.cut_length()).abs() < 1e-6);
+
+                let mut hm = Poly {
+                    vertices: poly.vertices.clone(),
+                    ..Default::default()
+                };
+                hm.triang();
+                hm.decomposition();
+                assert!(cut <= hm.cut_length() + 1e-6, "{} {}", method, seed);
+
+                // parts in the canonical order of decomposition
+                let cycles = poly.convex_part_cycles();
+                let index = |p: &Point2<f32>| poly.vertices.iter().position(|v| *v == [p.x, p.y]);
+                let keys: Vec<(Option<usize>, Option<usize>)> = cycles
+                    .iter()
+                    .map(|cycle| (index(&cycle[0]), index(&cycle[1])))
+                    .collect();
+                assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+            }
+        }
+    }
+}
@@ -0,0 +1,279 @@
+//! Navigation mesh on the convex parts: the parts are the nodes, the
+//! edges they share are the portals between them.  Paths are found by
+//! A* over the parts and pulled tight with the funnel algorithm.
+use crate::geometry::{convex_contains, cross, Open};
+use crate::poly::Poly;
+use cgmath::{MetricSpace, Point2};
+use std::collections::BinaryHeap;
+
+/// Edge shared by two convex parts
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Portal {
+    /// Part on the other side of the edge
+    pub neighbour: usize,
+    /// End of the edge on the left when walking into the neighbour
+    pub left: Point2<f32>,
+    /// End of the edge on the right when walking into the neighbour
+    pub right: Point2<f32>,
+}
+
+impl Portal {
+    fn middle(&self) -> Point2<f32> {
+        Point2::new(
+            (self.left.x + self.right.x) / 2.0,
+            (self.left.y + self.right.y) / 2.0,
+        )
+    }
+}
+
+/// Convex parts of a polygon with the portals between them
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NavMesh {
+    /// Parts as ccw cycles, numbered as in Poly::convex_parts
+    pub parts: Vec<Vec<Point2<f32>>>,
+    /// Portals out of every part
+    pub portals: Vec<Vec<Portal>>,
+}
+
+impl Poly {
+    /// Builds the navigation mesh of the convex parts.  Two parts are
+    /// adjacent if they share an edge, as the parts of decomposition
+    /// and refine_parts do.
+    pub fn navmesh(&self) -> NavMesh {
+        // the part is on the left of its ccw edges, so walking out of it
+        // the end of an edge is on the left
+        let portals = self
+            .part_adjacency()
+            .neighbours
+            .into_iter()
+            .map(|neighbours| {
+                neighbours
+                    .into_iter()
+                    .map(|neighbour| Portal {
+                        neighbour: neighbour.index,
+                        left: neighbour.edge[1],
+                        right: neighbour.edge[0],
+                    })
+                    .collect()
+            })
+            .collect();
+        NavMesh {
+            parts: self.convex_part_cycles(),
+            portals,
+        }
+    }
+}
+
+impl NavMesh {
+    /// Index of a part containing the point, None if it lies outside
+    /// the polygon.  A point on a portal may be found in either part.
+    pub fn locate(&self, point: [f32; 2]) -> Option<usize> {
+        let point = Point2::new(point[0], point[1]);
+        self.parts
+            .iter()
+            .position(|part| convex_contains(part, point))
+    }
+
+    /// Parts crossed by a shortest path from the start to the goal
+    /// through the middles of the portals, found by A*.  None if the
+    /// start or the goal lies outside the polygon.
+    pub fn corridor(&self, start: [f32; 2], goal: [f32; 2]) -> Option<Vec<usize>> {
+        let (first, last) = (self.locate(start)?, self.locate(goal)?);
+        let goal = Point2::new(goal[0], goal[1]);
+
+        // parts are entered through the middle of a portal
+        let mut entry = vec![Point2::new(start[0], start[1]); self.parts.len()];
+        let mut cost = vec![f32::INFINITY; self.parts.len()];
+        let mut came_from: Vec<Option<usize>> = vec![None; self.parts.len()];
+        let mut open = BinaryHeap::new();
+        cost[first] = 0.0;
+        open.push(Open {
+            cost: f64::from(entry[first].distance(goal)),
+            index: first,
+        });
+
+        while let Some(Open {
+            cost: estimate,
+            index: part,
+        }) = open.pop()
+        {
+            if part == last {
+                let mut corridor = vec![last];
+                while let Some(previous) = came_from[*corridor.last().unwrap()] {
+                    corridor.push(previous);
+                }
+                corridor.reverse();
+                return Some(corridor);
+            }
+            // skip entries superseded by a cheaper one
+            if estimate > f64::from(cost[part] + entry[part].distance(goal)) {
+                continue;
+            }
+            for portal in self.portals[part].iter() {
+                let middle = portal.middle();
+                let through = cost[part] + entry[part].distance(middle);
+                if through < cost[portal.neighbour] {
+                    cost[portal.neighbour] = through;
+                    entry[portal.neighbour] = middle;
+                    came_from[portal.neighbour] = Some(part);
+                    open.push(Open {
+                        cost: f64::from(through + middle.distance(goal)),
+                        index: portal.neighbour,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    /// Shortest path from the start to the goal within the corridor of
+    /// parts, as a polyline from the start to the goal whose inner
+    /// points are polygon vertices.  None if the start or the goal lies
+    /// outside the polygon.
+    pub fn find_path(&self, start: [f32; 2], goal: [f32; 2]) -> Option<Vec<[f32; 2]>> {
+        let corridor = self.corridor(start, goal)?;
+        let (start, goal) = (
+            Point2::new(start[0], start[1]),
+            Point2::new(goal[0], goal[1]),
+        );
+
+        let mut portals = vec![(start, start)];
+        for pair in corridor.windows(2) {
+            let portal = self.portals[pair[0]]
+                .iter()
+                .find(|portal| portal.neighbour == pair[1])
+                .unwrap();
+            portals.push((portal.left, portal.right));
+        }
+        portals.push((goal, goal));
+
+        let path = string_pull(&portals);
+        Some(path.into_iter().map(|p| [p.x, p.y]).collect())
+    }
+}
+
+/// Simple stupid funnel algorithm: pulls the path through the (left,
+/// right) portals tight.  The first and the last portal are the start
+/// and the goal.
+fn string_pull(portals: &[(Point2<f32>, Point2<f32>)]) -> Vec<Point2<f32>> {
+    let mut path = vec![portals[0].0];
+    let mut apex = portals[0].0;
+    let (mut left, mut right) = portals[0];
+    let (mut left_idx, mut right_idx) = (0, 0);
+
+    let mut idx = 1;
+    while idx < portals.len() {
+        let (portal_left, portal_right) = portals[idx];
+
+        // narrow the funnel from the right unless it crosses the left side
+        if cross(apex, right, portal_right) >= 0.0 {
+            if apex == right || cross(apex, left, portal_right) < 0.0 {
+                right = portal_right;
+                right_idx = idx;
+            } else {
+                path.push(left);
+                apex = left;
+                right = apex;
+                right_idx = left_idx;
+                idx = left_idx + 1;
+                continue;
+            }
+        }
+
+        // and from the left unless it crosses the right side
+        if cross(apex, left, portal_left) <= 0.0 {
+            if apex == left || cross(apex, right, portal_left) > 0.0 {
+                left = portal_left;
+                left_idx = idx;
+            } else {
+                path.push(right);
+                apex = right;
+                left = apex;
+                left_idx = right_idx;
+                idx = right_idx + 1;
+                continue;
+            }
+        }
+        idx += 1;
+    }
+
+    path.push(portals[portals.len() - 1].0);
+    // a corner is reached again when the funnel restarts at it
+    path.dedup();
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{random_polygon, Method};
+    use crate::geometry::segments_cross;
+
+    fn navmesh(vertices: Vec<[f32; 2]>) -> (Poly, NavMesh) {
+        let mut poly = Poly {
+            vertices,
+            ..Default::default()
+        };
+        poly.triang();
+        poly.decomposition();
+        let navmesh = poly.navmesh();
+        (poly, navmesh)
+    }
+
+    #[test]
+    fn test_path_around_the_bend() {
+        let u_shape = vec![
+            [0.0, 0.0],
+            [3.0, 0.0],
+            [3.0, 3.0],
+            [2.0, 3.0],
+            [2.0, 1.0],
+            [1.0, 1.0],
+            [1.0, 3.0],
+            [0.0, 3.0],
+        ];
+        let (_, navmesh) = navmesh(u_shape);
+        let portals: usize = navmesh.portals.iter().map(|p| p.len()).sum();
+        assert_eq!(portals, 2 * (navmesh.parts.len() - 1));
+
+        let path = navmesh.find_path([0.5, 2.5], [2.5, 2.5]).unwrap();
+        assert_eq!(path, vec![[0.5, 2.5], [1.0, 1.0], [2.0, 1.0], [2.5, 2.5]]);
+        let path = navmesh.find_path([0.5, 2.5], [0.5, 0.5]).unwrap();
+        assert_eq!(path, vec![[0.5, 2.5], [0.5, 0.5]]);
+
+        assert_eq!(navmesh.locate([1.5, 2.0]), None);
+        assert_eq!(navmesh.find_path([0.5, 2.5], [1.5, 2.0]), None);
+    }
+
+    #[test]
+    fn test_paths_stay_inside() {
+        for method in [Method::Star, Method::SpacePartition, Method::Comb] {
+            for seed in 0..4 {
+                let (poly, navmesh) = navmesh(random_polygon(method, 60, seed));
+                let center = |part: &[Point2<f32>]| {
+                    let n = part.len() as f32;
+                    [
+                        part.iter().map(|p| p.x).sum::<f32>() / n,
+                        part.iter().map(|p| p.y).sum::<f32>() / n,
+                    ]
+                };
+                let start = center(&navmesh.parts[0]);
+                let goal = center(navmesh.parts.last().unwrap());
+                let path = navmesh.find_path(start, goal).unwrap();
+                assert_eq!((path[0], *path.last().unwrap()), (start, goal));
+
+                // no segment of the path crosses an edge of the polygon
+                let length = poly.vertices.len();
+                let point = |p: [f32; 2]| [p[0] as f64, p[1] as f64];
+                for segment in path.windows(2) {
+                    let (a, b) = (point(segment[0]), point(segment[1]));
+                    for idx in 0..length {
+                        let c = point(poly.vertices[idx]);
+                        let d = point(poly.vertices[(idx + 1) % length]);
+                        assert!(!segments_cross(a, b, c, d), "{} {}", method, seed);
+                    }
+                }
+            }
+        }
+    }
+}
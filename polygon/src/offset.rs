@@ -0,0 +1,285 @@
+//! Offsetting: the polygon shrunk (inset) or grown (outset) by a
+//! distance, e.g. by the radius of an agent before the decomposition.
+//!
+//! The offset is the wavefront of the straight skeleton stopped at the
+//! distance, which splits an inset where it disconnects.  An outset is
+//! the inset of the outside of the polygon, i.e. the wavefront of the
+//! reversed outline.  Round and square joins are edges of zero length
+//! added at the corners where the offset edges move apart, so that the
+//! wavefront grows them like any other edge.
+use crate::poly::Poly;
+use crate::skeleton::{Line, Wavefronts};
+use smart_default::SmartDefault;
+
+/// Shape of the offset where two offset edges move apart: at the reflex
+/// vertices of an inset and the convex vertices of an outset
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Join {
+    /// The edges are extended until they meet
+    Miter,
+    /// A circular arc around the vertex
+    Round,
+    /// The miter cut off at the distance from the vertex
+    Square,
+}
+
+/// Options of Poly::offset
+#[derive(SmartDefault, Clone, Debug, PartialEq)]
+pub struct OffsetOptions {
+    #[default(Join::Miter)]
+    pub join: Join,
+    /// Longest miter, as a multiple of the distance; longer miters are
+    /// squared off at this length
+    #[default = 2.0]
+    pub miter_limit: f32,
+    /// Largest gap between a round join and the true arc, as a fraction
+    /// of the distance
+    #[default = 0.01]
+    pub arc_tolerance: f32,
+}
+
+fn rotate(vector: [f64; 2], angle: f64) -> [f64; 2] {
+    let (sin, cos) = angle.sin_cos();
+    [
+        vector[0] * cos - vector[1] * sin,
+        vector[0] * sin + vector[1] * cos,
+    ]
+}
+
+impl Poly {
+    /// The polygon offset by the distance: shrunk for a negative
+    /// distance, grown for a positive one.  An inset can fall apart into
+    /// several polygons or vanish; an outset is a single polygon, whose
+    /// holes, where it closes around a notch, are filled.  The polygons
+    /// are simple and ccw, ready for triang.  Takes the vertices in either
+    /// orientation.
+    pub fn offset(&self, distance: f32, options: &OffsetOptions) -> Vec<Poly> {
+        let mut outline: Vec<[f64; 2]> = self
+            .vertices
+            .iter()
+            .map(|v| [v[0] as f64, v[1] as f64])
+            .collect();
+        let length = outline.len();
+        if length < 3 || !distance.is_finite() {
+            return vec![];
+        }
+        // the wavefront moves to the left of the edges
+        if (self.doubled_area() < 0.0) != (distance > 0.0) {
+            outline.reverse();
+        }
+        let until = distance.abs() as f64;
+
+        let normals: Vec<[f64; 2]> = (0..length)
+            .map(|idx| {
+                let (a, b) = (outline[idx], outline[(idx + 1) % length]);
+                let norm = (b[0] - a[0]).hypot(b[1] - a[1]);
+                [(a[1] - b[1]) / norm, (b[0] - a[0]) / norm]
+            })
+            .collect();
+        let mut lines: Vec<Line> = (0..length)
+            .map(|idx| Line::new(outline[idx], [normals[idx][1], -normals[idx][0]], 1.0))
+            .collect();
+
+        // the lines at every vertex, from the edge in to the edge out
+        let mut corners = vec![];
+        for (idx, point) in outline.iter().enumerate() {
+            let (edge_in, edge_out) = ((idx + length - 1) % length, idx);
+            let (normal_in, normal_out) = (normals[edge_in], normals[edge_out]);
+            // negative where the offset edges move apart
+            let turn = (normal_in[0] * normal_out[1] - normal_in[1] * normal_out[0])
+                .atan2(normal_in[0] * normal_out[0] + normal_in[1] * normal_out[1]);
+            let joins: Vec<(f64, f64)> = if turn >= 0.0 {
+                vec![]
+            } else {
+                match options.join {
+                    Join::Miter => {
+                        let limit = (options.miter_limit as f64).max(1.0);
+                        if 1.0 / (turn / 2.0).cos() > limit {
+                            vec![(turn / 2.0, limit)]
+                        } else {
+                            vec![]
+                        }
+                    }
+                    Join::Square => vec![(turn / 2.0, 1.0)],
+                    Join::Round => {
+                        // the joins are tangents of the arc; each turns by
+                        // a step whose gap to the arc is within tolerance
+                        let tolerance = (options.arc_tolerance as f64).max(1e-6);
+                        let step = 2.0 * (1.0 / (1.0 + tolerance)).acos();
+                        let steps = (turn.abs() / step).ceil() as usize;
+                        (1..steps)
+                            .map(|k| (turn * k as f64 / steps as f64, 1.0))
+                            .collect()
+                    }
+                }
+            };
+
+            let mut previous = edge_in;
+            for (angle, speed) in joins {
+                let normal = rotate(normal_in, angle);
+                lines.push(Line::new(*point, [normal[1], -normal[0]], speed));
+                corners.push([idx, previous, lines.len() - 1]);
+                previous = lines.len() - 1;
+            }
+            corners.push([idx, previous, edge_out]);
+        }
+
+        let mut wavefronts = Wavefronts::with_lines(lines, &outline, &corners);
+        wavefronts.run(until);
+
+        let mut polys = vec![];
+        for waves in wavefronts.wavefronts.iter() {
+            let mut vertices: Vec<[f32; 2]> = waves
+                .iter()
+                .map(|wave| [wave.position[0] as f32, wave.position[1] as f32])
+                .collect();
+            vertices.dedup();
+            while vertices.len() > 1 && vertices.first() == vertices.last() {
+                vertices.pop();
+            }
+            let mut poly = Poly {
+                vertices,
+                ..Default::default()
+            };
+            let area = poly.doubled_area();
+            // an outset keeps the cw outer boundary of the outside, not
+            // the ccw boundaries of its holes
+            if poly.vertices.len() < 3 || area == 0.0 || (area < 0.0) != (distance > 0.0) {
+                continue;
+            }
+            if distance > 0.0 {
+                poly.vertices.reverse();
+            }
+            polys.push(poly);
+        }
+        polys
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{random_polygon, Method};
+
+    fn area(poly: &Poly) -> f64 {
+        poly.doubled_area() / 2.0
+    }
+
+    fn offset(vertices: Vec<[f32; 2]>, distance: f32, join: Join) -> Vec<Poly> {
+        let poly = Poly {
+            vertices,
+            ..Default::default()
+        };
+        let options = OffsetOptions {
+            join,
+            ..Default::default()
+        };
+        poly.offset(distance, &options)
+    }
+
+    #[test]
+    fn test_offset_square() {
+        let square = vec![[0.0, 0.0], [2.0, 0.0], [2.0, 2.0], [0.0, 2.0]];
+        let inset = offset(square.clone(), -0.5, Join::Round);
+        assert_eq!(inset.len(), 1);
+        assert_eq!(area(&inset[0]), 1.0);
+        assert!(offset(square.clone(), -1.5, Join::Miter).is_empty());
+
+        // a miter of a right angle is within the default limit
+        let miter = offset(square.clone(), 1.0, Join::Miter);
+        assert_eq!(miter[0].vertices.len(), 4);
+        assert_eq!(area(&miter[0]), 16.0);
+        let round = offset(square.clone(), 1.0, Join::Round);
+        let circle = 4.0 + 8.0 + std::f64::consts::PI;
+        assert!(area(&round[0]) > circle && area(&round[0]) < circle * 1.01);
+        let square = offset(square, 1.0, Join::Square);
+        assert_eq!(square[0].vertices.len(), 8);
+        assert!((area(&square[0]) - (4.0 + 8.0 * 2f64.sqrt())).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_inset_splits_and_outset_fills() {
+        // two squares joined by a narrow corridor
+        let dumbbell = vec![
+            [0.0, 0.0],
+            [2.0, 0.0],
+            [2.0, 0.9],
+            [3.0, 0.9],
+            [3.0, 0.0],
+            [5.0, 0.0],
+            [5.0, 2.0],
+            [3.0, 2.0],
+            [3.0, 1.1],
+            [2.0, 1.1],
+            [2.0, 2.0],
+            [0.0, 2.0],
+        ];
+        for join in [Join::Miter, Join::Round, Join::Square] {
+            let mut parts = offset(dumbbell.clone(), -0.2, join);
+            assert_eq!(parts.len(), 2);
+            for part in parts.iter_mut() {
+                assert_eq!(part.check_simple(), Ok(()));
+                part.triang();
+                assert!(!part.triangles.is_empty());
+            }
+        }
+
+        // a C whose gap closes
+        let c_shape = vec![
+            [0.0, 0.0],
+            [3.0, 0.0],
+            [3.0, 1.0],
+            [1.0, 1.0],
+            [1.0, 2.0],
+            [3.0, 2.0],
+            [3.0, 3.0],
+            [0.0, 3.0],
+        ];
+        let grown = offset(c_shape, 0.6, Join::Miter);
+        assert_eq!(grown.len(), 1);
+        assert_eq!(grown[0].check_simple(), Ok(()));
+        assert!((area(&grown[0]) - 4.2 * 4.2).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_offsets_triangulate() {
+        for method in Method::ALL.iter() {
+            for seed in 0..3 {
+                let poly = Poly {
+                    vertices: random_polygon(*method, 40, seed),
+                    ..Default::default()
+                };
+                let original = area(&poly).abs();
+                for &distance in [-0.02, -0.005, 0.005, 0.02].iter() {
+                    for &join in [Join::Miter, Join::Round, Join::Square].iter() {
+                        let options = OffsetOptions {
+                            join,
+                            ..Default::default()
+                        };
+                        let mut total = 0.0;
+                        for mut part in poly.offset(distance, &options) {
+                            assert_eq!(part.check_simple(), Ok(()), "{} {}", method, seed);
+                            assert!(area(&part) > 0.0);
+                            total += area(&part);
+                            part.triang();
+                        }
+                        if distance < 0.0 {
+                            assert!(total < original, "{} {}", method, seed);
+                        } else {
+                            assert!(total > original, "{} {}", method, seed);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_offset_of_a_large_star() {
+        let star = random_polygon(Method::Star, 400, 0);
+        let inset = offset(star.clone(), -0.01, Join::Round);
+        let outset = offset(star, 0.01, Join::Round);
+        assert!(inset.len() > 1);
+        assert_eq!(outset.len(), 1);
+    }
+}
@@ -1,9 +1,20 @@
+use crate::error::PolyError;
 use cgmath::Point2;
 use smart_default::SmartDefault;
 use spade::delaunay::DelaunayWalkLocate;
 use spade::delaunay::*;
 use spade::kernels::FloatKernel;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
+
+// labels of the triangulation edges used by the decomposition
+pub(crate) const NON_ESSENTIAL: u8 = 0;
+pub(crate) const ESSENTIAL: u8 = 1;
+pub(crate) const POLY_EDGE: u8 = 2;
+pub(crate) const OUTSIDE: u8 = 3;
+
+/// Labels of the triangulation edges, the result of the labeling
+/// stage of the decomposition
+pub(crate) struct EdgeLabels(Vec<u8>);
 
 #[derive(SmartDefault)]
 pub struct Poly {
@@ -12,9 +23,18 @@ pub struct Poly {
     pub triangles: Vec<[Point2<f32>; 3]>,
     pub triangulation:
         ConstrainedDelaunayTriangulation<Point2<f32>, FloatKernel, DelaunayWalkLocate>,
+    /// Handle in the triangulation of the vertex at each position of
+    /// vertices.  triang inserts the vertices in order, the edits
+    /// insert and remove single vertices, which permutes the handles.
+    pub handles: Vec<usize>,
+    /// Position in vertices of each vertex handle, the inverse of handles
+    pub positions: Vec<usize>,
     pub bad_edges: HashSet<usize>,
     pub essential_diagonals: Vec<Vec<[f32; 2]>>,
     pub convex_parts: Vec<Vec<Point2<f32>>>,
+    /// Vertices of the convex parts that are not polygon vertices,
+    /// added by refine_parts
+    pub steiner_points: Vec<[f32; 2]>,
 }
 
 impl Poly {
@@ -33,6 +53,12 @@ impl Poly {
             self.initialise_triangulation();
         }
 
+        self.collect_triangles();
+    }
+
+    /// Finds the bad edges of the triangulation and stores the
+    /// triangles inside the polygon in the triangles field.
+    pub(crate) fn collect_triangles(&mut self) {
         // Need to collect and remove the "bad edges" of the triangulation
         // They occur because the triangulation is of the convex hull of
         // the poly vertices so there will be some triangulation edges 
@@ -59,7 +85,8 @@ impl Poly {
 
         // loop through the neighbours of the bad edges
         // if they are outside the poly they are also inserted in the bad_edges vector
-        for _ in 0..10 {
+        // until no new ones are found, however deep the pockets of the poly are
+        while !to_be_visited.is_empty() {
             let mut newer_bad_edges: HashSet<usize> = HashSet::new();
 
             for edge in to_be_visited.iter() {
@@ -72,290 +99,354 @@ impl Poly {
                     }
                 }
             }
+            newer_bad_edges.retain(|e| !self.bad_edges.contains(e));
             self.bad_edges = self.bad_edges.union(&newer_bad_edges).cloned().collect();
             to_be_visited = newer_bad_edges;
         }
 
         // loop through the triangles and check if they contain bad edges
         // if yes, then ignore; if no, then add to the triangles field
+        self.triangles.clear();
         for face in self.triangulation.triangles() {
             let triangle = face.as_triangle();
-            let mut should_add = true;
-
-            for edge in face.adjacent_edges() {
-                let fixed_edge = edge.fix();
-                let other_edge = edge.sym().fix();
-
-                if self.bad_edges.contains(&fixed_edge) || self.bad_edges.contains(&other_edge) {
-                    should_add = false;
-                }
-            }
-
-            if should_add {
+            if self.inside(face.fix()) {
                 self.triangles
                     .push([*triangle[0], *triangle[1], *triangle[2]]);
             }
         }
     }
 
+    /// Returns true if the face is a triangle inside the polygon,
+    /// i.e. none of its edges is a bad edge
+    pub(crate) fn inside(&self, face: FixedFaceHandle) -> bool {
+        face != self.triangulation.infinite_face().fix()
+            && self.triangulation.face(face).adjacent_edges().all(|edge| {
+                !self.bad_edges.contains(&edge.fix()) && !self.bad_edges.contains(&edge.sym().fix())
+            })
+    }
+
     /// Implementation of the Hertel-Mehlhorn convex decomposition
     /// of a polygon.  Starts from a triangulation and
     /// eliminates all triangle edges that are not essential, i.e.
     /// whose elimination does not make an angle concave.
+    /// Steiner points of an earlier refinement are dropped.
     pub fn decomposition(&mut self) {
-        // labels: 2 = poly edges, 1 = essential, 0 = non-essential
-        let mut edge_labels: HashMap<FixedEdgeHandle, i32> = HashMap::new();
+        self.steiner_points.clear();
+        let edge_labels = self.label_diagonals();
+        self.glue(&edge_labels);
+    }
 
-        // ignore the bad edges from the triangulation
-        let triangulation_edges = self
-            .triangulation
-            .edges()
-            .filter(|e| !self.bad_edges.contains(&e.fix()));
+    /// Labeling stage of the decomposition: finds the essential
+    /// diagonals, stores them in essential_diagonals and returns the
+    /// labels of all triangulation edges for glue.
+    pub(crate) fn label_diagonals(&mut self) -> EdgeLabels {
+        let mut edge_labels = self.label_edges();
+        let vertices: Vec<FixedVertexHandle> = (0..self.vertices.len()).collect();
+        let essentials = self.find_essentials(&vertices, &mut edge_labels);
+        self.remove_inessential(&vertices, &mut edge_labels);
+
+        // keep the essentials that survived the second traversal
+        for fixed_edge in essentials {
+            if edge_labels[fixed_edge] == ESSENTIAL {
+                let edge = self.triangulation.edge(fixed_edge);
+                let (from, to) = (*edge.from(), *edge.to());
+                self.essential_diagonals
+                    .push(vec![[from.x, -from.y], [to.x, -to.y]]);
+            }
+        }
+        EdgeLabels(edge_labels)
+    }
 
-        // label the remaining edges
-        for edge in triangulation_edges {
+    /// Labels both halves of every triangulation edge, indexed by
+    /// fixed edge handle.  Edges outside the polygon are labelled OUTSIDE.
+    fn label_edges(&self) -> Vec<u8> {
+        let mut edge_labels = vec![OUTSIDE; 2 * self.triangulation.num_edges()];
+        for edge in self.triangulation.edges() {
             let fixed_edge = edge.fix();
             let mirror_edge = edge.sym().fix();
 
-            let mut add_label = |label: i32| {
-                edge_labels.insert(fixed_edge, label);
-                edge_labels.insert(mirror_edge, label);
-            };
+            // ignore the bad edges from the triangulation
+            if self.bad_edges.contains(&fixed_edge) || self.bad_edges.contains(&mirror_edge) {
+                continue;
+            }
 
-            if self.triangulation.is_constraint_edge(fixed_edge) {
-                add_label(2);
+            let label = if self.triangulation.is_constraint_edge(fixed_edge) {
+                POLY_EDGE
             } else {
-                add_label(0);
-            }
+                NON_ESSENTIAL
+            };
+            edge_labels[fixed_edge] = label;
+            edge_labels[mirror_edge] = label;
         }
+        edge_labels
+    }
 
-        // loop over vertices
-        for vertex in self.triangulation.vertices() {
-            let vertex_fixed = vertex.fix();
+    /// First traversal of the vertices: labels the diagonals whose
+    /// removal would make an angle concave as essential.  Returns
+    /// them in the order they were found.
+    pub(crate) fn find_essentials(
+        &self,
+        vertices: &[FixedVertexHandle],
+        edge_labels: &mut [u8],
+    ) -> Vec<FixedEdgeHandle> {
+        let mut essentials = vec![];
+
+        for &vertex_fixed in vertices {
+            let vertex = self.triangulation.vertex(vertex_fixed);
             let next_poly_vertex = *self.triangulation.vertex(self.next_vertex(vertex_fixed));
             let prev_poly_vertex = *self
                 .triangulation
                 .vertex(self.previous_vertex(vertex_fixed));
             let vertex_coords = *vertex;
+            let vertex_convex =
+                self.convex_angle(vertex_coords, prev_poly_vertex, next_poly_vertex);
 
             // store all outgoing (non bad) edges from this vertex
             let mut outgoing_edges: Vec<EdgeHandle<Point2<f32>, CdtEdge>> = vertex
                 .ccw_out_edges()
-                .filter(|e| edge_labels.get(&e.fix()).is_some())
+                .filter(|e| edge_labels[e.fix()] != OUTSIDE)
                 .collect();
 
-            loop {
-                let mut check_again = false;
+            // the edges before idx are all labelled by now, so after
+            // removing an edge there is no need to start over
+            let mut idx = 0;
+            while idx < outgoing_edges.len() {
+                let edge = outgoing_edges[idx];
 
-                // loop through the outgoing edges of the vertex
-                for idx in 0..outgoing_edges.len() {
-                    let edge = outgoing_edges[idx];
-                    let fixed_edge = edge.fix();
-                    let mirror_edge = edge.sym().fix();
-                    let opposite_vertex = edge.to().fix();
-                    let opp_prev = *self
-                        .triangulation
-                        .vertex(self.previous_vertex(opposite_vertex));
-                    let opp_next = *self.triangulation.vertex(self.next_vertex(opposite_vertex));
-                    let opp_coords = *edge.to();
-
-                    let label = edge_labels.get(&fixed_edge).unwrap(); // ok to unwrap becaus of the filter used for outgoing_edges
-
-                    // if edge is not known to be essential or poly edge
-                    if label == &0 {
-                        // if the vertex and its opposite wrt this diagonal are convex, then not essential
-                        if self.convex_angle(vertex_coords, prev_poly_vertex, next_poly_vertex)
-                            && self.convex_angle(opp_coords, opp_prev, opp_next)
-                        {
-                            outgoing_edges.remove(idx);
-                            check_again = true;
-                            break;
-                        }
-
-                        // check if essential diagonal
-                        // this means check if angle between its preceeding and subsequent edges
-                        // in outgoing_edges is concave
-                        let prev_vert = *outgoing_edges
-                            [(idx + outgoing_edges.len() - 1) % outgoing_edges.len()]
-                        .to();
-                        let next_vert = *outgoing_edges[(idx + 1) % outgoing_edges.len()].to();
-
-                        // note the order switch!
-                        if !self.convex_angle(vertex_coords, next_vert, prev_vert) {
-                            // if essential label with 1
-                            *edge_labels.get_mut(&fixed_edge).unwrap() = 1;
-                            *edge_labels.get_mut(&mirror_edge).unwrap() = 1;
-                            // add to essentials
-                            self.essential_diagonals
-                                .push(vec![[vertex.x, -vertex.y], [opp_coords.x, -opp_coords.y]]);
-                            continue;
-                        } else {
-                            // else remove from outgoing edges and check again
-                            outgoing_edges.remove(idx);
-                            check_again = true;
-                            break;
-                        }
-                    }
+                // skip poly edges and edges already known to be essential
+                if edge_labels[edge.fix()] != NON_ESSENTIAL {
+                    idx += 1;
+                    continue;
                 }
 
-                if !check_again {
-                    break;
+                // if the vertex and its opposite wrt this diagonal are convex, then not essential
+                if vertex_convex && self.opposite_convex(edge) {
+                    outgoing_edges.remove(idx);
+                    continue;
+                }
+
+                // check if essential diagonal
+                // this means check if angle between its preceeding and subsequent edges
+                // in outgoing_edges is concave
+                let prev_vert =
+                    *outgoing_edges[(idx + outgoing_edges.len() - 1) % outgoing_edges.len()].to();
+                let next_vert = *outgoing_edges[(idx + 1) % outgoing_edges.len()].to();
+
+                // note the order switch!
+                if !self.convex_angle(vertex_coords, next_vert, prev_vert) {
+                    edge_labels[edge.fix()] = ESSENTIAL;
+                    edge_labels[edge.sym().fix()] = ESSENTIAL;
+                    essentials.push(edge.fix());
+                    idx += 1;
+                } else {
+                    outgoing_edges.remove(idx);
                 }
             }
         }
+        essentials
+    }
 
-        // --- Remove not really essential essentials ------
-        // Comment this for loop out to see effect on polygon1
-        // It is another traversal of the poly edges to
-        // establish which essential diagonals are truly essential
-        // --------------------------------------------------
-        for vertex in self.triangulation.vertices() {
-            let vertex_fixed = vertex.fix();
+    /// Second traversal of the vertices to establish which essential
+    /// diagonals are truly essential.  Skip it to see the effect on
+    /// polygon1.
+    pub(crate) fn remove_inessential(&self, vertices: &[FixedVertexHandle], edge_labels: &mut [u8]) {
+        for &vertex_fixed in vertices {
+            let vertex = self.triangulation.vertex(vertex_fixed);
             let next_poly_vertex = *self.triangulation.vertex(self.next_vertex(vertex_fixed));
             let prev_poly_vertex = *self
                 .triangulation
                 .vertex(self.previous_vertex(vertex_fixed));
             let vertex_coords = *vertex;
 
+            // only a concave vertex can have a diagonal that is not really essential
+            if self.convex_angle(vertex_coords, prev_poly_vertex, next_poly_vertex) {
+                continue;
+            }
+
             // only look at the essential and poly edges
-            // so ignore the bad edges and those with label 0
             let mut outgoing_edges: Vec<EdgeHandle<Point2<f32>, CdtEdge>> = vertex
                 .ccw_out_edges()
-                .filter(|e| {
-                    let label = edge_labels.get(&e.fix());
-                    label.is_some() && label != Some(&0)
-                })
+                .filter(|e| matches!(edge_labels[e.fix()], ESSENTIAL | POLY_EDGE))
                 .collect();
 
+            // removing a diagonal widens the angles of its neighbours,
+            // so start over after each removal
             loop {
-                let mut check_again = false;
-
-                // loop through the outgoing edges of the vertex
-                for idx in 0..outgoing_edges.len() {
+                let not_essential = (0..outgoing_edges.len()).find(|&idx| {
                     let edge = outgoing_edges[idx];
-                    let fixed_edge = edge.fix();
-                    let mirror_edge = edge.sym().fix();
-                    let opposite_vertex = edge.to().fix();
-                    let opp_prev = *self
-                        .triangulation
-                        .vertex(self.previous_vertex(opposite_vertex));
-                    let opp_next = *self.triangulation.vertex(self.next_vertex(opposite_vertex));
-                    let opp_coords = *edge.to();
-
-                    let label = edge_labels.get(&fixed_edge).unwrap(); // ok to unwrap becaus of the filter used for outgoing_edges
-
-                    // if edge has been marked as essential (so not poly edge)
-                    if label == &1 {
-                       
-                        // if the vertex is concave and its opposite wrt this diagonal is convex, then we check
-                        if !self.convex_angle(vertex_coords, prev_poly_vertex, next_poly_vertex)
-                            && self.convex_angle(opp_coords, opp_prev, opp_next)
-                        {   
-                            // check if essential diagonal
-                            let prev_vert = *outgoing_edges
-                                [(idx + outgoing_edges.len() - 1) % outgoing_edges.len()]
-                            .to();
-                            let next_vert = *outgoing_edges[(idx + 1) % outgoing_edges.len()].to();
-
-                            // note the order switch!
-                            if self.convex_angle(vertex_coords, next_vert, prev_vert) {
-                                
-                                // if not really essential label with 0
-                                *edge_labels.get_mut(&fixed_edge).unwrap() = 0;
-                                *edge_labels.get_mut(&mirror_edge).unwrap() = 0;
-                                // remove from essentials
-                                self.essential_diagonals.retain(|vector| {
-                                    *vector
-                                        != vec![
-                                            [vertex.x, -vertex.y],
-                                            [opp_coords.x, -opp_coords.y],
-                                        ]
-                                });
-                                outgoing_edges.remove(idx);
-                                check_again = true;
-                                break;
-                            }
-                        }
+                    if edge_labels[edge.fix()] != ESSENTIAL || !self.opposite_convex(edge) {
+                        return false;
                     }
-                }
-
-                if !check_again {
-                    break;
+                    let prev_vert = *outgoing_edges
+                        [(idx + outgoing_edges.len() - 1) % outgoing_edges.len()]
+                    .to();
+                    let next_vert = *outgoing_edges[(idx + 1) % outgoing_edges.len()].to();
+
+                    // note the order switch!
+                    self.convex_angle(vertex_coords, next_vert, prev_vert)
+                });
+
+                match not_essential {
+                    Some(idx) => {
+                        let edge = outgoing_edges.remove(idx);
+                        edge_labels[edge.fix()] = NON_ESSENTIAL;
+                        edge_labels[edge.sym().fix()] = NON_ESSENTIAL;
+                    }
+                    None => break,
                 }
             }
         }
+    }
 
-        
+    /// Gluing stage of the decomposition: glues the triangles together
+    /// along the non-essential edges.  A union-find over the faces of
+    /// the triangulation collects the triangles of each convex part in
+    /// near-linear time.
+    ///
+    /// The parts are appended to convex_parts in a canonical order that
+    /// only depends on the polygon vertices: every part starts at its
+    /// vertex of smallest position and the parts are sorted by that
+    /// position, then by the position of their second vertex.
+    pub(crate) fn glue(&mut self, edge_labels: &EdgeLabels) {
+        let faces: Vec<FixedFaceHandle> = self
+            .triangulation
+            .triangles()
+            .map(|face| face.fix())
+            .filter(|face| {
+                self.triangulation
+                    .face(*face)
+                    .adjacent_edges()
+                    .all(|e| edge_labels.0[e.fix()] != OUTSIDE)
+            })
+            .collect();
+
+        for part in self.glue_faces(&faces, &edge_labels.0) {
+            let new_convex_part = self.vertex_ordering(&part);
+            self.convex_parts.push(new_convex_part);
+        }
+    }
 
-        // -------- Gluing together the triangles along the non-essential edges ----------
-        // First add all triangles to the convex_polys vector
-        let mut convex_polys: Vec<Vec<EdgeHandle<Point2<f32>, CdtEdge>>> = Vec::new();
-        for triang in self.triangulation.triangles() {
-            let mut triangle: Vec<EdgeHandle<Point2<f32>, CdtEdge>> = Vec::new();
-            let mut do_not_add: bool = false;
-            for edge in triang.adjacent_edges() {
-                if !self.bad_edges.contains(&edge.fix()) {
-                    triangle.push(edge);
-                } else {
-                    do_not_add = true;
+    /// Glues the given faces along their non-essential edges.  Returns
+    /// the parts in canonical order as lists of edges, each edge given
+    /// by the handles of its vertices.
+    pub(crate) fn glue_faces(
+        &self,
+        faces: &[FixedFaceHandle],
+        edge_labels: &[u8],
+    ) -> Vec<Vec<[usize; 2]>> {
+        let num_faces = self.triangulation.num_faces();
+        let mut parent: Vec<usize> = (0..num_faces).collect();
+        let mut size: Vec<usize> = vec![1; num_faces];
+
+        for &face in faces {
+            for edge in self.triangulation.face(face).adjacent_edges() {
+                if edge_labels[edge.fix()] == NON_ESSENTIAL {
+                    let a = find_root(&mut parent, face);
+                    let b = find_root(&mut parent, edge.sym().face().fix());
+                    if a != b {
+                        let (small, large) = if size[a] < size[b] { (a, b) } else { (b, a) };
+                        parent[small] = large;
+                        size[large] += size[small];
+                    }
                 }
             }
-            if !do_not_add {
-                convex_polys.push(triangle);
-            }
         }
 
-        for (edge, value) in edge_labels.iter() {
-            // find the polygons containing this non-essential edge
-            // collect their unique vertices minus the non-essential
-            // edge in a new_poly
-            if value == &0 {
-                let edge_handle = self.triangulation.edge(*edge);
-                let mut new_poly: Vec<EdgeHandle<Point2<f32>, CdtEdge>> = Vec::new();
-
-                for poly in convex_polys.iter() {
-                    if poly.contains(&edge_handle) {
-                        for e in poly.iter().filter(|e| **e != edge_handle) {
-                            if !new_poly.contains(e) && !new_poly.contains(&e.sym()) {
-                                new_poly.push(*e);
-                            }
-                        }
-                    }
-                    if poly.contains(&edge_handle.sym()) {
-                        for e in poly.iter().filter(|e| **e != edge_handle) {
-                            if !new_poly.contains(e) && !new_poly.contains(&e.sym()) {
-                                new_poly.push(*e);
-                            }
-                        }
-                    }
+        // the boundary of a part consists of the edges of its
+        // triangles that are not glued, i.e. poly edges and essentials
+        let mut part_of_root: Vec<Option<usize>> = vec![None; num_faces];
+        let mut parts: Vec<Vec<[usize; 2]>> = Vec::new();
+        for &face in faces {
+            let root = find_root(&mut parent, face);
+            let part = *part_of_root[root].get_or_insert_with(|| {
+                parts.push(Vec::new());
+                parts.len() - 1
+            });
+            for edge in self.triangulation.face(face).adjacent_edges() {
+                if edge_labels[edge.fix()] != NON_ESSENTIAL {
+                    parts[part].push([edge.from().fix(), edge.to().fix()]);
                 }
+            }
+        }
+
+        // sorting the edges by vertex position starts the parts at their
+        // first vertex; two parts starting at the same vertex continue
+        // at different ones
+        for part in parts.iter_mut() {
+            part.sort_by_key(|tuple| self.positions[tuple[0]]);
+        }
+        parts.sort_by_key(|part| (self.positions[part[0][0]], self.positions[part[0][1]]));
+        parts
+    }
 
-                convex_polys = convex_polys
-                    .into_iter()
-                    .filter(|poly| {
-                        !poly.contains(&edge_handle) && !poly.contains(&edge_handle.sym())
-                    })
-                    .collect();
+    /// Checks that the vertices describe a simple polygon, i.e. that there
+    /// are at least three distinct vertices and that the edges only meet
+    /// at their common vertices.  triang assumes this holds.
+    pub fn check_simple(&self) -> Result<(), PolyError> {
+        let length = self.vertices.len();
+        if length < 3 {
+            return Err(PolyError::TooFewVertices(length));
+        }
+        if let Some(idx) = self
+            .vertices
+            .iter()
+            .position(|v| !v[0].is_finite() || !v[1].is_finite())
+        {
+            return Err(PolyError::NonFiniteVertex(idx));
+        }
 
-                convex_polys.push(new_poly);
+        // spade returns the handle of the existing vertex for duplicates
+        let mut triangulation = FloatCDT::with_walk_locate();
+        for (idx, v) in self.vertices.iter().enumerate() {
+            if triangulation.insert(Point2::new(v[0], v[1])) != idx {
+                return Err(PolyError::DuplicateVertex(idx));
             }
         }
 
-        for convex_part in convex_polys.iter() {
-            let new_convex_part = self.vertex_ordering(convex_part);
-            self.convex_parts.push(new_convex_part);
+        for idx in 0..length {
+            let next_idx = (idx + 1) % length;
+            if !triangulation.can_add_constraint(idx, next_idx) {
+                return Err(PolyError::IntersectingEdges(idx));
+            }
+            triangulation.add_constraint(idx, next_idx);
+        }
+
+        // after the intersection test, so a bow-tie whose halves cancel
+        // out is reported as intersecting
+        if self.doubled_area() == 0.0 {
+            return Err(PolyError::ZeroArea);
         }
+
+        // a vertex on an edge splits the edge into several constraints
+        let all_edges_kept =
+            (0..length).all(|idx| triangulation.exists_constraint(idx, (idx + 1) % length));
+        if !all_edges_kept || triangulation.num_constraints() != length {
+            return Err(PolyError::TouchingEdges);
+        }
+        Ok(())
+    }
+
+    /// Returns the convex parts as closed ccw cycles of vertices,
+    /// i.e. without the repetitions of the [point1, point2], [point2, point3], ...
+    /// format used by the convex_parts field.
+    pub fn convex_part_cycles(&self) -> Vec<Vec<Point2<f32>>> {
+        self.convex_parts
+            .iter()
+            .map(|part| part.iter().step_by(2).cloned().collect())
+            .collect()
     }
 
     /// Constrained Delaunay triangulation from the spade crate.
     /// Constraints are the polygon edges.
-    fn initialise_triangulation(&mut self) {
+    pub(crate) fn initialise_triangulation(&mut self) {
         
         self.triangulation = FloatCDT::with_walk_locate();
 
        for v in self.vertices.iter() {
             self.triangulation.insert(Point2::new(v[0], v[1]));
         }
+        self.handles = (0..self.vertices.len()).collect();
+        self.positions = self.handles.clone();
 
         // add polygon edges as constraints in the CDT
         for idx in 0..self.vertices.len() {
@@ -372,57 +463,41 @@ impl Poly {
     }
     
     /// Checks that the polygon vertices are in ccw order
-    /// by looking at the convex hull
+    /// by looking at the sign of the area
     fn poly_vertices_ccw(&self) -> bool {
-        
-        let mut convex_hull_iter = self.triangulation.infinite_face().adjacent_edges();
-       
-        let first_edge = convex_hull_iter.nth(0);
-        
-        let mut first_vertex =  first_edge.unwrap().from().fix();
-        let mut second_vertex = first_edge.unwrap().to().fix();
-        
-
-        if first_vertex == 0 {
-            first_vertex = self.vertices.len();
-        }
-        if second_vertex == 0 {
-            second_vertex = self.vertices.len();
-        }
+        self.doubled_area() > 0.0
+    }
 
-        if first_vertex < second_vertex {
-            false
-        }
-        else {
-            true
-        }
+    /// Shoelace formula, positive for ccw vertices
+    pub(crate) fn doubled_area(&self) -> f64 {
+        crate::geometry::doubled_area(&self.vertices)
     }
 
-    /// Ordering function that ensures that the vertices of the
-    /// convex parts are ordered as follows:
+    /// Converts the edges of a convex part, ordered by vertex index,
+    /// to coordinates ordered as follows:
     /// [point1, point2], [point2, point3], ...
-    fn vertex_ordering(
-        &self,
-        convex_poly: &Vec<EdgeHandle<Point2<f32>, CdtEdge>>,
-    ) -> Vec<Point2<f32>> {
-        let mut ordered_poly: Vec<[usize; 2]> = Vec::new();
+    pub(crate) fn vertex_ordering(&self, convex_part: &[[usize; 2]]) -> Vec<Point2<f32>> {
         let mut final_poly: Vec<Point2<f32>> = Vec::new();
-        // first order by vertex index
-        for edge in convex_poly.iter() {
-            ordered_poly.push([edge.from().fix(), edge.to().fix()]);
-        }
-        ordered_poly.sort_by_key(|tuple| tuple[0]);
-
-        // then convert to coordinates
-        for vertex in ordered_poly.iter() {
+        for vertex in convex_part.iter() {
             final_poly.push(*self.triangulation.vertex(vertex[0]));
             final_poly.push(*self.triangulation.vertex(vertex[1]));
         }
         final_poly
     }
 
+    /// Returns true if the angle at the opposite vertex of the edge,
+    /// i.e. at its target, is convex
+    fn opposite_convex(&self, edge: EdgeHandle<Point2<f32>, CdtEdge>) -> bool {
+        let opposite_vertex = edge.to().fix();
+        let opp_prev = *self
+            .triangulation
+            .vertex(self.previous_vertex(opposite_vertex));
+        let opp_next = *self.triangulation.vertex(self.next_vertex(opposite_vertex));
+        self.convex_angle(*edge.to(), opp_prev, opp_next)
+    }
+
     /// Returns true if angle is convex
-    fn convex_angle(
+    pub(crate) fn convex_angle(
         &self,
         vertex: Point2<f32>,
         prev_poly_vertex: Point2<f32>,
@@ -446,18 +521,34 @@ impl Poly {
         }
     }
 
-    fn previous_vertex(&self, vertex: usize) -> usize {
-        (vertex + self.vertices.len() - 1) % self.vertices.len()
+    /// Handle of the polygon vertex before the one with the given handle
+    pub(crate) fn previous_vertex(&self, vertex: usize) -> usize {
+        let length = self.vertices.len();
+        self.handles[(self.positions[vertex] + length - 1) % length]
+    }
+
+    /// Handle of the polygon vertex after the one with the given handle
+    pub(crate) fn next_vertex(&self, vertex: usize) -> usize {
+        self.handles[(self.positions[vertex] + 1) % self.vertices.len()]
     }
+}
 
-    fn next_vertex(&self, vertex: usize) -> usize {
-        (vertex + 1) % self.vertices.len()
+/// Root of x in the union-find forest, halving the path on the way.
+fn find_root(parent: &mut [usize], mut x: usize) -> usize {
+    while parent[x] != x {
+        parent[x] = parent[parent[x]];
+        x = parent[x];
     }
+    x
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::generate::{random_polygon, Method};
+    use crate::geometry::{convex_overlap, cross, doubled_area, orientation};
+    use crate::test_util::decomposed;
+    use proptest::prelude::*;
 
     #[test]
     fn test_ccw_detection() {
@@ -468,6 +559,41 @@ mod tests {
         assert_eq!(orientation, false);
     }
 
+    #[test]
+    fn test_check_simple() {
+        let polygon = |vertices: Vec<[f32; 2]>| Poly {
+            vertices,
+            ..Default::default()
+        };
+        let square = vec![[0.0, 0.0], [4.0, 0.0], [4.0, 4.0], [0.0, 4.0]];
+        assert_eq!(polygon(square).check_simple(), Ok(()));
+
+        let bowtie = vec![[0.0, 0.0], [4.0, 4.0], [4.0, 0.0], [0.0, 6.0]];
+        assert_eq!(
+            polygon(bowtie).check_simple(),
+            Err(PolyError::IntersectingEdges(2))
+        );
+
+        let repeated = vec![[0.0, 0.0], [4.0, 0.0], [4.0, 4.0], [0.0, 4.0], [4.0, 0.0]];
+        assert_eq!(
+            polygon(repeated).check_simple(),
+            Err(PolyError::DuplicateVertex(4))
+        );
+
+        let on_edge = vec![[0.0, 0.0], [4.0, 0.0], [2.0, 0.0], [2.0, 4.0]];
+        assert!(polygon(on_edge).check_simple().is_err());
+
+        let line = vec![[0.0, 0.0], [1.0, 1.0], [2.0, 2.0]];
+        assert_eq!(polygon(line).check_simple(), Err(PolyError::ZeroArea));
+
+        // zero area, but not on a line
+        let symmetric_bowtie = vec![[0.0, 0.0], [1.0, 1.0], [1.0, 0.0], [0.0, 1.0]];
+        assert_eq!(
+            polygon(symmetric_bowtie).check_simple(),
+            Err(PolyError::IntersectingEdges(2))
+        );
+    }
+
     #[test]
     fn test_triangle() {
         let mut polygon = Poly::default();
@@ -493,4 +619,193 @@ mod tests {
         assert_eq!(convex_part_number, 2);
         assert_eq!(essential_number, 1);
     }
+
+    /// A U whose slot is a long channel of collinear vertices, so the
+    /// triangles outside the polygon form a chain from the hull to the
+    /// bottom of the slot, deeper than a fixed number of flood rounds
+    fn slot_poly(depth: usize) -> Vec<[f32; 2]> {
+        let top = depth as f32 + 1.0;
+        let mut vertices = vec![[0.0, 0.0], [3.0, 0.0], [3.0, top], [2.0, top]];
+        vertices.extend((1..=depth).rev().map(|y| [2.0, y as f32]));
+        vertices.extend((1..=depth).map(|y| [1.0, y as f32]));
+        vertices.extend(vec![[1.0, top], [0.0, top]]);
+        vertices
+    }
+
+    fn triangle_area(polygon: &Poly) -> f32 {
+        polygon
+            .triangles
+            .iter()
+            .map(|t| cross(t[0], t[1], t[2]) / 2.0)
+            .sum()
+    }
+
+    #[test]
+    fn test_deep_pocket() {
+        let mut polygon = Poly {
+            vertices: slot_poly(40),
+            ..Default::default()
+        };
+        polygon.triang();
+        assert_eq!(polygon.triangles.len(), polygon.vertices.len() - 2);
+        // the polygon is the 3x41 rectangle minus the 1x40 slot
+        assert_eq!(triangle_area(&polygon), 3.0 * 41.0 - 40.0);
+    }
+
+    /// Star whose hull edges all skip a reflex vertex
+    fn star_poly(points: usize) -> Vec<[f32; 2]> {
+        (0..2 * points)
+            .map(|idx| {
+                let angle = std::f32::consts::PI * idx as f32 / points as f32;
+                let radius = if idx % 2 == 0 { 2.0 } else { 1.0 };
+                [radius * angle.cos(), radius * angle.sin()]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_orientation_of_any_rotation() {
+        for points in 3..8 {
+            let star = star_poly(points);
+            for reversed in [false, true] {
+                for start in 0..star.len() {
+                    let mut vertices = star.clone();
+                    vertices.rotate_left(start);
+                    if reversed {
+                        vertices.reverse();
+                    }
+                    let mut polygon = Poly {
+                        vertices,
+                        ..Default::default()
+                    };
+                    polygon.triang();
+                    assert_eq!(polygon.changed_orientation, reversed, "{} {}", points, start);
+                    assert_eq!(polygon.triangles.len(), 2 * points - 2, "{} {}", points, start);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_canonical_part_order() {
+        let vertices = random_polygon(Method::Comb, 40, 3);
+        let index = |p: &Point2<f32>| vertices.iter().position(|v| *v == [p.x, p.y]).unwrap();
+
+        let mut polygon = Poly {
+            vertices: vertices.clone(),
+            ..Default::default()
+        };
+        polygon.triang();
+        polygon.decomposition();
+
+        let cycles: Vec<Vec<usize>> = polygon
+            .convex_part_cycles()
+            .iter()
+            .map(|cycle| cycle.iter().map(index).collect())
+            .collect();
+        assert!(cycles.len() > 1);
+        for cycle in cycles.iter() {
+            assert_eq!(cycle[0], *cycle.iter().min().unwrap());
+        }
+        assert!(cycles.windows(2).all(|w| (w[0][0], w[0][1]) < (w[1][0], w[1][1])));
+        // the parts of the comb's base share its vertices
+        assert!(cycles.windows(2).any(|w| w[0][0] == w[1][0]));
+    }
+
+    // ------ Invariants of the decomposition on random polygons ------
+
+    /// Tolerance relative to the unit square of the generated polygons
+    const EPS: f64 = 1e-6;
+
+    fn point(p: Point2<f32>) -> [f64; 2] {
+        [p.x as f64, p.y as f64]
+    }
+
+    fn method() -> impl Strategy<Value = Method> {
+        prop::sample::select(Method::ALL.to_vec())
+    }
+
+    proptest! {
+        #[test]
+        fn prop_parts_are_convex(method in method(), vertices in 3..60usize, seed: u64) {
+            let polygon = decomposed(random_polygon(method, vertices, seed));
+            for part in polygon.convex_part_cycles() {
+                let part: Vec<[f64; 2]> = part.into_iter().map(point).collect();
+                prop_assert!(part.len() >= 3);
+                for idx in 0..part.len() {
+                    let turn = orientation(
+                        part[idx],
+                        part[(idx + 1) % part.len()],
+                        part[(idx + 2) % part.len()],
+                    );
+                    prop_assert!(turn >= -EPS, "reflex angle in part {:?}", part);
+                }
+            }
+        }
+
+        #[test]
+        fn prop_parts_tile_the_polygon(method in method(), vertices in 3..60usize, seed: u64) {
+            let polygon = decomposed(random_polygon(method, vertices, seed));
+            let parts: Vec<Vec<[f64; 2]>> = polygon
+                .convex_part_cycles()
+                .into_iter()
+                .map(|part| part.into_iter().map(point).collect())
+                .collect();
+
+            for (idx, a) in parts.iter().enumerate() {
+                for b in parts.iter().skip(idx + 1) {
+                    prop_assert!(!convex_overlap(a, b, EPS), "parts {:?} and {:?} overlap", a, b);
+                }
+            }
+
+            let outline: Vec<[f64; 2]> = polygon
+                .vertices
+                .iter()
+                .map(|v| [v[0] as f64, v[1] as f64])
+                .collect();
+            let polygon_area = doubled_area(&outline) / 2.0;
+            let parts_area: f64 = parts.iter().map(|part| doubled_area(part) / 2.0).sum();
+            prop_assert!((parts_area - polygon_area).abs() <= 1e-4 * polygon_area);
+            prop_assert_eq!(polygon.check_decomposition(), Ok(()));
+        }
+
+        #[test]
+        fn prop_essentials_are_triangulation_edges(
+            method in method(),
+            vertices in 3..60usize,
+            seed: u64,
+        ) {
+            let polygon = decomposed(random_polygon(method, vertices, seed));
+            for diagonal in polygon.essential_diagonals.iter() {
+                // essential diagonals are stored with y flipped
+                let a = Point2::new(diagonal[0][0], -diagonal[0][1]);
+                let b = Point2::new(diagonal[1][0], -diagonal[1][1]);
+                prop_assert!(
+                    polygon
+                        .triangles
+                        .iter()
+                        .any(|t| t.contains(&a) && t.contains(&b)),
+                    "{:?} is not a triangulation edge",
+                    diagonal
+                );
+            }
+        }
+
+        #[test]
+        fn prop_part_count_bound(method in method(), vertices in 3..60usize, seed: u64) {
+            let polygon = decomposed(random_polygon(method, vertices, seed));
+            let length = polygon.vertices.len();
+            let v = |idx: usize| {
+                let v = polygon.vertices[idx % length];
+                [v[0] as f64, v[1] as f64]
+            };
+            let reflex = (0..length)
+                .filter(|idx| orientation(v(idx + length - 1), v(*idx), v(idx + 1)) < 0.0)
+                .count();
+            // any convex decomposition needs at least ceil(r/2) + 1 parts,
+            // and HM is within a factor 4 of the optimum
+            let lower_bound = (reflex + 1) / 2 + 1;
+            prop_assert!(polygon.convex_parts.len() <= 4 * lower_bound);
+        }
+    }
 }
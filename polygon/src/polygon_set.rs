@@ -0,0 +1,503 @@
+//! Scenes of several polygons whose interiors do not overlap.  Every
+//! polygon is decomposed on its own, except that polygons sharing edges,
+//! or parts of edges, are merged and decomposed together, so that convex
+//! parts can reach across the edges they share.  The triangles and parts
+//! are tagged with the polygons they lie in.
+use crate::error::SetError;
+use crate::geometry::{
+    distance, doubled_area, on_segment, orientation, ring_contains, segments_cross,
+};
+use crate::poly::Poly;
+use cgmath::Point2;
+use std::collections::HashMap;
+
+/// Polygons decomposed together, and their decomposition
+#[derive(Default)]
+pub struct Group {
+    /// Indices of the polygons in PolygonSet::polygons
+    pub polygons: Vec<usize>,
+    /// The polygon, or the union of the polygons, triangulated and
+    /// decomposed
+    pub poly: Poly,
+}
+
+/// Triangle or convex part of a PolygonSet
+#[derive(Clone, Debug, PartialEq)]
+pub struct Piece {
+    /// Polygons the piece overlaps: one, unless the polygons were merged
+    pub origins: Vec<usize>,
+    /// Vertices in ccw order
+    pub vertices: Vec<Point2<f32>>,
+}
+
+/// Several polygons on one plane, which may touch but not overlap
+#[derive(Default)]
+pub struct PolygonSet {
+    /// The polygons, in either orientation
+    pub polygons: Vec<Vec<[f32; 2]>>,
+    /// The groups of polygons decomposed together, filled by decomposition
+    pub groups: Vec<Group>,
+}
+
+fn to64(ring: &[[f32; 2]]) -> Vec<[f64; 2]> {
+    ring.iter().map(|v| [v[0] as f64, v[1] as f64]).collect()
+}
+
+/// Lower left and upper right corner of the bounding box of the ring
+fn bounds(ring: &[[f64; 2]]) -> ([f64; 2], [f64; 2]) {
+    let mut min = [f64::INFINITY; 2];
+    let mut max = [f64::NEG_INFINITY; 2];
+    for p in ring.iter() {
+        for axis in 0..2 {
+            min[axis] = min[axis].min(p[axis]);
+            max[axis] = max[axis].max(p[axis]);
+        }
+    }
+    (min, max)
+}
+
+fn disjoint((min_a, max_a): ([f64; 2], [f64; 2]), (min_b, max_b): ([f64; 2], [f64; 2])) -> bool {
+    (0..2).any(|axis| min_a[axis] > max_b[axis] || min_b[axis] > max_a[axis])
+}
+
+/// A point inside a simple ring, in linear time.  The lowest vertex is
+/// convex; the middle of its ear is inside unless other vertices lie in
+/// the ear, and then the middle between the vertex and the one of them
+/// furthest into the ear is.
+fn inner_point(ring: &[[f64; 2]]) -> [f64; 2] {
+    let length = ring.len();
+    let lowest = (0..length)
+        .min_by(|a, b| {
+            let (a, b) = (ring[*a], ring[*b]);
+            a[1].total_cmp(&b[1]).then(a[0].total_cmp(&b[0]))
+        })
+        .unwrap();
+    let (prev, vertex, next) = (
+        ring[(lowest + length - 1) % length],
+        ring[lowest],
+        ring[(lowest + 1) % length],
+    );
+    let sign = orientation(prev, vertex, next).signum();
+    let in_ear = |p: [f64; 2]| {
+        orientation(prev, vertex, p) * sign >= 0.0
+            && orientation(vertex, next, p) * sign >= 0.0
+            && orientation(next, prev, p) * sign >= 0.0
+    };
+    let deepest = ring
+        .iter()
+        .filter(|p| **p != prev && **p != vertex && **p != next && in_ear(**p))
+        .max_by(|a, b| {
+            let depth = |p: [f64; 2]| (orientation(next, prev, p) * sign).abs();
+            depth(**a).total_cmp(&depth(**b))
+        });
+    match deepest {
+        Some(p) => [(vertex[0] + p[0]) / 2.0, (vertex[1] + p[1]) / 2.0],
+        None => [
+            (prev[0] + vertex[0] + next[0]) / 3.0,
+            (prev[1] + vertex[1] + next[1]) / 3.0,
+        ],
+    }
+}
+
+/// The ring clipped to the convex ccw cycle, by Sutherland-Hodgman
+fn clip(ring: &[[f64; 2]], convex: &[[f64; 2]]) -> Vec<[f64; 2]> {
+    let mut clipped = ring.to_vec();
+    for idx in 0..convex.len() {
+        let (a, b) = (convex[idx], convex[(idx + 1) % convex.len()]);
+        let input = std::mem::take(&mut clipped);
+        for k in 0..input.len() {
+            let (p, q) = (input[k], input[(k + 1) % input.len()]);
+            let (side_p, side_q) = (orientation(a, b, p), orientation(a, b, q));
+            if side_p >= 0.0 {
+                clipped.push(p);
+            }
+            if (side_p >= 0.0) != (side_q >= 0.0) {
+                let t = side_p / (side_p - side_q);
+                clipped.push([p[0] + t * (q[0] - p[0]), p[1] + t * (q[1] - p[1])]);
+            }
+        }
+    }
+    clipped
+}
+
+impl PolygonSet {
+    pub fn new(polygons: Vec<Vec<[f32; 2]>>) -> PolygonSet {
+        PolygonSet {
+            polygons,
+            groups: vec![],
+        }
+    }
+
+    /// Checks that every polygon is simple and that no two of them
+    /// overlap: their edges do not cross and neither contains a vertex,
+    /// the middle of an edge or an inner point of the other.  They may
+    /// share vertices and edges.  Compares every pair of polygons whose
+    /// bounding boxes meet, edge by edge.
+    pub fn check(&self) -> Result<(), SetError> {
+        let rings: Vec<Vec<[f64; 2]>> = self.polygons.iter().map(|p| to64(p)).collect();
+        let mut inner = vec![];
+        for (idx, polygon) in self.polygons.iter().enumerate() {
+            let poly = Poly {
+                vertices: polygon.clone(),
+                ..Default::default()
+            };
+            poly.check_simple()
+                .map_err(|err| SetError::Polygon(idx, err))?;
+            // a point inside, to catch a polygon lying on another's edges
+            inner.push(inner_point(&rings[idx]));
+        }
+
+        let boxes: Vec<([f64; 2], [f64; 2])> = rings.iter().map(|ring| bounds(ring)).collect();
+        for a in 0..rings.len() {
+            for b in a + 1..rings.len() {
+                if disjoint(boxes[a], boxes[b]) {
+                    continue;
+                }
+                if overlap(&rings[a], &rings[b], inner[a])
+                    || overlap(&rings[b], &rings[a], inner[b])
+                {
+                    return Err(SetError::Overlap(a, b));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The polygons with the vertices of the other polygons that lie
+    /// inside their edges added, so that an edge shared in part, at a
+    /// T-junction, is split into edges shared whole and edges of one
+    /// polygon.  Compares every pair of polygons whose bounding boxes
+    /// meet, edge by vertex.
+    fn split_edges(&self) -> Vec<Vec<[f32; 2]>> {
+        let rings: Vec<Vec<[f64; 2]>> = self.polygons.iter().map(|p| to64(p)).collect();
+        let boxes: Vec<([f64; 2], [f64; 2])> = rings.iter().map(|ring| bounds(ring)).collect();
+        let mut split = vec![];
+        for (a, ring) in rings.iter().enumerate() {
+            let others: Vec<usize> = (0..rings.len())
+                .filter(|b| *b != a && !disjoint(boxes[a], boxes[*b]))
+                .collect();
+            let mut polygon = vec![];
+            for idx in 0..ring.len() {
+                let (p, q) = (ring[idx], ring[(idx + 1) % ring.len()]);
+                polygon.push(self.polygons[a][idx]);
+                let mut within: Vec<(f64, [f32; 2])> = vec![];
+                for b in others.iter() {
+                    for (vertex, r) in rings[*b].iter().enumerate() {
+                        if *r != p
+                            && *r != q
+                            && orientation(p, q, *r) == 0.0
+                            && on_segment(p, q, *r)
+                        {
+                            within.push((distance(p, *r), self.polygons[*b][vertex]));
+                        }
+                    }
+                }
+                within.sort_by(|x, y| x.0.total_cmp(&y.0));
+                within.dedup_by(|x, y| x.1 == y.1);
+                polygon.extend(within.into_iter().map(|(_, vertex)| vertex));
+            }
+            split.push(polygon);
+        }
+        split
+    }
+
+    /// Groups of the polygons, split at T-junctions, joined by shared
+    /// edges; the polygons of a group are merged if their union is a
+    /// simple polygon, and decomposed on their own otherwise
+    fn groups(polygons: &[Vec<[f32; 2]>]) -> Vec<Vec<usize>> {
+        let key = |p: [f32; 2]| [p[0].to_bits(), p[1].to_bits()];
+        let mut parent: Vec<usize> = (0..polygons.len()).collect();
+        fn root(parent: &mut [usize], mut idx: usize) -> usize {
+            while parent[idx] != idx {
+                parent[idx] = parent[parent[idx]];
+                idx = parent[idx];
+            }
+            idx
+        }
+        let mut edges: HashMap<[[u32; 2]; 2], usize> = HashMap::new();
+        for (idx, polygon) in polygons.iter().enumerate() {
+            for vertex in 0..polygon.len() {
+                let (from, to) = (polygon[vertex], polygon[(vertex + 1) % polygon.len()]);
+                let edge = if key(from) < key(to) {
+                    [key(from), key(to)]
+                } else {
+                    [key(to), key(from)]
+                };
+                if let Some(other) = edges.insert(edge, idx) {
+                    let (a, b) = (root(&mut parent, idx), root(&mut parent, other));
+                    parent[a] = b;
+                }
+            }
+        }
+        let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+        for idx in 0..polygons.len() {
+            groups.entry(root(&mut parent, idx)).or_default().push(idx);
+        }
+        let mut groups: Vec<Vec<usize>> = groups.into_values().collect();
+        groups.sort();
+        groups
+    }
+
+    /// Outline of the union of the polygons of a group, which share
+    /// edges, or None if it is not a single simple polygon
+    fn union(polygons: &[Vec<[f32; 2]>], group: &[usize]) -> Option<Vec<[f32; 2]>> {
+        let key = |p: [f32; 2]| [p[0].to_bits(), p[1].to_bits()];
+        // the shared edges run both ways around ccw polygons and cancel
+        let mut edges: HashMap<[[u32; 2]; 2], [f32; 2]> = HashMap::new();
+        for idx in group.iter() {
+            let mut ring = polygons[*idx].clone();
+            if doubled_area(&ring) < 0.0 {
+                ring.reverse();
+            }
+            for vertex in 0..ring.len() {
+                let (from, to) = (ring[vertex], ring[(vertex + 1) % ring.len()]);
+                if edges.remove(&[key(to), key(from)]).is_none() {
+                    edges.insert([key(from), key(to)], from);
+                }
+            }
+        }
+        let mut next: HashMap<[u32; 2], [u32; 2]> = HashMap::new();
+        let mut position: HashMap<[u32; 2], [f32; 2]> = HashMap::new();
+        for ([from, to], point) in edges.iter() {
+            // two edges leaving a vertex pinch the union there
+            if next.insert(*from, *to).is_some() {
+                return None;
+            }
+            position.insert(*from, *point);
+        }
+        let start = *next.keys().next()?;
+        let mut outline = vec![position[&start]];
+        let mut current = next[&start];
+        while current != start {
+            outline.push(*position.get(&current)?);
+            current = *next.get(&current)?;
+        }
+        // several boundaries, the union has holes
+        if outline.len() != next.len() {
+            return None;
+        }
+        let poly = Poly {
+            vertices: outline,
+            ..Default::default()
+        };
+        poly.check_simple().ok().map(|()| poly.vertices)
+    }
+
+    /// Checks the polygons, then triangulates and decomposes every group
+    /// of them.  Polygons are grouped if they share an edge or part of
+    /// one, where a vertex of one lies exactly on an edge of the other.
+    pub fn decomposition(&mut self) -> Result<(), SetError> {
+        self.check()?;
+        let split = self.split_edges();
+        let mut groups = vec![];
+        for group in PolygonSet::groups(&split) {
+            match (group.len(), PolygonSet::union(&split, &group)) {
+                (1, _) | (_, None) => groups.extend(
+                    group
+                        .into_iter()
+                        .map(|idx| (vec![idx], self.polygons[idx].clone())),
+                ),
+                (_, Some(outline)) => groups.push((group, outline)),
+            }
+        }
+        self.groups = groups
+            .into_iter()
+            .map(|(polygons, vertices)| {
+                let mut poly = Poly {
+                    vertices,
+                    ..Default::default()
+                };
+                poly.triang();
+                poly.decomposition();
+                Group { polygons, poly }
+            })
+            .collect();
+        Ok(())
+    }
+
+    /// Tags the pieces of every group with the polygons they overlap
+    fn tag(&self, pieces: impl Fn(&Poly) -> Vec<Vec<Point2<f32>>>) -> Vec<Piece> {
+        let mut tagged = vec![];
+        for group in self.groups.iter() {
+            for vertices in pieces(&group.poly) {
+                let origins = if group.polygons.len() == 1 {
+                    group.polygons.clone()
+                } else {
+                    let convex: Vec<[f64; 2]> =
+                        vertices.iter().map(|p| [p.x as f64, p.y as f64]).collect();
+                    let area = doubled_area(&convex);
+                    group
+                        .polygons
+                        .iter()
+                        .filter(|idx| {
+                            let mut ring = to64(&self.polygons[**idx]);
+                            if doubled_area(&ring) < 0.0 {
+                                ring.reverse();
+                            }
+                            doubled_area(&clip(&ring, &convex)) > 1e-6 * area
+                        })
+                        .copied()
+                        .collect()
+                };
+                tagged.push(Piece { origins, vertices });
+            }
+        }
+        tagged
+    }
+
+    /// Triangles of all the groups, in the order of the groups
+    pub fn triangles(&self) -> Vec<Piece> {
+        self.tag(|poly| poly.triangles.iter().map(|t| t.to_vec()).collect())
+    }
+
+    /// Convex parts of all the groups, in the order of the groups
+    pub fn convex_parts(&self) -> Vec<Piece> {
+        self.tag(|poly| poly.convex_part_cycles())
+    }
+}
+
+/// True if a's edges cross b's, or a has a vertex, the middle of an edge
+/// or the inner point strictly inside b
+fn overlap(a: &[[f64; 2]], b: &[[f64; 2]], inner: [f64; 2]) -> bool {
+    let crossing = (0..a.len()).any(|i| {
+        let (p, q) = (a[i], a[(i + 1) % a.len()]);
+        (0..b.len()).any(|j| segments_cross(p, q, b[j], b[(j + 1) % b.len()]))
+    });
+    crossing
+        || ring_contains(b, inner, false)
+        || (0..a.len()).any(|i| {
+            let (p, q) = (a[i], a[(i + 1) % a.len()]);
+            ring_contains(b, p, false)
+                || ring_contains(b, [(p[0] + q[0]) / 2.0, (p[1] + q[1]) / 2.0], false)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::PolyError;
+    use crate::generate::{random_polygon, Method};
+
+    fn square(x: f32, y: f32) -> Vec<[f32; 2]> {
+        vec![[x, y], [x + 1.0, y], [x + 1.0, y + 1.0], [x, y + 1.0]]
+    }
+
+    fn area(pieces: &[Piece]) -> f64 {
+        pieces
+            .iter()
+            .map(|piece| {
+                let ring: Vec<[f32; 2]> = piece.vertices.iter().map(|p| [p.x, p.y]).collect();
+                doubled_area(&ring) / 2.0
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_separate_and_merged() {
+        // a square with a triangle on its right side, an L of three
+        // around the corner of a fourth, and one on its own in cw order
+        let mut lonely = square(10.0, 0.0);
+        lonely.reverse();
+        let mut set = PolygonSet::new(vec![
+            square(0.0, 0.0),
+            vec![[1.0, 0.0], [2.0, 0.5], [1.0, 1.0]],
+            lonely,
+            square(0.0, 5.0),
+            square(1.0, 5.0),
+            square(1.0, 6.0),
+        ]);
+        set.decomposition().unwrap();
+        let groups: Vec<&Vec<usize>> = set.groups.iter().map(|g| &g.polygons).collect();
+        assert_eq!(groups, vec![&vec![0, 1], &vec![2], &vec![3, 4, 5]]);
+
+        // the square and the triangle merge into a convex pentagon,
+        // tagged with both
+        let parts = set.convex_parts();
+        assert_eq!(parts[0].origins, vec![0, 1]);
+        assert_eq!(parts[0].vertices.len(), 5);
+        assert!(parts
+            .iter()
+            .filter(|part| part.origins.contains(&2))
+            .all(|part| part.origins == vec![2]));
+        assert!((area(&parts) - 5.5).abs() < 1e-6);
+        let triangles = set.triangles();
+        assert!((area(&triangles) - 5.5).abs() < 1e-6);
+        for polygon in 0..set.polygons.len() {
+            assert!(triangles.iter().any(|t| t.origins.contains(&polygon)));
+        }
+    }
+
+    #[test]
+    fn test_touching_at_a_vertex() {
+        let mut set = PolygonSet::new(vec![square(0.0, 0.0), square(1.0, 1.0)]);
+        set.decomposition().unwrap();
+        assert_eq!(set.groups.len(), 2);
+
+        // four rectangles around a hole share edges, but their union
+        // has a hole, so they are decomposed one by one
+        let mut set = PolygonSet::new(vec![
+            vec![[0.0, 0.0], [2.0, 0.0], [2.0, 1.0], [1.0, 1.0], [0.0, 1.0]],
+            vec![[2.0, 0.0], [3.0, 0.0], [3.0, 2.0], [2.0, 2.0], [2.0, 1.0]],
+            vec![[1.0, 2.0], [2.0, 2.0], [3.0, 2.0], [3.0, 3.0], [1.0, 3.0]],
+            vec![[0.0, 1.0], [1.0, 1.0], [1.0, 2.0], [1.0, 3.0], [0.0, 3.0]],
+        ]);
+        set.decomposition().unwrap();
+        assert_eq!(set.groups.len(), 4);
+        assert!(set
+            .convex_parts()
+            .iter()
+            .all(|part| part.origins.len() == 1));
+    }
+
+    #[test]
+    fn test_t_junctions() {
+        // two squares on a rectangle, each sharing half of its top edge,
+        // and a third one touching its right end
+        let mut set = PolygonSet::new(vec![
+            vec![[0.0, 0.0], [2.0, 0.0], [2.0, 1.0], [0.0, 1.0]],
+            square(0.0, 1.0),
+            square(1.0, 1.0),
+            square(2.0, 1.0),
+        ]);
+        set.decomposition().unwrap();
+        let groups: Vec<&Vec<usize>> = set.groups.iter().map(|g| &g.polygons).collect();
+        assert_eq!(groups, vec![&vec![0, 1, 2, 3]]);
+        // the union is an L, cut into parts that reach across the
+        // polygons
+        let parts = set.convex_parts();
+        assert!(parts.iter().any(|part| part.origins.len() > 1));
+        assert!((area(&parts) - 5.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_inner_point() {
+        // the ear at the lowest vertex holds the reflex vertex
+        let dart = vec![[0.0, 0.0], [4.0, 1.0], [1.0, 1.0], [0.0, 4.0]];
+        assert_eq!(inner_point(&dart), [0.5, 0.5]);
+        for method in Method::ALL.iter() {
+            for seed in 0..5 {
+                let mut ring = to64(&random_polygon(*method, 50, seed));
+                assert!(ring_contains(&ring, inner_point(&ring), false));
+                ring.reverse();
+                assert!(ring_contains(&ring, inner_point(&ring), false));
+            }
+        }
+    }
+
+    #[test]
+    fn test_overlap() {
+        let mut set = PolygonSet::new(vec![square(0.0, 0.0), square(0.5, 0.5)]);
+        assert_eq!(set.decomposition(), Err(SetError::Overlap(0, 1)));
+        // one inside the other, or the same twice
+        let inner = vec![[0.25, 0.25], [0.75, 0.25], [0.75, 0.75]];
+        let set = PolygonSet::new(vec![square(0.0, 0.0), inner]);
+        assert_eq!(set.check(), Err(SetError::Overlap(0, 1)));
+        let set = PolygonSet::new(vec![square(0.0, 0.0), square(0.0, 0.0)]);
+        assert_eq!(set.check(), Err(SetError::Overlap(0, 1)));
+        let set = PolygonSet::new(vec![square(0.0, 0.0), vec![[0.0, 0.0], [1.0, 0.0]]]);
+        assert_eq!(
+            set.check(),
+            Err(SetError::Polygon(1, PolyError::TooFewVertices(2)))
+        );
+    }
+}
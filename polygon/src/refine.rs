@@ -0,0 +1,443 @@
+//! Refinement of the convex parts into smaller parts bounded in size
+//! and shape, by straight cuts that end in Steiner points.
+use crate::geometry::{distance, doubled_area};
+use crate::poly::Poly;
+use cgmath::Point2;
+use smart_default::SmartDefault;
+use std::collections::HashMap;
+
+/// Bounds on the size and shape of the convex parts for refine_parts.
+/// The defaults impose none.
+#[derive(SmartDefault, Clone, Debug, PartialEq)]
+pub struct PartLimits {
+    /// Largest area of a part
+    #[default(f32::INFINITY)]
+    pub max_area: f32,
+    /// Largest distance between two vertices of a part
+    #[default(f32::INFINITY)]
+    pub max_diameter: f32,
+    /// Largest ratio of the diameter of a part to its width, the
+    /// distance between the closest pair of parallel lines enclosing it
+    #[default(f32::INFINITY)]
+    pub max_aspect_ratio: f32,
+    /// Smallest angle in degrees that a cut makes with the edges it
+    /// meets.  Smaller angles of the polygon itself are kept.
+    #[default = 0.0]
+    pub min_angle: f32,
+}
+
+/// End of a cut through a convex part: one of its vertices, or a new
+/// point on the edge starting at a vertex
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum CutEnd {
+    Vertex(usize),
+    Edge(usize, Point2<f32>),
+}
+
+impl CutEnd {
+    fn point(self, cycle: &[Point2<f32>]) -> Point2<f32> {
+        match self {
+            CutEnd::Vertex(idx) => cycle[idx],
+            CutEnd::Edge(_, point) => point,
+        }
+    }
+}
+
+/// Pieces of a convex part on the two sides of a cut
+struct Cut {
+    left: Vec<Point2<f32>>,
+    right: Vec<Point2<f32>>,
+    ends: [CutEnd; 2],
+}
+
+/// Part on the left of an edge of a part, by the bits of its end points
+type EdgeKey = [[u32; 2]; 2];
+
+fn edge_key(from: Point2<f32>, to: Point2<f32>) -> EdgeKey {
+    [
+        [from.x.to_bits(), from.y.to_bits()],
+        [to.x.to_bits(), to.y.to_bits()],
+    ]
+}
+
+fn point64(point: Point2<f32>) -> [f64; 2] {
+    [point.x as f64, point.y as f64]
+}
+
+fn area(cycle: &[Point2<f32>]) -> f64 {
+    let cycle: Vec<[f64; 2]> = cycle.iter().map(|p| point64(*p)).collect();
+    doubled_area(&cycle) / 2.0
+}
+
+/// Diameter of the cycle with the two vertices at that distance
+fn diameter(cycle: &[Point2<f32>]) -> (f64, usize, usize) {
+    let mut farthest = (0.0, 0, 0);
+    for a in 0..cycle.len() {
+        for b in a + 1..cycle.len() {
+            let d = distance(point64(cycle[a]), point64(cycle[b]));
+            if d > farthest.0 {
+                farthest = (d, a, b);
+            }
+        }
+    }
+    farthest
+}
+
+/// Width of a convex cycle: one of the closest pairs of parallel lines
+/// enclosing it contains an edge.
+fn width(cycle: &[Point2<f32>]) -> f64 {
+    let mut width = f64::INFINITY;
+    for idx in 0..cycle.len() {
+        let a = point64(cycle[idx]);
+        let b = point64(cycle[(idx + 1) % cycle.len()]);
+        let length = distance(a, b);
+        if length == 0.0 {
+            continue;
+        }
+        let height = cycle
+            .iter()
+            .map(|p| {
+                let p = point64(*p);
+                ((b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0])) / length
+            })
+            .fold(0.0, f64::max);
+        width = width.min(height);
+    }
+    width
+}
+
+/// Interior angle of the convex cycle at the vertex
+fn angle(cycle: &[Point2<f32>], vertex: usize) -> f64 {
+    let length = cycle.len();
+    let p = point64(cycle[vertex]);
+    let a = point64(cycle[(vertex + length - 1) % length]);
+    let b = point64(cycle[(vertex + 1) % length]);
+    let (u, v) = ([a[0] - p[0], a[1] - p[1]], [b[0] - p[0], b[1] - p[1]]);
+    let cross = v[0] * u[1] - v[1] * u[0];
+    let dot = u[0] * v[0] + u[1] * v[1];
+    // a straight angle at a rounded Steiner point may come out reflex
+    cross.atan2(dot).rem_euclid(2.0 * std::f64::consts::PI)
+}
+
+/// Splits the convex ccw cycle into ccw pieces along the line through
+/// the point with the given unit direction; None if the line misses
+/// the interior of the cycle.  Vertices closer to the line than snap
+/// lie on it.
+fn split(cycle: &[Point2<f32>], point: [f64; 2], direction: [f64; 2], snap: f64) -> Option<Cut> {
+    let length = cycle.len();
+    let side: Vec<f64> = cycle
+        .iter()
+        .map(|p| {
+            let p = point64(*p);
+            let s = direction[0] * (p[1] - point[1]) - direction[1] * (p[0] - point[0]);
+            if s.abs() <= snap {
+                0.0
+            } else {
+                s
+            }
+        })
+        .collect();
+
+    let (mut left, mut right, mut ends) = (vec![], vec![], vec![]);
+    for idx in 0..length {
+        let next = (idx + 1) % length;
+        if side[idx] >= 0.0 {
+            left.push(cycle[idx]);
+        }
+        if side[idx] <= 0.0 {
+            right.push(cycle[idx]);
+        }
+        if side[idx] == 0.0 {
+            ends.push(CutEnd::Vertex(idx));
+        } else if side[idx] * side[next] < 0.0 {
+            let t = side[idx] / (side[idx] - side[next]);
+            let (a, b) = (point64(cycle[idx]), point64(cycle[next]));
+            let crossing = Point2::new(
+                (a[0] + t * (b[0] - a[0])) as f32,
+                (a[1] + t * (b[1] - a[1])) as f32,
+            );
+            if crossing == cycle[idx] || crossing == cycle[next] {
+                return None;
+            }
+            left.push(crossing);
+            right.push(crossing);
+            ends.push(CutEnd::Edge(idx, crossing));
+        }
+    }
+
+    // rounding the crossings may flatten a sliver of a piece
+    if ends.len() != 2
+        || left.len() < 3
+        || right.len() < 3
+        || area(&left) <= 0.0
+        || area(&right) <= 0.0
+    {
+        return None;
+    }
+    Some(Cut {
+        left,
+        right,
+        ends: [ends[0], ends[1]],
+    })
+}
+
+impl PartLimits {
+    fn exceeded_by(&self, cycle: &[Point2<f32>], diameter: f64) -> bool {
+        area(cycle) > self.max_area as f64
+            || diameter > self.max_diameter as f64
+            || diameter > self.max_aspect_ratio as f64 * width(cycle)
+    }
+}
+
+impl Poly {
+    /// Runs decomposition and refines its parts with refine_parts.
+    pub fn constrained_decomposition(&mut self, limits: &PartLimits) -> usize {
+        self.decomposition();
+        self.refine_parts(limits)
+    }
+
+    /// Cuts the convex parts until they are within the limits.  A part is
+    /// cut across its diameter, through the middle, or at the smallest
+    /// turn away from that direction for which the cut meets the edges
+    /// at min_angle or more.  The pieces take the place of the part in
+    /// convex_parts and the cuts are added to essential_diagonals.
+    ///
+    /// The ends of the cuts that are not vertices become Steiner points.
+    /// A Steiner point on an edge between two parts is inserted in the
+    /// neighbouring part as well, so the parts meet along whole edges.
+    /// The triangles are not refined.
+    ///
+    /// Returns the number of parts that are still beyond the limits,
+    /// either because no cut keeps min_angle or because they are too
+    /// small to cut at the precision of the coordinates.
+    pub fn refine_parts(&mut self, limits: &PartLimits) -> usize {
+        let mut parts = self.convex_part_cycles();
+        let mut origin: Vec<usize> = (0..parts.len()).collect();
+        let mut left_of: HashMap<EdgeKey, usize> = HashMap::new();
+        for (idx, part) in parts.iter().enumerate() {
+            for vertex in 0..part.len() {
+                left_of.insert(edge_key(part[vertex], part[(vertex + 1) % part.len()]), idx);
+            }
+        }
+
+        let mut min = [f64::INFINITY; 2];
+        let mut max = [f64::NEG_INFINITY; 2];
+        for v in self.vertices.iter() {
+            for axis in 0..2 {
+                min[axis] = min[axis].min(v[axis] as f64);
+                max[axis] = max[axis].max(v[axis] as f64);
+            }
+        }
+        let extent = distance(min, max);
+        let min_angle = (limits.min_angle as f64).to_radians();
+        // the cut turns away from the perpendicular of the diameter in steps of 10 degrees
+        let turns: Vec<f64> = (0..17)
+            .map(|step| ((step + 1) / 2) as f64 * if step % 2 == 0 { 10.0 } else { -10.0 })
+            .map(f64::to_radians)
+            .collect();
+
+        let mut todo: Vec<usize> = (0..parts.len()).rev().collect();
+        let mut beyond_limits = 0;
+        while let Some(idx) = todo.pop() {
+            let part = parts[idx].clone();
+            let (size, a, b) = diameter(&part);
+            if !limits.exceeded_by(&part, size) {
+                continue;
+            }
+            if size < 1e-5 * extent {
+                beyond_limits += 1;
+                continue;
+            }
+
+            let (a, b) = (point64(part[a]), point64(part[b]));
+            let middle = [(a[0] + b[0]) / 2.0, (a[1] + b[1]) / 2.0];
+            let along = [(b[0] - a[0]) / size, (b[1] - a[1]) / size];
+            let cut = turns.iter().find_map(|turn| {
+                let (sin, cos) = turn.sin_cos();
+                let direction = [
+                    -along[1] * cos - along[0] * sin,
+                    along[0] * cos - along[1] * sin,
+                ];
+                let cut = split(&part, middle, direction, 1e-6 * size)?;
+                // the angles the cut makes in the pieces at its ends
+                let mut smallest = f64::INFINITY;
+                for end in cut.ends.iter() {
+                    let point = end.point(&part);
+                    for piece in [&cut.left, &cut.right] {
+                        let vertex = piece.iter().position(|p| *p == point).unwrap();
+                        smallest = smallest.min(angle(piece, vertex));
+                    }
+                }
+                (smallest >= min_angle).then_some(cut)
+            });
+            let Cut { left, right, ends } = match cut {
+                Some(cut) => cut,
+                None => {
+                    beyond_limits += 1;
+                    continue;
+                }
+            };
+
+            // the neighbour across a split edge gets the Steiner point too
+            for end in ends.iter() {
+                if let CutEnd::Edge(vertex, point) = *end {
+                    let from = part[vertex];
+                    let to = part[(vertex + 1) % part.len()];
+                    if let Some(neighbour) = left_of.remove(&edge_key(to, from)) {
+                        let at = parts[neighbour].iter().position(|p| *p == to).unwrap();
+                        parts[neighbour].insert(at + 1, point);
+                        left_of.insert(edge_key(to, point), neighbour);
+                        left_of.insert(edge_key(point, from), neighbour);
+                    }
+                    self.steiner_points.push([point.x, point.y]);
+                }
+            }
+            let [from, to] = [ends[0].point(&part), ends[1].point(&part)];
+            self.essential_diagonals
+                .push(vec![[from.x, -from.y], [to.x, -to.y]]);
+
+            let new = parts.len();
+            for (piece, piece_idx) in [(&left, idx), (&right, new)] {
+                for vertex in 0..piece.len() {
+                    left_of.insert(
+                        edge_key(piece[vertex], piece[(vertex + 1) % piece.len()]),
+                        piece_idx,
+                    );
+                }
+            }
+            parts[idx] = left;
+            parts.push(right);
+            origin.push(origin[idx]);
+            todo.push(new);
+            todo.push(idx);
+        }
+
+        let mut order: Vec<usize> = (0..parts.len()).collect();
+        order.sort_by_key(|idx| origin[*idx]);
+        self.convex_parts = order
+            .into_iter()
+            .map(|idx| {
+                let cycle = &parts[idx];
+                (0..cycle.len())
+                    .flat_map(|vertex| [cycle[vertex], cycle[(vertex + 1) % cycle.len()]])
+                    .collect()
+            })
+            .collect();
+        beyond_limits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{random_polygon, Method};
+
+    fn refined(vertices: Vec<[f32; 2]>, limits: &PartLimits) -> (Poly, usize) {
+        let mut poly = Poly {
+            vertices,
+            ..Default::default()
+        };
+        poly.triang();
+        let beyond_limits = poly.constrained_decomposition(limits);
+        (poly, beyond_limits)
+    }
+
+    /// The part edges without a reverse edge in another part cover the
+    /// boundary once, i.e. no vertex of a part lies inside an edge of
+    /// its neighbour.
+    fn assert_conforming(poly: &Poly) {
+        let cycles = poly.convex_part_cycles();
+        let mut edges: HashMap<EdgeKey, f64> = HashMap::new();
+        for cycle in cycles.iter() {
+            for idx in 0..cycle.len() {
+                let (a, b) = (cycle[idx], cycle[(idx + 1) % cycle.len()]);
+                if edges.remove(&edge_key(b, a)).is_none() {
+                    edges.insert(edge_key(a, b), distance(point64(a), point64(b)));
+                }
+            }
+        }
+        let boundary: f64 = edges.values().sum();
+        let length = poly.vertices.len();
+        let perimeter: f64 = (0..length)
+            .map(|idx| {
+                let (a, b) = (poly.vertices[idx], poly.vertices[(idx + 1) % length]);
+                distance([a[0] as f64, a[1] as f64], [b[0] as f64, b[1] as f64])
+            })
+            .sum();
+        assert!((boundary - perimeter).abs() < 1e-4 * perimeter);
+    }
+
+    #[test]
+    fn test_square_cut_to_area() {
+        let square = vec![[0.0, 0.0], [4.0, 0.0], [4.0, 4.0], [0.0, 4.0]];
+        let limits = PartLimits {
+            max_area: 1.0,
+            ..Default::default()
+        };
+        let (poly, beyond_limits) = refined(square, &limits);
+        assert_eq!(beyond_limits, 0);
+        assert!(poly.convex_parts.len() >= 16);
+        assert!(poly
+            .convex_part_cycles()
+            .iter()
+            .all(|part| area(part) <= 1.0));
+        assert!(!poly.steiner_points.is_empty());
+        assert_eq!(poly.check_decomposition(), Ok(()));
+        assert_conforming(&poly);
+    }
+
+    #[test]
+    fn test_steiner_points_go_with_the_refined_parts() {
+        let square = vec![[0.0, 0.0], [4.0, 0.0], [4.0, 4.0], [0.0, 4.0]];
+        let limits = PartLimits {
+            max_area: 1.0,
+            ..Default::default()
+        };
+        let (mut poly, _) = refined(square.clone(), &limits);
+        assert!(!poly.steiner_points.is_empty());
+        poly.move_vertex(2, [5.0, 5.0]).unwrap();
+        assert!(poly.steiner_points.is_empty());
+        assert_eq!(poly.convex_parts.len(), 1);
+        assert_eq!(poly.check_decomposition(), Ok(()));
+
+        let (mut poly, _) = refined(square, &limits);
+        poly.convex_parts.clear();
+        poly.essential_diagonals.clear();
+        poly.decomposition();
+        assert!(poly.steiner_points.is_empty());
+        assert_eq!(poly.check_decomposition(), Ok(()));
+    }
+
+    #[test]
+    fn test_refined_parts_within_limits() {
+        let limits = PartLimits {
+            max_diameter: 0.15,
+            max_aspect_ratio: 3.0,
+            min_angle: 30.0,
+            ..Default::default()
+        };
+        for method in [Method::Star, Method::SpacePartition, Method::Comb] {
+            let (poly, beyond_limits) = refined(random_polygon(method, 40, 1), &limits);
+            assert_eq!(poly.check_decomposition(), Ok(()), "{}", method);
+            assert_conforming(&poly);
+
+            let mut exceeding = 0;
+            for part in poly.convex_part_cycles() {
+                if limits.exceeded_by(&part, diameter(&part).0) {
+                    exceeding += 1;
+                }
+                // the angles at Steiner points are made by the cuts
+                for vertex in 0..part.len() {
+                    if poly
+                        .steiner_points
+                        .contains(&[part[vertex].x, part[vertex].y])
+                    {
+                        assert!(angle(&part, vertex) >= 30f64.to_radians() - 1e-6);
+                    }
+                }
+            }
+            assert_eq!(exceeding, beyond_limits, "{}", method);
+            assert!(beyond_limits * 10 < poly.convex_parts.len(), "{}", method);
+        }
+    }
+}
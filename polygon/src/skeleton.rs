@@ -0,0 +1,709 @@
+//! Straight skeleton and approximate medial axis of the polygon, both as
+//! a graph of nodes and arcs.
+//!
+//! The straight skeleton is traced by shrinking the polygon: every edge
+//! moves inwards at unit speed and the vertices move along the bisectors
+//! of their edges.  An edge event removes an edge that has shrunk to a
+//! point, a split event cuts the wavefront in two where a reflex vertex
+//! runs into an edge.  Every vertex keeps the time its edge shrinks to
+//! a point and, if it is reflex, its first split; after an event these
+//! are found again only for the edges next to the new vertices and for
+//! the reflex vertices whose split was on an edge that changed, so an
+//! event takes linear time in the size of the wavefront, plus a pass
+//! over the wavefront for every such reflex vertex.
+//!
+//! The medial axis is approximated by the Voronoi diagram of points
+//! sampled along the boundary: the circumcentres of the Delaunay
+//! triangles of the samples, joined across the triangle edges whose ends
+//! are further apart along the boundary than in a straight line.
+use crate::geometry::{distance, orientation};
+use crate::poly::Poly;
+use cgmath::Point2;
+use std::collections::HashMap;
+
+/// Node of a skeleton
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SkeletonNode {
+    pub position: [f32; 2],
+    /// Time at which the shrinking polygon reaches the node, i.e. its
+    /// distance from the lines of the edges it belongs to; for the
+    /// medial axis the radius of the largest disc around the node
+    pub distance: f32,
+}
+
+/// Straight skeleton or medial axis of a polygon
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Skeleton {
+    pub nodes: Vec<SkeletonNode>,
+    /// Pairs of node indices, the earlier node first in the skeleton
+    pub arcs: Vec<[usize; 2]>,
+}
+
+/// Vertex of the shrinking polygon
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Wave {
+    /// Position at the current time
+    pub(crate) position: [f64; 2],
+    velocity: [f64; 2],
+    /// Skeleton node the vertex started from
+    node: usize,
+    /// Polygon edges whose lines meet at the vertex
+    edge_in: usize,
+    edge_out: usize,
+    /// Number of the vertex, in the order the vertices were made
+    id: usize,
+    /// Time at which the edge to the next vertex shrinks to a point
+    edge_time: Option<f64>,
+    /// Time at which a reflex vertex first runs into an edge of its
+    /// wavefront, and the id of the vertex the edge starts at
+    split: Option<(f64, usize)>,
+}
+
+/// Next event of the shrinking polygon, with the time until it happens
+enum Event {
+    /// The edge starting at this vertex shrinks to a point
+    Edge { wavefront: usize, vertex: usize },
+    /// The reflex vertex runs into the edge starting at another vertex
+    Split {
+        wavefront: usize,
+        vertex: usize,
+        edge: usize,
+    },
+}
+
+fn add(a: [f64; 2], b: [f64; 2], scale: f64) -> [f64; 2] {
+    [a[0] + b[0] * scale, a[1] + b[1] * scale]
+}
+
+fn sub(a: [f64; 2], b: [f64; 2]) -> [f64; 2] {
+    [a[0] - b[0], a[1] - b[1]]
+}
+
+fn dot(a: [f64; 2], b: [f64; 2]) -> f64 {
+    a[0] * b[0] + a[1] * b[1]
+}
+
+/// Supporting line of an edge of the shrinking polygon
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Line {
+    start: [f64; 2],
+    /// Unit vector along the edge
+    direction: [f64; 2],
+    /// Unit vector on the left of the edge, towards the inside
+    normal: [f64; 2],
+    /// Speed of the line along its normal, 1 for the skeleton
+    speed: f64,
+}
+
+impl Line {
+    pub(crate) fn new(start: [f64; 2], direction: [f64; 2], speed: f64) -> Line {
+        Line {
+            start,
+            direction,
+            normal: [-direction[1], direction[0]],
+            speed,
+        }
+    }
+}
+
+/// Shrinking polygon with the skeleton traced so far
+pub(crate) struct Wavefronts {
+    lines: Vec<Line>,
+    pub(crate) wavefronts: Vec<Vec<Wave>>,
+    now: f64,
+    tolerance: f64,
+    nodes: Vec<[f64; 3]>,
+    arcs: Vec<[usize; 2]>,
+    /// Id of the next vertex made
+    next_id: usize,
+}
+
+impl Wavefronts {
+    /// Wavefront of the ccw outline, with every edge at unit speed
+    fn new(outline: &[[f64; 2]]) -> Wavefronts {
+        let length = outline.len();
+        let lines = (0..length)
+            .map(|idx| {
+                let (a, b) = (outline[idx], outline[(idx + 1) % length]);
+                let edge = sub(b, a);
+                let norm = edge[0].hypot(edge[1]);
+                Line::new(a, [edge[0] / norm, edge[1] / norm], 1.0)
+            })
+            .collect();
+        let corners: Vec<[usize; 3]> = (0..length)
+            .map(|idx| [idx, (idx + length - 1) % length, idx])
+            .collect();
+        Wavefronts::with_lines(lines, outline, &corners)
+    }
+
+    /// Wavefront with the given lines, whose vertices are the corners
+    /// [point, line in, line out].  The points become the first nodes.
+    pub(crate) fn with_lines(
+        lines: Vec<Line>,
+        points: &[[f64; 2]],
+        corners: &[[usize; 3]],
+    ) -> Wavefronts {
+        let extent = points
+            .iter()
+            .map(|p| distance(*p, points[0]))
+            .fold(0.0, f64::max);
+        let mut wavefronts = Wavefronts {
+            lines,
+            wavefronts: vec![],
+            now: 0.0,
+            tolerance: 1e-9 * extent,
+            nodes: points.iter().map(|p| [p[0], p[1], 0.0]).collect(),
+            arcs: vec![],
+            next_id: 0,
+        };
+        let mut wavefront: Vec<Wave> = corners
+            .iter()
+            .map(|[point, edge_in, edge_out]| {
+                wavefronts.wave(points[*point], *point, *edge_in, *edge_out)
+            })
+            .collect();
+        wavefronts.update_events(&mut wavefront, 0);
+        wavefronts.wavefronts.push(wavefront);
+        wavefronts
+    }
+
+    /// Vertex on two lines, moving so that it stays on both while they
+    /// move inwards.  Opposite lines that met leave a vertex that does
+    /// not move.
+    fn wave(&mut self, position: [f64; 2], node: usize, edge_in: usize, edge_out: usize) -> Wave {
+        let (a, b) = (self.lines[edge_in], self.lines[edge_out]);
+        let velocity = if a.speed == b.speed {
+            // better conditioned for nearly parallel lines
+            let denominator = 1.0 + dot(a.normal, b.normal);
+            if denominator > 1e-12 {
+                let scale = a.speed / denominator;
+                [
+                    (a.normal[0] + b.normal[0]) * scale,
+                    (a.normal[1] + b.normal[1]) * scale,
+                ]
+            } else {
+                [0.0, 0.0]
+            }
+        } else {
+            let determinant = a.normal[0] * b.normal[1] - a.normal[1] * b.normal[0];
+            if determinant.abs() > 1e-12 {
+                [
+                    (b.normal[1] * a.speed - a.normal[1] * b.speed) / determinant,
+                    (a.normal[0] * b.speed - b.normal[0] * a.speed) / determinant,
+                ]
+            } else {
+                [0.0, 0.0]
+            }
+        };
+        self.next_id += 1;
+        Wave {
+            position,
+            velocity,
+            node,
+            edge_in,
+            edge_out,
+            id: self.next_id - 1,
+            edge_time: None,
+            split: None,
+        }
+    }
+
+    fn reflex(&self, wave: &Wave) -> bool {
+        let (a, b) = (
+            self.lines[wave.edge_in].direction,
+            self.lines[wave.edge_out].direction,
+        );
+        a[0] * b[1] - a[1] * b[0] < 0.0
+    }
+
+    /// Node at the position and the current time, one of the candidates
+    /// if it is there already
+    fn node(&mut self, position: [f64; 2], candidates: &[usize]) -> usize {
+        for &candidate in candidates.iter() {
+            let [x, y, _] = self.nodes[candidate];
+            if distance([x, y], position) <= self.tolerance {
+                return candidate;
+            }
+        }
+        self.nodes.push([position[0], position[1], self.now]);
+        self.nodes.len() - 1
+    }
+
+    fn arc(&mut self, from: usize, to: usize) {
+        if from != to {
+            self.arcs.push([from, to]);
+        }
+    }
+
+    /// Time until the edge from a to b shrinks to a point
+    fn edge_event(&self, a: &Wave, b: &Wave) -> Option<f64> {
+        let direction = self.lines[a.edge_out].direction;
+        let length = dot(sub(b.position, a.position), direction);
+        let rate = dot(sub(b.velocity, a.velocity), direction);
+        if length <= self.tolerance {
+            (rate <= 0.0).then_some(0.0)
+        } else {
+            (rate < 0.0).then(|| length / -rate)
+        }
+    }
+
+    /// Time until the reflex vertex runs into the edge from a to b
+    fn split_event(&self, reflex: &Wave, a: &Wave, b: &Wave) -> Option<f64> {
+        let line = self.lines[a.edge_out];
+        let (direction, normal) = (line.direction, line.normal);
+        let gap = dot(sub(reflex.position, line.start), normal) - line.speed * self.now;
+        let closing = line.speed - dot(reflex.velocity, normal);
+        if gap < -self.tolerance || closing <= 0.0 {
+            return None;
+        }
+        // vertices that start at the same node, like the corners of a
+        // round join, touch each other's edges but do not split them
+        if gap <= self.tolerance && (a.node == reflex.node || b.node == reflex.node) {
+            return None;
+        }
+        let time = gap.max(0.0) / closing;
+        let hit = add(reflex.position, reflex.velocity, time);
+        let (from, to) = (
+            add(a.position, a.velocity, time),
+            add(b.position, b.velocity, time),
+        );
+        let along = dot(sub(hit, from), direction);
+        let length = dot(sub(to, from), direction);
+        (along >= -self.tolerance && along <= length + self.tolerance).then_some(time)
+    }
+
+    /// Finds the events of the vertices of a wavefront that are new or
+    /// next to new ones, those with an id from first_new on.  The edges
+    /// between old vertices are the same as before, and so are the
+    /// splits of old reflex vertices on them.
+    fn update_events(&self, waves: &mut [Wave], first_new: usize) {
+        let length = waves.len();
+        let changed: Vec<usize> = (0..length)
+            .filter(|idx| waves[*idx].id >= first_new || waves[(idx + 1) % length].id >= first_new)
+            .collect();
+        for &vertex in changed.iter() {
+            waves[vertex].edge_time = self
+                .edge_event(&waves[vertex], &waves[(vertex + 1) % length])
+                .map(|time| self.now + time);
+        }
+
+        let index: HashMap<usize, usize> = waves
+            .iter()
+            .enumerate()
+            .map(|(idx, wave)| (wave.id, idx))
+            .collect();
+        let all: Vec<usize> = (0..length).collect();
+        for vertex in 0..length {
+            if !self.reflex(&waves[vertex]) {
+                continue;
+            }
+            // an old split is kept if its edge is still there unchanged,
+            // then only the changed edges can come first
+            let kept = waves[vertex].id < first_new
+                && waves[vertex].split.map_or(true, |(_, edge)| {
+                    index.get(&edge).map_or(false, |idx| !changed.contains(idx))
+                });
+            let mut split = if kept { waves[vertex].split } else { None };
+            for &other in if kept { &changed } else { &all }.iter() {
+                let after = (other + 1) % length;
+                if other == vertex || after == vertex {
+                    continue;
+                }
+                if let Some(time) = self.split_event(&waves[vertex], &waves[other], &waves[after]) {
+                    let time = self.now + time;
+                    if split.map_or(true, |(best, _)| time < best) {
+                        split = Some((time, waves[other].id));
+                    }
+                }
+            }
+            waves[vertex].split = split;
+        }
+    }
+
+    /// The event that happens first, edge events before split events at
+    /// the same time
+    fn next_event(&self) -> Option<(f64, Event)> {
+        let mut edge: Option<(f64, Event)> = None;
+        let mut split: Option<(f64, usize, usize, usize)> = None;
+        for (wavefront, waves) in self.wavefronts.iter().enumerate() {
+            for (vertex, wave) in waves.iter().enumerate() {
+                if let Some(time) = wave.edge_time {
+                    if edge.as_ref().map_or(true, |(best, _)| time < *best) {
+                        edge = Some((time, Event::Edge { wavefront, vertex }));
+                    }
+                }
+                if let Some((time, other)) = wave.split {
+                    if split.map_or(true, |(best, ..)| time < best) {
+                        split = Some((time, wavefront, vertex, other));
+                    }
+                }
+            }
+        }
+        let split = split.map(|(time, wavefront, vertex, other)| {
+            let edge = self.wavefronts[wavefront]
+                .iter()
+                .position(|wave| wave.id == other)
+                .unwrap();
+            let event = Event::Split {
+                wavefront,
+                vertex,
+                edge,
+            };
+            (time, event)
+        });
+        let event = match (edge, split) {
+            (Some(edge), Some(split)) if split.0 < edge.0 - self.tolerance => Some(split),
+            (None, split) => split,
+            (edge, _) => edge,
+        };
+        event.map(|(time, event)| ((time - self.now).max(0.0), event))
+    }
+
+    /// Keeps a wavefront of three or more vertices, with the events of
+    /// the vertices from first_new on; a smaller one has collapsed, and
+    /// its vertices end at the node of the new vertex.
+    fn keep(&mut self, mut waves: Vec<Wave>, node: usize, first_new: usize) {
+        if waves.len() > 2 {
+            self.update_events(&mut waves, first_new);
+            self.wavefronts.push(waves);
+            return;
+        }
+        for wave in waves.iter() {
+            self.arc(wave.node, node);
+        }
+    }
+
+    /// True if the wavefront has no area left, e.g. where two parallel
+    /// edges met all along
+    fn flat(&self, waves: &[Wave]) -> bool {
+        let length = waves.len();
+        let (mut area, mut perimeter) = (0.0, 0.0);
+        for idx in 0..length {
+            let (a, b) = (waves[idx].position, waves[(idx + 1) % length].position);
+            area += a[0] * b[1] - b[0] * a[1];
+            perimeter += distance(a, b);
+        }
+        area.abs() <= self.tolerance * perimeter
+    }
+
+    /// Ends a flat wavefront in a ridge: its vertices lie on a line and
+    /// are joined in their order along it.
+    fn collapse(&mut self, waves: Vec<Wave>) {
+        let origin = waves[0].position;
+        let far = waves
+            .iter()
+            .map(|wave| wave.position)
+            .fold(origin, |far, p| {
+                if distance(origin, p) > distance(origin, far) {
+                    p
+                } else {
+                    far
+                }
+            });
+        let direction = sub(far, origin);
+        let mut order: Vec<(f64, Wave)> = waves
+            .into_iter()
+            .map(|wave| (dot(sub(wave.position, origin), direction), wave))
+            .collect();
+        order.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut previous: Option<usize> = None;
+        for (_, wave) in order {
+            let candidates: Vec<usize> = previous.into_iter().chain(Some(wave.node)).collect();
+            let node = self.node(wave.position, &candidates);
+            self.arc(wave.node, node);
+            if let Some(previous) = previous {
+                self.arc(previous, node);
+            }
+            previous = Some(node);
+        }
+    }
+
+    fn advance(&mut self, time: f64) {
+        self.now += time;
+        for waves in self.wavefronts.iter_mut() {
+            for wave in waves.iter_mut() {
+                wave.position = add(wave.position, wave.velocity, time);
+            }
+        }
+    }
+
+    /// Shrinks the polygon up to the time, or until it has vanished.
+    /// Collapsed and flat wavefronts are removed.
+    pub(crate) fn run(&mut self, until: f64) {
+        // every edge event removes a vertex, every split event a reflex
+        // vertex, so this is a generous bound for degenerate input
+        let mut events = 4 * self.wavefronts.iter().map(Vec::len).sum::<usize>() + 16;
+        while events > 0 {
+            events -= 1;
+            let (flat, wavefronts): (Vec<Vec<Wave>>, Vec<Vec<Wave>>) =
+                std::mem::take(&mut self.wavefronts)
+                    .into_iter()
+                    .partition(|waves| self.flat(waves));
+            self.wavefronts = wavefronts;
+            for waves in flat {
+                self.collapse(waves);
+            }
+            let (time, event) = match self.next_event() {
+                Some(event) if self.now + event.0 <= until => event,
+                _ => break,
+            };
+            self.advance(time);
+            let first_new = self.next_id;
+
+            match event {
+                Event::Edge { wavefront, vertex } => {
+                    let mut waves = self.wavefronts.swap_remove(wavefront);
+                    waves.rotate_left(vertex);
+                    let (a, b) = (waves[0], waves.remove(1));
+                    let middle = add(a.position, sub(b.position, a.position), 0.5);
+                    let node = self.node(middle, &[a.node, b.node]);
+                    self.arc(a.node, node);
+                    self.arc(b.node, node);
+                    waves[0] = self.wave(middle, node, a.edge_in, b.edge_out);
+                    self.keep(waves, node, first_new);
+                }
+                Event::Split {
+                    wavefront,
+                    vertex,
+                    edge,
+                } => {
+                    let mut waves = self.wavefronts.swap_remove(wavefront);
+                    let length = waves.len();
+                    waves.rotate_left(vertex);
+                    let edge = (edge + length - vertex) % length;
+                    let reflex = waves[0];
+                    let hit_edge = waves[edge].edge_out;
+                    let node = self.node(reflex.position, &[reflex.node]);
+                    self.arc(reflex.node, node);
+
+                    // the vertices after the edge, and those before it
+                    let mut first =
+                        vec![self.wave(reflex.position, node, reflex.edge_in, hit_edge)];
+                    first.extend_from_slice(&waves[edge + 1..]);
+                    let mut second =
+                        vec![self.wave(reflex.position, node, hit_edge, reflex.edge_out)];
+                    second.extend_from_slice(&waves[1..=edge]);
+                    self.keep(first, node, first_new);
+                    self.keep(second, node, first_new);
+                }
+            }
+        }
+        if until.is_finite() {
+            self.advance(until - self.now);
+        }
+    }
+}
+
+impl Poly {
+    /// Straight skeleton of the polygon.  Its nodes are the vertices of
+    /// the polygon, at distance zero, and the points where the shrinking
+    /// polygon changes, at the time they are reached; its arcs are traced
+    /// by the vertices of the shrinking polygon.  Takes the vertices in
+    /// either orientation.
+    pub fn straight_skeleton(&self) -> Skeleton {
+        let mut outline: Vec<[f64; 2]> = self
+            .vertices
+            .iter()
+            .map(|v| [v[0] as f64, v[1] as f64])
+            .collect();
+        if self.doubled_area() < 0.0 {
+            outline.reverse();
+        }
+        if outline.len() < 3 {
+            return Skeleton::default();
+        }
+        let mut wavefronts = Wavefronts::new(&outline);
+        wavefronts.run(f64::INFINITY);
+        // wavefronts without events left, which only rounding causes
+        for waves in std::mem::take(&mut wavefronts.wavefronts) {
+            wavefronts.collapse(waves);
+        }
+        Skeleton {
+            nodes: wavefronts
+                .nodes
+                .iter()
+                .map(|[x, y, time]| SkeletonNode {
+                    position: [*x as f32, *y as f32],
+                    distance: *time as f32,
+                })
+                .collect(),
+            arcs: wavefronts.arcs,
+        }
+    }
+
+    /// Approximate medial axis: the centres of the largest discs in the
+    /// polygon that touch its boundary twice.  The boundary is sampled
+    /// at most the spacing apart and the Voronoi vertices of the samples
+    /// inside the polygon are joined where they belong to samples that
+    /// lie on different parts of the boundary.  Branches into convex
+    /// corners stop about a spacing short of the corner.
+    pub fn medial_axis(&self, spacing: f32) -> Skeleton {
+        let length = self.vertices.len();
+        if length < 3 || spacing.is_nan() || spacing <= 0.0 {
+            return Skeleton::default();
+        }
+        let mut samples = vec![];
+        for idx in 0..length {
+            let (a, b) = (self.vertices[idx], self.vertices[(idx + 1) % length]);
+            let pieces = (((b[0] - a[0]).hypot(b[1] - a[1]) / spacing).ceil() as usize).max(1);
+            for piece in 0..pieces {
+                let t = piece as f32 / pieces as f32;
+                samples.push([a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t]);
+            }
+        }
+        // arc length along the boundary up to every sample
+        let key = |p: Point2<f32>| [p.x.to_bits(), p.y.to_bits()];
+        let mut along = vec![0.0];
+        let mut index = HashMap::new();
+        for idx in 0..samples.len() {
+            let (a, b) = (samples[idx], samples[(idx + 1) % samples.len()]);
+            along.push(
+                along[idx] + distance([a[0] as f64, a[1] as f64], [b[0] as f64, b[1] as f64]),
+            );
+            index.insert(key(Point2::new(a[0], a[1])), idx);
+        }
+        let perimeter = along[samples.len()];
+
+        let mut sampled = Poly {
+            vertices: samples,
+            ..Default::default()
+        };
+        sampled.triang();
+        let adjacency = sampled.triangle_adjacency();
+
+        // circumcentres, numbered like the triangles
+        let point = |p: Point2<f32>| [p.x as f64, p.y as f64];
+        let centres: Vec<SkeletonNode> = sampled
+            .triangles
+            .iter()
+            .map(|triangle| {
+                let [a, b, c] = [point(triangle[0]), point(triangle[1]), point(triangle[2])];
+                let (ab, ac) = (sub(b, a), sub(c, a));
+                let denominator = 2.0 * orientation(a, b, c);
+                let (ab2, ac2) = (dot(ab, ab), dot(ac, ac));
+                let offset = [
+                    (ac[1] * ab2 - ab[1] * ac2) / denominator,
+                    (ab[0] * ac2 - ac[0] * ab2) / denominator,
+                ];
+                let centre = add(a, offset, 1.0);
+                SkeletonNode {
+                    position: [centre[0] as f32, centre[1] as f32],
+                    distance: offset[0].hypot(offset[1]) as f32,
+                }
+            })
+            .collect();
+
+        let mut skeleton = Skeleton::default();
+        let mut nodes: HashMap<usize, usize> = HashMap::new();
+        for (triangle, neighbour) in adjacency.edges() {
+            let [from, to] = neighbour.edge;
+            let (from_idx, to_idx) = (index[&key(from)], index[&key(to)]);
+            let forward = (along[from_idx] - along[to_idx]).abs();
+            let boundary = forward.min(perimeter - forward);
+            // a Voronoi edge between neighbouring samples on a straight
+            // piece of the boundary only leads to the boundary
+            if boundary <= distance(point(from), point(to)) * (1.0 + 1e-3) {
+                continue;
+            }
+            let mut node = |idx: usize| {
+                *nodes.entry(idx).or_insert_with(|| {
+                    skeleton.nodes.push(centres[idx]);
+                    skeleton.nodes.len() - 1
+                })
+            };
+            let arc = [node(triangle), node(neighbour.index)];
+            skeleton.arcs.push(arc);
+        }
+        skeleton
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{random_polygon, Method};
+
+    fn poly(vertices: Vec<[f32; 2]>) -> Poly {
+        Poly {
+            vertices,
+            ..Default::default()
+        }
+    }
+
+    /// Number of nodes reached from node 0 along the arcs
+    fn connected(skeleton: &Skeleton) -> usize {
+        let mut seen = vec![false; skeleton.nodes.len()];
+        let mut todo = vec![0];
+        seen[0] = true;
+        while let Some(node) = todo.pop() {
+            for arc in skeleton.arcs.iter() {
+                for &(from, to) in [(arc[0], arc[1]), (arc[1], arc[0])].iter() {
+                    if from == node && !seen[to] {
+                        seen[to] = true;
+                        todo.push(to);
+                    }
+                }
+            }
+        }
+        seen.iter().filter(|seen| **seen).count()
+    }
+
+    #[test]
+    fn test_skeleton_of_rectangles() {
+        let square = poly(vec![[0.0, 0.0], [2.0, 0.0], [2.0, 2.0], [0.0, 2.0]]);
+        let skeleton = square.straight_skeleton();
+        assert_eq!(skeleton.nodes.len(), 5);
+        assert_eq!(skeleton.nodes[4].position, [1.0, 1.0]);
+        assert_eq!(skeleton.nodes[4].distance, 1.0);
+        assert_eq!(skeleton.arcs.len(), 4);
+
+        // a ridge along the middle, and cw input
+        let rectangle = poly(vec![[0.0, 0.0], [0.0, 1.0], [3.0, 1.0], [3.0, 0.0]]);
+        let skeleton = rectangle.straight_skeleton();
+        assert_eq!(skeleton.nodes.len(), 6);
+        assert_eq!(skeleton.arcs.len(), 5);
+        let mut ridge: Vec<[f32; 2]> = skeleton.nodes[4..].iter().map(|n| n.position).collect();
+        ridge.sort_by(|a, b| a[0].total_cmp(&b[0]));
+        assert_eq!(ridge, vec![[0.5, 0.5], [2.5, 0.5]]);
+        assert!(skeleton.nodes[4..].iter().all(|n| n.distance == 0.5));
+        assert_eq!(connected(&skeleton), 6);
+    }
+
+    #[test]
+    fn test_skeletons_are_trees() {
+        for method in Method::ALL.iter() {
+            for seed in 0..4 {
+                let poly = poly(random_polygon(*method, 30, seed));
+                let skeleton = poly.straight_skeleton();
+                let nodes = skeleton.nodes.len();
+                assert_eq!(skeleton.arcs.len(), nodes - 1, "{} {}", method, seed);
+                assert_eq!(connected(&skeleton), nodes, "{} {}", method, seed);
+                // arcs lead away from the boundary
+                assert!(skeleton.arcs.iter().all(|[from, to]| {
+                    skeleton.nodes[*from].distance <= skeleton.nodes[*to].distance + 1e-6
+                }));
+            }
+        }
+    }
+
+    #[test]
+    fn test_medial_axis_of_a_rectangle() {
+        let rectangle = poly(vec![[0.0, 0.0], [3.0, 0.0], [3.0, 1.0], [0.0, 1.0]]);
+        let axis = rectangle.medial_axis(0.05);
+        assert!(!axis.arcs.is_empty());
+        assert_eq!(connected(&axis), axis.nodes.len());
+        // the centres are as far from the boundary as their distance
+        for node in axis.nodes.iter() {
+            let [x, y] = node.position;
+            let clearance = x.min(3.0 - x).min(y).min(1.0 - y);
+            assert!((node.distance - clearance).abs() < 0.05, "{:?}", node);
+        }
+        // and the ridge along the middle is there
+        assert!(axis
+            .nodes
+            .iter()
+            .any(|node| (node.position[1] - 0.5).abs() < 1e-3
+                && (node.position[0] - 1.5).abs() < 0.05));
+    }
+}
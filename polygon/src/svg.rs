@@ -0,0 +1,267 @@
+use crate::poly::Poly;
+use smart_default::SmartDefault;
+use std::fmt::Write;
+
+/// Fill colours of the triangles, cycled by triangle index.
+/// Same palette as the one used by the GUI.
+pub const TRIANGLE_PALETTE: [[u8; 3]; 6] = [
+    [78, 91, 207],
+    [212, 158, 11],
+    [115, 23, 43],
+    [52, 133, 75],
+    [237, 137, 7],
+    [17, 143, 130],
+];
+
+/// Fill colours of the convex parts, cycled by part index.
+pub const PART_PALETTE: [[u8; 3]; 6] = [
+    [99, 164, 186],
+    [191, 128, 189],
+    [143, 191, 128],
+    [235, 233, 117],
+    [227, 176, 132],
+    [137, 124, 222],
+];
+
+/// Layers and styling of the SVG export.
+/// The defaults reproduce what the GUI paints on the canvas.
+#[derive(SmartDefault, Clone, Debug)]
+pub struct SvgOptions {
+    #[default = true]
+    pub show_triangles: bool,
+    #[default = true]
+    pub show_convex_parts: bool,
+    #[default = true]
+    pub show_essentials: bool,
+    #[default = true]
+    pub show_edges: bool,
+    #[default = true]
+    pub show_vertices: bool,
+    #[default = 5.0]
+    pub vertex_radius: f32,
+    #[default = 2.0]
+    pub line_width: f32,
+    /// Space left around the bounding box of the polygon
+    #[default = 10.0]
+    pub margin: f32,
+    /// Background fill, e.g. "white"; transparent if None.  Written as an
+    /// attribute value, with the XML special characters escaped.
+    #[default(Some("white".to_string()))]
+    pub background: Option<String>,
+    #[default([160, 160, 160])]
+    pub edge_colour: [u8; 3],
+    #[default([96, 96, 96])]
+    pub vertex_colour: [u8; 3],
+    #[default([255, 0, 0])]
+    pub essential_colour: [u8; 3],
+}
+
+impl Poly {
+    /// Renders the polygon, its triangulation, convex parts and essential
+    /// diagonals as an SVG document.  Uses screen coordinates like the GUI,
+    /// i.e. the y axis points down.
+    pub fn to_svg(&self, options: &SvgOptions) -> String {
+        let mut svg = String::new();
+        let margin = options.margin;
+
+        // bounding box in screen coordinates
+        let mut min = [f32::INFINITY, f32::INFINITY];
+        let mut max = [f32::NEG_INFINITY, f32::NEG_INFINITY];
+        for v in self.vertices.iter() {
+            min = [min[0].min(v[0]), min[1].min(-v[1])];
+            max = [max[0].max(v[0]), max[1].max(-v[1])];
+        }
+        if self.vertices.is_empty() {
+            min = [0.0, 0.0];
+            max = [0.0, 0.0];
+        }
+        let width = max[0] - min[0] + 2.0 * margin;
+        let height = max[1] - min[1] + 2.0 * margin;
+
+        // writing to a String cannot fail, hence the unwraps
+        writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="{x} {y} {w} {h}">"#,
+            x = min[0] - margin,
+            y = min[1] - margin,
+            w = width,
+            h = height
+        )
+        .unwrap();
+
+        if let Some(background) = &options.background {
+            writeln!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"/>"#,
+                min[0] - margin,
+                min[1] - margin,
+                width,
+                height,
+                escape(background)
+            )
+            .unwrap();
+        }
+
+        if options.show_triangles {
+            writeln!(
+                svg,
+                r#"<g id="triangles" stroke="{}" stroke-width="{}" stroke-linejoin="round">"#,
+                rgb(options.edge_colour),
+                options.line_width
+            )
+            .unwrap();
+            for (idx, triangle) in self.triangles.iter().enumerate() {
+                let points: Vec<[f32; 2]> = triangle.iter().map(|p| [p.x, -p.y]).collect();
+                writeln!(
+                    svg,
+                    r#"  <polygon points="{}" fill="{}"/>"#,
+                    points_attribute(&points),
+                    rgb(TRIANGLE_PALETTE[idx % TRIANGLE_PALETTE.len()])
+                )
+                .unwrap();
+            }
+            writeln!(svg, "</g>").unwrap();
+        }
+
+        if options.show_convex_parts {
+            writeln!(svg, r#"<g id="convex-parts" stroke="none">"#).unwrap();
+            for (idx, part) in self.convex_part_cycles().iter().enumerate() {
+                let points: Vec<[f32; 2]> = part.iter().map(|p| [p.x, -p.y]).collect();
+                writeln!(
+                    svg,
+                    r#"  <polygon points="{}" fill="{}"/>"#,
+                    points_attribute(&points),
+                    rgb(PART_PALETTE[idx % PART_PALETTE.len()])
+                )
+                .unwrap();
+            }
+            writeln!(svg, "</g>").unwrap();
+        }
+
+        // essential diagonals are already stored in screen coordinates
+        if options.show_essentials {
+            writeln!(
+                svg,
+                r#"<g id="essential-diagonals" stroke="{}" stroke-width="{}" stroke-linecap="round">"#,
+                rgb(options.essential_colour),
+                options.line_width
+            )
+            .unwrap();
+            for essential in self.essential_diagonals.iter() {
+                writeln!(
+                    svg,
+                    r#"  <line x1="{}" y1="{}" x2="{}" y2="{}"/>"#,
+                    essential[0][0], essential[0][1], essential[1][0], essential[1][1]
+                )
+                .unwrap();
+            }
+            writeln!(svg, "</g>").unwrap();
+        }
+
+        if options.show_edges && !self.vertices.is_empty() {
+            let points: Vec<[f32; 2]> = self.vertices.iter().map(|v| [v[0], -v[1]]).collect();
+            writeln!(
+                svg,
+                r#"<polygon id="edges" points="{}" fill="none" stroke="{}" stroke-width="{}" stroke-linejoin="round"/>"#,
+                points_attribute(&points),
+                rgb(options.edge_colour),
+                options.line_width
+            )
+            .unwrap();
+        }
+
+        if options.show_vertices {
+            writeln!(
+                svg,
+                r#"<g id="vertices" fill="{}">"#,
+                rgb(options.vertex_colour)
+            )
+            .unwrap();
+            for v in self.vertices.iter() {
+                writeln!(
+                    svg,
+                    r#"  <circle cx="{}" cy="{}" r="{}"/>"#,
+                    v[0], -v[1], options.vertex_radius
+                )
+                .unwrap();
+            }
+            writeln!(svg, "</g>").unwrap();
+        }
+
+        writeln!(svg, "</svg>").unwrap();
+        svg
+    }
+}
+
+fn rgb(colour: [u8; 3]) -> String {
+    format!("rgb({},{},{})", colour[0], colour[1], colour[2])
+}
+
+/// The text with the characters that are special in XML attribute values
+/// replaced by entities
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn points_attribute(points: &[[f32; 2]]) -> String {
+    points
+        .iter()
+        .map(|p| format!("{},{}", p[0], p[1]))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::beak_poly;
+
+    #[test]
+    fn test_svg_layers() {
+        let svg = beak_poly().to_svg(&SvgOptions::default());
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert_eq!(svg.matches("<circle").count(), 4);
+        assert_eq!(svg.matches("<line").count(), 1);
+        // 2 triangles + 2 convex parts + the polygon outline
+        assert_eq!(svg.matches("<polygon").count(), 5);
+    }
+
+    #[test]
+    fn test_svg_toggled_layers() {
+        let options = SvgOptions {
+            show_triangles: false,
+            show_essentials: false,
+            show_vertices: false,
+            ..Default::default()
+        };
+        let svg = beak_poly().to_svg(&options);
+        assert!(!svg.contains("<circle"));
+        assert!(!svg.contains("<line"));
+        assert!(!svg.contains(r#"id="triangles""#));
+        assert_eq!(svg.matches("<polygon").count(), 3);
+    }
+
+    #[test]
+    fn test_svg_background_is_escaped() {
+        let options = SvgOptions {
+            background: Some(r#"red"/><script>alert(1)</script><rect fill="x"#.to_string()),
+            ..Default::default()
+        };
+        let svg = beak_poly().to_svg(&options);
+        assert!(!svg.contains("<script"));
+        assert!(svg.contains(r#"fill="red&quot;/&gt;&lt;script&gt;"#));
+        assert_eq!(svg.matches("<rect").count(), 1);
+    }
+}
@@ -0,0 +1,19 @@
+//! Fixtures shared by the tests of the crate.
+use crate::poly::Poly;
+
+/// Triangulated and decomposed polygon
+pub fn decomposed(vertices: Vec<[f32; 2]>) -> Poly {
+    let mut poly = Poly {
+        vertices,
+        ..Default::default()
+    };
+    poly.triang();
+    poly.decomposition();
+    poly
+}
+
+/// The quadrilateral of poly::tests::test_beak_poly: two triangles,
+/// two convex parts and one essential diagonal
+pub fn beak_poly() -> Poly {
+    decomposed(vec![[10.0, 10.0], [10.0, 5.0], [20.0, 0.0], [0.0, 0.0]])
+}
@@ -0,0 +1,362 @@
+//! Reading and writing of the file formats of Shewchuk's Triangle,
+//! see https://www.cs.cmu.edu/~quake/triangle.html
+use crate::error::{error, ParseError};
+use crate::mesh::Mesh;
+use crate::poly::Poly;
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// Contents of a Triangle .poly file, with 0-based vertex indices.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TrianglePoly {
+    /// Empty if the .poly file refers to a separate .node file,
+    /// which can be read with read_node
+    pub vertices: Vec<[f32; 2]>,
+    pub segments: Vec<[usize; 2]>,
+    pub holes: Vec<[f32; 2]>,
+}
+
+/// Non-empty lines with comments stripped, split into whitespace separated
+/// tokens, together with their line numbers.
+struct Lines<'a> {
+    lines: Vec<(usize, Vec<&'a str>)>,
+    position: usize,
+}
+
+impl<'a> Lines<'a> {
+    fn new(text: &'a str) -> Self {
+        let lines = text
+            .lines()
+            .enumerate()
+            .map(|(idx, line)| (idx + 1, line.split('#').next().unwrap_or("")))
+            .map(|(idx, line)| (idx, line.split_whitespace().collect::<Vec<&str>>()))
+            .filter(|(_, tokens)| !tokens.is_empty())
+            .collect();
+        Lines { lines, position: 0 }
+    }
+
+    /// Next line, which must have at least min_tokens tokens
+    fn next(&mut self, min_tokens: usize, what: &str) -> Result<(usize, Vec<&'a str>), ParseError> {
+        let last_line = self.lines.last().map_or(0, |(idx, _)| *idx);
+        let (idx, tokens) = self.lines.get(self.position).cloned().ok_or_else(|| {
+            error(
+                last_line,
+                &format!("unexpected end of file, expected {}", what),
+            )
+        })?;
+        if tokens.len() < min_tokens {
+            return Err(error(idx, &format!("expected {}", what)));
+        }
+        self.position += 1;
+        Ok((idx, tokens))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.position >= self.lines.len()
+    }
+}
+
+fn number<T: std::str::FromStr>(line: usize, token: &str) -> Result<T, ParseError> {
+    token
+        .parse()
+        .map_err(|_| error(line, &format!("invalid number {}", token)))
+}
+
+/// Reads the vertex list of a .node file (or of the beginning of a .poly
+/// file).  Returns the vertices and the index of the first vertex (0 or 1).
+fn read_vertices(lines: &mut Lines<'_>) -> Result<(Vec<[f32; 2]>, usize), ParseError> {
+    let (idx, header) = lines.next(1, "vertex count")?;
+    let count: usize = number(idx, header[0])?;
+    if header.len() > 1 && header[1] != "2" {
+        return Err(error(idx, "only two dimensional vertices are supported"));
+    }
+
+    let mut vertices = Vec::with_capacity(count);
+    let mut first_index = 0;
+    for n in 0..count {
+        let (idx, tokens) = lines.next(3, "vertex")?;
+        let index: usize = number(idx, tokens[0])?;
+        if n == 0 {
+            first_index = index;
+            if first_index > 1 {
+                return Err(error(idx, "vertex numbering must start at 0 or 1"));
+            }
+        }
+        if index != first_index + n {
+            return Err(error(idx, "vertices must be numbered consecutively"));
+        }
+        vertices.push([number(idx, tokens[1])?, number(idx, tokens[2])?]);
+    }
+    Ok((vertices, first_index))
+}
+
+/// Reads a Triangle .node file.
+pub fn read_node(text: &str) -> Result<Vec<[f32; 2]>, ParseError> {
+    let mut lines = Lines::new(text);
+    read_vertices(&mut lines).map(|(vertices, _)| vertices)
+}
+
+/// Reads a Triangle .poly file.  Vertex, segment and hole attributes
+/// and boundary markers as well as regional attributes are ignored.
+pub fn read_poly(text: &str) -> Result<TrianglePoly, ParseError> {
+    let mut lines = Lines::new(text);
+    let (vertices, mut first_index) = read_vertices(&mut lines)?;
+
+    let (idx, header) = lines.next(1, "segment count")?;
+    let segment_count: usize = number(idx, header[0])?;
+    let mut segments = Vec::with_capacity(segment_count);
+    let mut endpoints: Vec<(usize, [usize; 2])> = Vec::with_capacity(segment_count);
+    for _ in 0..segment_count {
+        let (idx, tokens) = lines.next(3, "segment")?;
+        endpoints.push((idx, [number(idx, tokens[1])?, number(idx, tokens[2])?]));
+    }
+    // with a separate .node file the numbering is only known from the segments
+    if vertices.is_empty() {
+        first_index = endpoints
+            .iter()
+            .map(|(_, [a, b])| *a.min(b))
+            .min()
+            .unwrap_or(0)
+            .min(1);
+    }
+    for (idx, [a, b]) in endpoints {
+        if a < first_index || b < first_index {
+            return Err(error(idx, "segment endpoint out of range"));
+        }
+        let segment = [a - first_index, b - first_index];
+        if !vertices.is_empty() && (segment[0] >= vertices.len() || segment[1] >= vertices.len()) {
+            return Err(error(idx, "segment endpoint out of range"));
+        }
+        segments.push(segment);
+    }
+
+    // the hole section is optional in files without holes
+    let mut holes = vec![];
+    if !lines.is_empty() {
+        let (idx, header) = lines.next(1, "hole count")?;
+        let hole_count: usize = number(idx, header[0])?;
+        for _ in 0..hole_count {
+            let (idx, tokens) = lines.next(3, "hole")?;
+            holes.push([number(idx, tokens[1])?, number(idx, tokens[2])?]);
+        }
+    }
+
+    Ok(TrianglePoly {
+        vertices,
+        segments,
+        holes,
+    })
+}
+
+/// Reads the triangles of a Triangle .ele file as 0-based vertex indices.
+pub fn read_ele(text: &str) -> Result<Vec<[usize; 3]>, ParseError> {
+    let mut lines = Lines::new(text);
+    let (idx, header) = lines.next(1, "triangle count")?;
+    let count: usize = number(idx, header[0])?;
+    if header.len() > 1 && header[1] != "3" {
+        return Err(error(idx, "only triangles with three nodes are supported"));
+    }
+
+    let mut triangles = Vec::with_capacity(count);
+    let mut first_index = 0;
+    for n in 0..count {
+        let (idx, tokens) = lines.next(4, "triangle")?;
+        // triangles are numbered from 0 or 1 like the vertices
+        if n == 0 {
+            first_index = number::<usize>(idx, tokens[0])?.min(1);
+        }
+        let mut triangle = [0; 3];
+        for (vertex, token) in triangle.iter_mut().zip(tokens[1..4].iter()) {
+            *vertex = number::<usize>(idx, token)?
+                .checked_sub(first_index)
+                .ok_or_else(|| error(idx, "vertex index out of range"))?;
+        }
+        triangles.push(triangle);
+    }
+    Ok(triangles)
+}
+
+impl TrianglePoly {
+    /// Chains the segments into the boundary of a simple polygon.
+    /// Fails if the segments do not form exactly one closed loop,
+    /// e.g. because the file describes holes.
+    pub fn to_poly(&self) -> Result<Poly, ParseError> {
+        if !self.holes.is_empty() {
+            return Err(error(0, "polygons with holes are not supported"));
+        }
+        if self.segments.len() < 3 {
+            return Err(error(0, "a polygon needs at least three segments"));
+        }
+
+        let mut neighbours: HashMap<usize, Vec<usize>> = HashMap::new();
+        for [a, b] in self.segments.iter() {
+            if *a >= self.vertices.len() || *b >= self.vertices.len() {
+                return Err(error(0, "segment endpoint out of range"));
+            }
+            neighbours.entry(*a).or_default().push(*b);
+            neighbours.entry(*b).or_default().push(*a);
+        }
+        if neighbours.values().any(|n| n.len() != 2) {
+            return Err(error(
+                0,
+                "every segment endpoint must belong to exactly two segments",
+            ));
+        }
+
+        // walk along the loop starting at the first segment
+        let start = self.segments[0][0];
+        let mut ring = vec![start];
+        let mut previous = start;
+        let mut current = self.segments[0][1];
+        while current != start {
+            ring.push(current);
+            let next = neighbours[&current]
+                .iter()
+                .cloned()
+                .find(|n| *n != previous)
+                .unwrap_or(previous);
+            previous = current;
+            current = next;
+        }
+        if ring.len() != neighbours.len() {
+            return Err(error(0, "the segments form more than one loop"));
+        }
+
+        Ok(Poly {
+            vertices: ring.iter().map(|idx| self.vertices[*idx]).collect(),
+            ..Default::default()
+        })
+    }
+}
+
+impl Mesh {
+    /// Writes the vertices as a Triangle .node file, numbered from 1.
+    pub fn write_node<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "{} 2 0 0", self.positions.len())?;
+        for (idx, p) in self.positions.iter().enumerate() {
+            writeln!(writer, "{} {} {}", idx + 1, p[0], p[1])?;
+        }
+        Ok(())
+    }
+
+    /// Writes the triangles as a Triangle .ele file, numbered from 1.
+    /// The convex part of each triangle is written as its attribute,
+    /// -1 for a triangle of no part.
+    pub fn write_ele<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let attributes = if self.triangle_parts.is_empty() { 0 } else { 1 };
+        writeln!(writer, "{} 3 {}", self.indices.len(), attributes)?;
+        for (idx, [a, b, c]) in self.indices.iter().enumerate() {
+            write!(writer, "{} {} {} {}", idx + 1, a + 1, b + 1, c + 1)?;
+            if attributes == 1 {
+                write!(
+                    writer,
+                    " {}",
+                    self.part_of(idx).map_or(-1, |part| part as i64)
+                )?;
+            }
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the edges of the triangulation as a Triangle .edge file,
+    /// numbered from 1.  Polygon edges have boundary marker 1, diagonals 0.
+    pub fn write_edge<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        // count in how many triangles every undirected edge occurs
+        let mut edges: Vec<[u32; 2]> = vec![];
+        let mut occurrences: HashMap<[u32; 2], usize> = HashMap::new();
+        for [a, b, c] in self.indices.iter() {
+            for [from, to] in [[*a, *b], [*b, *c], [*c, *a]].iter() {
+                let key = [*from.min(to), *from.max(to)];
+                let count = occurrences.entry(key).or_insert(0);
+                if *count == 0 {
+                    edges.push(key);
+                }
+                *count += 1;
+            }
+        }
+
+        writeln!(writer, "{} 1", edges.len())?;
+        for (idx, edge) in edges.iter().enumerate() {
+            let marker = if occurrences[edge] == 1 { 1 } else { 0 };
+            writeln!(
+                writer,
+                "{} {} {} {}",
+                idx + 1,
+                edge[0] + 1,
+                edge[1] + 1,
+                marker
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BEAK: &str = "# beak polygon
+4 2 0 1
+1 10.0 10.0 1
+2 10.0 5.0 1
+3 20.0 0.0 1
+4 0.0 0.0 1
+4 1
+1 1 2 1
+2 3 2 1 # reversed segment
+3 4 3 1
+4 4 1 1
+0
+";
+
+    #[test]
+    fn test_read_poly() {
+        let triangle_poly = read_poly(BEAK).unwrap();
+        assert_eq!(triangle_poly.vertices.len(), 4);
+        assert_eq!(triangle_poly.segments[1], [2, 1]);
+
+        let mut polygon = triangle_poly.to_poly().unwrap();
+        assert_eq!(
+            polygon.vertices,
+            vec![[10.0, 10.0], [10.0, 5.0], [20.0, 0.0], [0.0, 0.0]]
+        );
+        polygon.triang();
+        assert_eq!(polygon.triangles.len(), 2);
+    }
+
+    #[test]
+    fn test_read_errors() {
+        let error = read_poly("3 2 0 0\n1 0 0\n2 1 0\n").unwrap_err();
+        assert_eq!(error.line, 3);
+
+        let two_loops = "6 2 0 0\n0 0 0\n1 1 0\n2 0 1\n3 5 5\n4 6 5\n5 5 6\n\
+                         6 0\n0 0 1\n1 1 2\n2 2 0\n3 3 4\n4 4 5\n5 5 3\n";
+        assert!(read_poly(two_loops).unwrap().to_poly().is_err());
+    }
+
+    #[test]
+    fn test_write_and_read_back() {
+        let mut polygon = read_poly(BEAK).unwrap().to_poly().unwrap();
+        polygon.triang();
+        let mesh = polygon.mesh();
+
+        let mut node = Vec::new();
+        mesh.write_node(&mut node).unwrap();
+        let mut ele = Vec::new();
+        mesh.write_ele(&mut ele).unwrap();
+        let mut edge = Vec::new();
+        mesh.write_edge(&mut edge).unwrap();
+
+        let vertices = read_node(&String::from_utf8(node).unwrap()).unwrap();
+        let triangles = read_ele(&String::from_utf8(ele).unwrap()).unwrap();
+        assert_eq!(vertices, mesh.positions);
+        assert_eq!(triangles.len(), 2);
+        assert_eq!(triangles[0][0], mesh.indices[0][0] as usize);
+
+        // 4 polygon edges and 1 diagonal
+        let edge = String::from_utf8(edge).unwrap();
+        assert!(edge.starts_with("5 1"));
+        assert_eq!(edge.lines().filter(|l| l.ends_with(" 0")).count(), 1);
+    }
+}
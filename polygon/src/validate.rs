@@ -0,0 +1,422 @@
+//! Runtime check of a convex decomposition, for consumers that want to
+//! verify the parts before handing them on, e.g. as collision shapes.
+use crate::error::DecompositionError;
+use crate::geometry::{convex_overlap, doubled_area, key, orientation, segments_intersect};
+use crate::poly::Poly;
+use std::collections::{HashMap, HashSet};
+
+/// Uniform grid over the polygon edges, so that a diagonal is only tested
+/// against the edges near it.
+pub(crate) struct EdgeGrid {
+    min: [f64; 2],
+    cell: f64,
+    size: usize,
+    cells: Vec<Vec<usize>>,
+}
+
+impl EdgeGrid {
+    pub(crate) fn new(outline: &[[f64; 2]]) -> EdgeGrid {
+        let mut min = [f64::INFINITY; 2];
+        let mut max = [f64::NEG_INFINITY; 2];
+        for p in outline.iter() {
+            for axis in 0..2 {
+                min[axis] = min[axis].min(p[axis]);
+                max[axis] = max[axis].max(p[axis]);
+            }
+        }
+        let size = ((outline.len() as f64).sqrt().ceil() as usize).max(1);
+        let extent = (max[0] - min[0])
+            .max(max[1] - min[1])
+            .max(f64::MIN_POSITIVE);
+        let mut grid = EdgeGrid {
+            min,
+            cell: extent / size as f64,
+            size,
+            cells: vec![vec![]; size * size],
+        };
+        for idx in 0..outline.len() {
+            let (a, b) = (outline[idx], outline[(idx + 1) % outline.len()]);
+            for cell in grid.cells_along(a, b) {
+                grid.cells[cell].push(idx);
+            }
+        }
+        grid
+    }
+
+    fn coordinate(&self, value: f64, axis: usize) -> usize {
+        let cell = ((value - self.min[axis]) / self.cell).floor();
+        (cell.max(0.0) as usize).min(self.size - 1)
+    }
+
+    /// Cells crossed by the segment ab, column by column.  The ranges are
+    /// widened by one cell against rounding.
+    fn cells_along(&self, a: [f64; 2], b: [f64; 2]) -> Vec<usize> {
+        let (a, b) = if a[0] <= b[0] { (a, b) } else { (b, a) };
+        let first = self.coordinate(a[0], 0).saturating_sub(1);
+        let last = (self.coordinate(b[0], 0) + 1).min(self.size - 1);
+        let mut cells = vec![];
+        for column in first..=last {
+            let left = (self.min[0] + column as f64 * self.cell).max(a[0]);
+            let right = (self.min[0] + (column + 1) as f64 * self.cell).min(b[0]);
+            let (low, high) = if b[0] > a[0] {
+                let y =
+                    |x: f64| a[1] + (b[1] - a[1]) * ((x - a[0]) / (b[0] - a[0])).clamp(0.0, 1.0);
+                let (y0, y1) = (y(left), y(right));
+                (y0.min(y1), y0.max(y1))
+            } else {
+                (a[1].min(b[1]), a[1].max(b[1]))
+            };
+            let low = self.coordinate(low, 1).saturating_sub(1);
+            let high = (self.coordinate(high, 1) + 1).min(self.size - 1);
+            cells.extend((low..=high).map(|row| row * self.size + column));
+        }
+        cells
+    }
+
+    /// True if the point lies in the ccw outline or within the tolerance,
+    /// on the doubled triangle areas, of its boundary.  Counts the edges
+    /// crossed by the ray to the right of the point among those in the
+    /// cells along the ray.
+    fn contains(&self, outline: &[[f64; 2]], point: [f64; 2], tolerance: f64) -> bool {
+        let end = [self.min[0] + self.cell * self.size as f64, point[1]];
+        let mut inside = false;
+        for edge in self.edges_near(point, end) {
+            let (a, b) = (outline[edge], outline[(edge + 1) % outline.len()]);
+            let d = [b[0] - a[0], b[1] - a[1]];
+            let along =
+                ((point[0] - a[0]) * d[0] + (point[1] - a[1]) * d[1]) / (d[0] * d[0] + d[1] * d[1]);
+            if orientation(a, b, point).abs() <= tolerance && (0.0..=1.0).contains(&along) {
+                return true;
+            }
+            if (a[1] > point[1]) != (b[1] > point[1])
+                && point[0] < a[0] + (b[0] - a[0]) * (point[1] - a[1]) / (b[1] - a[1])
+            {
+                inside = !inside;
+            }
+        }
+        inside
+    }
+
+    /// Indices of the edges in the cells crossed by the segment ab
+    pub(crate) fn edges_near(&self, a: [f64; 2], b: [f64; 2]) -> Vec<usize> {
+        let mut edges: Vec<usize> = self
+            .cells_along(a, b)
+            .into_iter()
+            .flat_map(|cell| self.cells[cell].iter().cloned())
+            .collect();
+        edges.sort_unstable();
+        edges.dedup();
+        edges
+    }
+}
+
+/// The segment from vertex i to vertex j leaves the ccw outline in
+/// neither of its end points (O'Rourke's in-cone test).
+fn in_cone(outline: &[[f64; 2]], i: usize, j: usize) -> bool {
+    let length = outline.len();
+    let prev = outline[(i + length - 1) % length];
+    let next = outline[(i + 1) % length];
+    let (a, b) = (outline[i], outline[j]);
+    if orientation(a, next, prev) >= 0.0 {
+        orientation(a, b, prev) > 0.0 && orientation(b, a, next) > 0.0
+    } else {
+        !(orientation(a, b, next) >= 0.0 && orientation(b, a, prev) >= 0.0)
+    }
+}
+
+/// True if the part edge from vertex i to vertex j of the ccw outline lies
+/// in the polygon, with the part on its left: either an edge of the outline
+/// or a diagonal.
+pub(crate) fn edge_inside(outline: &[[f64; 2]], grid: &EdgeGrid, i: usize, j: usize) -> bool {
+    let length = outline.len();
+    if j == (i + 1) % length {
+        return true;
+    }
+    if i == (j + 1) % length || i == j {
+        return false;
+    }
+    if !in_cone(outline, i, j) || !in_cone(outline, j, i) {
+        return false;
+    }
+    let (a, b) = (outline[i], outline[j]);
+    grid.edges_near(a, b).into_iter().all(|edge| {
+        let next = (edge + 1) % length;
+        edge == i
+            || edge == j
+            || next == i
+            || next == j
+            || !segments_intersect(a, b, outline[edge], outline[next])
+    })
+}
+
+/// True if the part edge from a to b, with a Steiner point at one end or
+/// both, lies in the polygon: it crosses no edge of the ccw outline by
+/// more than the tolerance and its middle is in the outline.  Steiner
+/// points on the outline are rounded off it, hence the tolerance.
+fn steiner_edge_inside(
+    outline: &[[f64; 2]],
+    grid: &EdgeGrid,
+    a: [f64; 2],
+    b: [f64; 2],
+    tolerance: f64,
+) -> bool {
+    let crossing = grid.edges_near(a, b).into_iter().any(|edge| {
+        let (p, q) = (outline[edge], outline[(edge + 1) % outline.len()]);
+        let sides = [
+            orientation(a, b, p),
+            orientation(a, b, q),
+            orientation(p, q, a),
+            orientation(p, q, b),
+        ];
+        sides[0] * sides[1] < 0.0
+            && sides[2] * sides[3] < 0.0
+            && sides.iter().all(|side| side.abs() > tolerance)
+    });
+    let middle = [(a[0] + b[0]) / 2.0, (a[1] + b[1]) / 2.0];
+    !crossing && grid.contains(outline, middle, tolerance)
+}
+
+impl Poly {
+    /// Checks that the convex parts decompose the polygon: every part is
+    /// convex and in ccw order, its vertices are vertices of the polygon
+    /// or Steiner points and its edges lie in the polygon, no two parts
+    /// overlap and together they have the area
+    /// of the polygon, so they cover it.
+    /// Returns all the violations found.
+    pub fn check_decomposition(&self) -> Result<(), Vec<DecompositionError>> {
+        let parts: Vec<Vec<[f64; 2]>> = self
+            .convex_part_cycles()
+            .iter()
+            .map(|part| part.iter().map(|p| [p.x as f64, p.y as f64]).collect())
+            .collect();
+        if parts.is_empty() {
+            return Err(vec![DecompositionError::NoParts]);
+        }
+
+        let mut outline: Vec<[f64; 2]> = self
+            .vertices
+            .iter()
+            .map(|v| [v[0] as f64, v[1] as f64])
+            .collect();
+        if doubled_area(&outline) < 0.0 {
+            outline.reverse();
+        }
+        let index: HashMap<[u64; 2], usize> = outline
+            .iter()
+            .enumerate()
+            .map(|(idx, p)| (key(*p), idx))
+            .collect();
+        let steiner_points: HashSet<[u64; 2]> = self
+            .steiner_points
+            .iter()
+            .map(|p| key([p[0] as f64, p[1] as f64]))
+            .collect();
+        let grid = EdgeGrid::new(&outline);
+        // cross products are compared relative to the size of the polygon
+        let extent = grid.cell * grid.size as f64;
+        let mut tolerance = 1e-9 * extent * extent;
+        // Steiner points are rounded to f32, which moves them off the
+        // edges they split by up to half a unit in the last place
+        if !steiner_points.is_empty() {
+            let magnitude = outline
+                .iter()
+                .map(|p| p[0].abs().max(p[1].abs()))
+                .fold(0.0, f64::max);
+            tolerance = tolerance.max(2.0 * f32::EPSILON as f64 * magnitude * extent);
+        }
+
+        let mut errors = vec![];
+        let mut valid_parts = vec![];
+        for (part_idx, part) in parts.iter().enumerate() {
+            let length = part.len();
+            if length < 3 {
+                errors.push(DecompositionError::DegeneratePart(part_idx));
+                continue;
+            }
+            if doubled_area(part) <= 0.0 {
+                errors.push(DecompositionError::ClockwisePart(part_idx));
+                continue;
+            }
+            valid_parts.push(part_idx);
+
+            for vertex in 0..length {
+                let prev = part[(vertex + length - 1) % length];
+                let next = part[(vertex + 1) % length];
+                if orientation(prev, part[vertex], next) < -tolerance {
+                    errors.push(DecompositionError::ReflexVertex {
+                        part: part_idx,
+                        vertex,
+                    });
+                }
+            }
+
+            let indices: Vec<Option<usize>> =
+                part.iter().map(|p| index.get(&key(*p)).cloned()).collect();
+            let steiner: Vec<bool> = part
+                .iter()
+                .map(|p| steiner_points.contains(&key(*p)))
+                .collect();
+            for (vertex, idx) in indices.iter().enumerate() {
+                let next = (vertex + 1) % length;
+                let inside = match (idx, indices[next]) {
+                    (None, _) if !steiner[vertex] => {
+                        errors.push(DecompositionError::ForeignVertex {
+                            part: part_idx,
+                            vertex,
+                        });
+                        continue;
+                    }
+                    (Some(i), Some(j)) => edge_inside(&outline, &grid, *i, j),
+                    // the next vertex is reported on its own if foreign
+                    (_, None) if !steiner[next] => true,
+                    _ => steiner_edge_inside(&outline, &grid, part[vertex], part[next], tolerance),
+                };
+                if !inside {
+                    errors.push(DecompositionError::EdgeOutside {
+                        part: part_idx,
+                        vertex,
+                    });
+                }
+            }
+        }
+
+        // sweep over the parts sorted by their leftmost x
+        let bounds = |part: &[[f64; 2]]| {
+            let mut min = [f64::INFINITY; 2];
+            let mut max = [f64::NEG_INFINITY; 2];
+            for p in part.iter() {
+                for axis in 0..2 {
+                    min[axis] = min[axis].min(p[axis]);
+                    max[axis] = max[axis].max(p[axis]);
+                }
+            }
+            (min, max)
+        };
+        let mut sorted: Vec<(usize, [f64; 2], [f64; 2])> = valid_parts
+            .iter()
+            .map(|idx| {
+                let (min, max) = bounds(&parts[*idx]);
+                (*idx, min, max)
+            })
+            .collect();
+        sorted.sort_by(|a, b| a.1[0].total_cmp(&b.1[0]));
+        for (position, (a, a_min, a_max)) in sorted.iter().enumerate() {
+            for (b, b_min, b_max) in sorted[position + 1..].iter() {
+                if b_min[0] >= a_max[0] {
+                    break;
+                }
+                if b_min[1] < a_max[1]
+                    && a_min[1] < b_max[1]
+                    && convex_overlap(&parts[*a], &parts[*b], tolerance)
+                {
+                    errors.push(DecompositionError::Overlap(*a.min(b), *a.max(b)));
+                }
+            }
+        }
+
+        let polygon_area = doubled_area(&outline) / 2.0;
+        let parts_area: f64 = parts
+            .iter()
+            .map(|part| doubled_area(part).abs())
+            .sum::<f64>()
+            / 2.0;
+        if (parts_area - polygon_area).abs() > 1e-6 * polygon_area {
+            errors.push(DecompositionError::AreaMismatch {
+                polygon: polygon_area,
+                parts: parts_area,
+            });
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::beak_poly;
+    use cgmath::Point2;
+
+    /// Convex parts in the [point1, point2], [point2, point3], ... format
+    fn parts(cycles: &[&[[f32; 2]]]) -> Vec<Vec<Point2<f32>>> {
+        cycles
+            .iter()
+            .map(|cycle| {
+                (0..cycle.len())
+                    .flat_map(|idx| {
+                        let a = cycle[idx];
+                        let b = cycle[(idx + 1) % cycle.len()];
+                        vec![Point2::new(a[0], a[1]), Point2::new(b[0], b[1])]
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_decomposition() {
+        assert_eq!(beak_poly().check_decomposition(), Ok(()));
+        assert_eq!(
+            Poly::default().check_decomposition(),
+            Err(vec![DecompositionError::NoParts])
+        );
+    }
+
+    #[test]
+    fn test_violations() {
+        // the beak in ccw order is d, c, b, a with a reflex vertex at b
+        let mut polygon = beak_poly();
+        let [a, b, c, d] = [[10.0, 10.0], [10.0, 5.0], [20.0, 0.0], [0.0, 0.0]];
+
+        polygon.convex_parts = parts(&[&[d, c, b, a]]);
+        assert_eq!(
+            polygon.check_decomposition(),
+            Err(vec![DecompositionError::ReflexVertex {
+                part: 0,
+                vertex: 2
+            }])
+        );
+
+        polygon.convex_parts = parts(&[&[d, c, b], &[a, d, c]]);
+        let errors = polygon.check_decomposition().unwrap_err();
+        assert!(errors.contains(&DecompositionError::EdgeOutside { part: 1, vertex: 2 }));
+        assert!(errors.contains(&DecompositionError::Overlap(0, 1)));
+
+        let foreign = [[1.0, 1.0], [2.0, 1.0], [1.0, 2.0]];
+        polygon.convex_parts = parts(&[&[d, c, b], &[d, b, a], &foreign]);
+        let errors = polygon.check_decomposition().unwrap_err();
+        assert!(errors.contains(&DecompositionError::ForeignVertex { part: 2, vertex: 0 }));
+        assert!(errors.contains(&DecompositionError::Overlap(1, 2)));
+        assert!(matches!(
+            errors.last(),
+            Some(DecompositionError::AreaMismatch { .. })
+        ));
+
+        polygon.convex_parts = parts(&[&[d, c, b], &[a, b, d]]);
+        assert_eq!(
+            polygon.check_decomposition(),
+            Err(vec![DecompositionError::ClockwisePart(1)])
+        );
+    }
+
+    #[test]
+    fn test_steiner_edges() {
+        // s splits the edge from c to b
+        let mut polygon = beak_poly();
+        let [a, b, c, d] = [[10.0, 10.0], [10.0, 5.0], [20.0, 0.0], [0.0, 0.0]];
+        let s = [15.0, 2.5];
+        polygon.steiner_points = vec![s];
+        polygon.convex_parts = parts(&[&[d, c, s], &[d, s, b], &[d, b, a]]);
+        assert_eq!(polygon.check_decomposition(), Ok(()));
+
+        // the edge from s to a runs outside, between two boundary points
+        polygon.convex_parts = parts(&[&[d, c, s], &[d, s, a]]);
+        let errors = polygon.check_decomposition().unwrap_err();
+        assert!(errors.contains(&DecompositionError::EdgeOutside { part: 1, vertex: 1 }));
+        assert!(!errors.contains(&DecompositionError::EdgeOutside { part: 1, vertex: 0 }));
+    }
+}
@@ -0,0 +1,465 @@
+//! Visibility graph and Euclidean shortest paths inside the polygon.
+//! A shortest path between two points of a simple polygon is a polyline
+//! that bends only at reflex vertices, so the graph has the reflex
+//! vertices as nodes and joins two of them if they see each other.
+//! Polygons with holes are not supported by Poly yet; their vertices
+//! would become nodes in the same way.
+use crate::geometry::{distance, on_segment, orientation, ring_contains, Open};
+use crate::poly::Poly;
+use crate::validate::EdgeGrid;
+use std::collections::BinaryHeap;
+use std::f64::consts::PI;
+
+/// Reflex vertices of a polygon and which of them see each other
+pub struct VisibilityGraph {
+    /// Positions of the reflex vertices
+    pub nodes: Vec<[f32; 2]>,
+    /// Indices of the nodes seen from every node
+    pub edges: Vec<Vec<usize>>,
+    /// The polygon in ccw order
+    outline: Vec<[f64; 2]>,
+    grid: EdgeGrid,
+}
+
+impl Poly {
+    /// Visibility graph of the reflex vertices.  Takes the vertices in
+    /// either orientation.  Every pair of reflex vertices is tested
+    /// against the edges near the segment between them.
+    pub fn visibility_graph(&self) -> VisibilityGraph {
+        let mut outline: Vec<[f64; 2]> = self
+            .vertices
+            .iter()
+            .map(|v| [v[0] as f64, v[1] as f64])
+            .collect();
+        if self.doubled_area() < 0.0 {
+            outline.reverse();
+        }
+        let length = outline.len();
+        let reflex: Vec<usize> = (0..length)
+            .filter(|idx| {
+                let prev = outline[(idx + length - 1) % length];
+                orientation(prev, outline[*idx], outline[(idx + 1) % length]) < 0.0
+            })
+            .collect();
+
+        let grid = EdgeGrid::new(&outline);
+        let mut graph = VisibilityGraph {
+            nodes: reflex
+                .iter()
+                .map(|idx| [outline[*idx][0] as f32, outline[*idx][1] as f32])
+                .collect(),
+            edges: vec![vec![]; reflex.len()],
+            outline,
+            grid,
+        };
+        for (a, from) in reflex.iter().enumerate() {
+            for (b, to) in reflex.iter().enumerate().skip(a + 1) {
+                if graph.visible(graph.outline[*from], graph.outline[*to]) {
+                    graph.edges[a].push(b);
+                    graph.edges[b].push(a);
+                }
+            }
+        }
+        graph
+    }
+
+    /// Shortest path from the start to the goal inside the polygon, see
+    /// VisibilityGraph::shortest_path.  Builds the visibility graph
+    /// first; for several queries on the same polygon build it once.
+    pub fn shortest_path(&self, start: [f32; 2], goal: [f32; 2]) -> Option<Vec<[f32; 2]>> {
+        self.visibility_graph().shortest_path(start, goal)
+    }
+
+    /// Region of the polygon seen from the point, see
+    /// VisibilityGraph::visible_region.
+    pub fn visibility_region(&self, point: [f32; 2]) -> Option<Vec<[f32; 2]>> {
+        self.visibility_graph().visible_region(point)
+    }
+}
+
+impl VisibilityGraph {
+    /// True if the segment from vertex idx towards the point does not
+    /// leave the polygon at the vertex.  A segment along one of the
+    /// edges at the vertex stays on the boundary and counts as inside.
+    fn opens_inside(&self, idx: usize, point: [f64; 2]) -> bool {
+        let length = self.outline.len();
+        let prev = self.outline[(idx + length - 1) % length];
+        let next = self.outline[(idx + 1) % length];
+        let vertex = self.outline[idx];
+        let (left_of_next, left_of_prev) = (
+            orientation(vertex, next, point) >= 0.0,
+            orientation(prev, vertex, point) >= 0.0,
+        );
+        if orientation(prev, vertex, next) >= 0.0 {
+            left_of_next && left_of_prev
+        } else {
+            left_of_next || left_of_prev
+        }
+    }
+
+    /// True if the closed segment pq lies in the closed polygon, given
+    /// that p and q do.  The segment may touch the boundary, e.g. run
+    /// along an edge or through a vertex, but it may not cross it.
+    pub(crate) fn visible(&self, p: [f64; 2], q: [f64; 2]) -> bool {
+        let length = self.outline.len();
+        self.grid.edges_near(p, q).into_iter().all(|edge| {
+            let (a, b) = (self.outline[edge], self.outline[(edge + 1) % length]);
+            let (a_side, b_side) = (orientation(p, q, a), orientation(p, q, b));
+            let (p_side, q_side) = (orientation(a, b, p), orientation(a, b, q));
+            if a_side * b_side < 0.0 && p_side * q_side < 0.0 {
+                return false;
+            }
+            // an end point on the edge must look into the polygon
+            let within = |point: [f64; 2]| point != a && point != b && on_segment(a, b, point);
+            if (p_side == 0.0 && within(p) && q_side < 0.0)
+                || (q_side == 0.0 && within(q) && p_side < 0.0)
+            {
+                return false;
+            }
+            // and so must the segment at a vertex on it; every vertex
+            // starts one edge, so it is looked at once
+            if a_side != 0.0 || !on_segment(p, q, a) {
+                return true;
+            }
+            (a == p || self.opens_inside(edge, p)) && (a == q || self.opens_inside(edge, q))
+        })
+    }
+
+    /// True if the point lies inside the polygon or on its boundary
+    fn contains(&self, point: [f64; 2]) -> bool {
+        ring_contains(&self.outline, point, true)
+    }
+
+    /// First point of the boundary hit by the ray from the point in the
+    /// direction, skipping the edges in skip.  The ends of the edges are
+    /// given a little slack, so that a ray through a vertex hits one of
+    /// its edges.
+    fn cast(
+        &self,
+        from: [f64; 2],
+        direction: [f64; 2],
+        reach: f64,
+        skip: &[usize],
+    ) -> Option<[f64; 2]> {
+        let length = self.outline.len();
+        let to = [
+            from[0] + reach * direction[0],
+            from[1] + reach * direction[1],
+        ];
+        let mut nearest: Option<(f64, [f64; 2])> = None;
+        for edge in self.grid.edges_near(from, to) {
+            if skip.contains(&edge) {
+                continue;
+            }
+            let (a, b) = (self.outline[edge], self.outline[(edge + 1) % length]);
+            let along = [b[0] - a[0], b[1] - a[1]];
+            let denominator = direction[0] * along[1] - direction[1] * along[0];
+            if denominator == 0.0 {
+                continue;
+            }
+            let offset = [a[0] - from[0], a[1] - from[1]];
+            let t = (offset[0] * along[1] - offset[1] * along[0]) / denominator;
+            let u = (offset[0] * direction[1] - offset[1] * direction[0]) / denominator;
+            if t > 0.0
+                && (-1e-12..=1.0 + 1e-12).contains(&u)
+                && nearest.map_or(true, |(s, _)| t < s)
+            {
+                let u = u.clamp(0.0, 1.0);
+                nearest = Some((t, [a[0] + u * along[0], a[1] + u * along[1]]));
+            }
+        }
+        nearest.map(|(_, point)| point)
+    }
+
+    /// Region of the polygon seen from the point, as a ccw polygon, or
+    /// None if the point lies outside.  The region is star-shaped around
+    /// the point and starts at it if the point lies on the boundary.
+    /// Rays are cast just past either side of every polygon vertex seen
+    /// from the point, so the corners of the region are off by a hair.
+    pub fn visible_region(&self, point: [f32; 2]) -> Option<Vec<[f32; 2]>> {
+        let from = [point[0] as f64, point[1] as f64];
+        if !self.contains(from) {
+            return None;
+        }
+        let length = self.outline.len();
+        let mut min = [f64::INFINITY; 2];
+        let mut max = [f64::NEG_INFINITY; 2];
+        for p in self.outline.iter() {
+            for axis in 0..2 {
+                min[axis] = min[axis].min(p[axis]);
+                max[axis] = max[axis].max(p[axis]);
+            }
+        }
+        let reach = 2.0 * (max[0] - min[0]).hypot(max[1] - min[1]);
+
+        // angles are measured ccw from the edge leaving the point, if it
+        // lies on the boundary, so the region spans from zero to the
+        // inner angle there
+        let angle_to = |p: [f64; 2]| (p[1] - from[1]).atan2(p[0] - from[0]);
+        let on_edge = (0..length).find(|edge| {
+            let (a, b) = (self.outline[*edge], self.outline[(edge + 1) % length]);
+            orientation(a, b, from) == 0.0 && on_segment(a, b, from)
+        });
+        let vertex = self.outline.iter().position(|v| *v == from);
+        let (base, opening, skip) = match (vertex, on_edge) {
+            (Some(idx), _) => {
+                let (prev, next) = ((idx + length - 1) % length, (idx + 1) % length);
+                let base = angle_to(self.outline[next]);
+                let opening = (angle_to(self.outline[prev]) - base).rem_euclid(2.0 * PI);
+                (base, opening, vec![prev, idx])
+            }
+            (None, Some(edge)) => (angle_to(self.outline[(edge + 1) % length]), PI, vec![edge]),
+            (None, None) => (0.0, 2.0 * PI, vec![]),
+        };
+        let boundary = !skip.is_empty();
+
+        let mut rays = vec![];
+        for v in self.outline.iter() {
+            if *v == from || !self.visible(from, *v) {
+                continue;
+            }
+            for side in [-1e-9, 1e-9].iter() {
+                let angle = (angle_to(*v) - base + side).rem_euclid(2.0 * PI);
+                if boundary && (angle <= 0.0 || angle >= opening) {
+                    continue;
+                }
+                let direction = [(base + angle).cos(), (base + angle).sin()];
+                if let Some(hit) = self.cast(from, direction, reach, &skip) {
+                    rays.push((angle, hit));
+                }
+            }
+        }
+        rays.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut region: Vec<[f64; 2]> = if boundary { vec![from] } else { vec![] };
+        region.extend(rays.into_iter().map(|(_, hit)| hit));
+        // rays just past either side of a vertex hit next to each other
+        let tolerance = 1e-7 * reach;
+        let close = |a: [f64; 2], b: [f64; 2]| (b[0] - a[0]).hypot(b[1] - a[1]) <= tolerance;
+        region.dedup_by(|b, a| close(*a, *b));
+        while region.len() > 1 && close(region[0], region[region.len() - 1]) {
+            region.pop();
+        }
+        Some(
+            region
+                .into_iter()
+                .map(|p| [p[0] as f32, p[1] as f32])
+                .collect(),
+        )
+    }
+
+    /// Euclidean shortest path from the start to the goal inside the
+    /// polygon, found by Dijkstra's algorithm over the nodes seen from the
+    /// start and the goal.  A polyline from the start to the goal whose
+    /// inner points are reflex vertices, or None if the start or the goal
+    /// lies outside the polygon.
+    pub fn shortest_path(&self, start: [f32; 2], goal: [f32; 2]) -> Option<Vec<[f32; 2]>> {
+        let point = |p: [f32; 2]| [p[0] as f64, p[1] as f64];
+        let (from, to) = (point(start), point(goal));
+        if !self.contains(from) || !self.contains(to) {
+            return None;
+        }
+        if self.visible(from, to) {
+            return Some(vec![start, goal]);
+        }
+
+        // the start and the goal are nodes after the reflex vertices
+        let positions: Vec<[f64; 2]> = self.nodes.iter().map(|p| point(*p)).collect();
+        let (first, last) = (self.nodes.len(), self.nodes.len() + 1);
+        let to_goal: Vec<bool> = positions.iter().map(|p| self.visible(*p, to)).collect();
+        let position = |node: usize| match node {
+            node if node == first => from,
+            node if node == last => to,
+            node => positions[node],
+        };
+
+        let mut distances = vec![f64::INFINITY; last + 1];
+        let mut came_from = vec![first; last + 1];
+        let mut open = BinaryHeap::new();
+        distances[first] = 0.0;
+        open.push(Open {
+            cost: 0.0,
+            index: first,
+        });
+        while let Some(Open {
+            cost: done,
+            index: node,
+        }) = open.pop()
+        {
+            if node == last {
+                let mut path = vec![goal];
+                let mut node = last;
+                while node != first {
+                    node = came_from[node];
+                    path.push(if node == first {
+                        start
+                    } else {
+                        self.nodes[node]
+                    });
+                }
+                path.reverse();
+                return Some(path);
+            }
+            if done > distances[node] {
+                continue;
+            }
+            let seen: Vec<usize> = if node == first {
+                (0..first)
+                    .filter(|idx| self.visible(from, positions[*idx]))
+                    .collect()
+            } else {
+                let goal_seen = if to_goal[node] { Some(last) } else { None };
+                self.edges[node].iter().cloned().chain(goal_seen).collect()
+            };
+            for next in seen {
+                let through = done + distance(position(node), position(next));
+                if through < distances[next] {
+                    distances[next] = through;
+                    came_from[next] = node;
+                    open.push(Open {
+                        cost: through,
+                        index: next,
+                    });
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{random_polygon, Method};
+
+    fn length(path: &[[f32; 2]]) -> f64 {
+        path.windows(2)
+            .map(|pair| {
+                distance(
+                    [pair[0][0] as f64, pair[0][1] as f64],
+                    [pair[1][0] as f64, pair[1][1] as f64],
+                )
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_path_around_the_bend() {
+        let mut u_shape = vec![
+            [0.0, 0.0],
+            [3.0, 0.0],
+            [3.0, 3.0],
+            [2.0, 3.0],
+            [2.0, 1.0],
+            [1.0, 1.0],
+            [1.0, 3.0],
+            [0.0, 3.0],
+        ];
+        // either orientation
+        for _ in 0..2 {
+            let poly = Poly {
+                vertices: u_shape.clone(),
+                ..Default::default()
+            };
+            let graph = poly.visibility_graph();
+            assert_eq!(graph.nodes.len(), 2);
+            assert_eq!(graph.edges, vec![vec![1], vec![0]]);
+
+            let path = graph.shortest_path([0.5, 2.5], [2.5, 2.5]).unwrap();
+            assert_eq!(path, vec![[0.5, 2.5], [1.0, 1.0], [2.0, 1.0], [2.5, 2.5]]);
+            // along the boundary
+            let path = graph.shortest_path([0.0, 3.0], [3.0, 3.0]).unwrap();
+            assert_eq!(path, vec![[0.0, 3.0], [1.0, 1.0], [2.0, 1.0], [3.0, 3.0]]);
+            let path = graph.shortest_path([0.0, 0.5], [3.0, 0.5]).unwrap();
+            assert_eq!(path, vec![[0.0, 0.5], [3.0, 0.5]]);
+            // across the mouth of the notch
+            let path = graph.shortest_path([1.0, 2.0], [2.0, 2.0]).unwrap();
+            assert_eq!(path, vec![[1.0, 2.0], [1.0, 1.0], [2.0, 1.0], [2.0, 2.0]]);
+
+            assert_eq!(graph.shortest_path([0.5, 2.5], [1.5, 2.0]), None);
+            u_shape.reverse();
+        }
+    }
+
+    #[test]
+    fn test_visible_region() {
+        let area = |region: &[[f32; 2]]| {
+            let poly = Poly {
+                vertices: region.to_vec(),
+                ..Default::default()
+            };
+            poly.doubled_area() / 2.0
+        };
+        let square = Poly {
+            vertices: vec![[0.0, 0.0], [2.0, 0.0], [2.0, 2.0], [0.0, 2.0]],
+            ..Default::default()
+        };
+        let graph = square.visibility_graph();
+        for point in [[1.0, 1.0], [0.0, 0.0], [1.0, 0.0], [2.0, 0.5]] {
+            let region = graph.visible_region(point).unwrap();
+            assert!((area(&region) - 4.0).abs() < 1e-5, "{:?}", point);
+        }
+        assert_eq!(graph.visible_region([3.0, 1.0]), None);
+
+        // the notch hides most of the right arm of a U
+        let u_shape = Poly {
+            vertices: vec![
+                [0.0, 0.0],
+                [3.0, 0.0],
+                [3.0, 3.0],
+                [2.0, 3.0],
+                [2.0, 1.0],
+                [1.0, 1.0],
+                [1.0, 3.0],
+                [0.0, 3.0],
+            ],
+            ..Default::default()
+        };
+        let region = u_shape.visibility_region([0.5, 2.5]).unwrap();
+        assert!((area(&region) - (3.0 + 1.0 / 6.0)).abs() < 1e-5);
+        let region = u_shape.visibility_region([1.0, 1.0]).unwrap();
+        assert_eq!(region[0], [1.0, 1.0]);
+        assert!((area(&region) - 5.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_no_longer_than_the_navmesh_path() {
+        for method in [Method::Star, Method::SpacePartition, Method::Comb] {
+            for seed in 0..4 {
+                let mut poly = Poly {
+                    vertices: random_polygon(method, 60, seed),
+                    ..Default::default()
+                };
+                poly.triang();
+                poly.decomposition();
+                let navmesh = poly.navmesh();
+                let graph = poly.visibility_graph();
+                for idx in (0..poly.triangles.len()).step_by(7) {
+                    let center = |t: usize| {
+                        let t = &poly.triangles[t];
+                        [
+                            (t[0].x + t[1].x + t[2].x) / 3.0,
+                            (t[0].y + t[1].y + t[2].y) / 3.0,
+                        ]
+                    };
+                    let (start, goal) = (center(idx), center(poly.triangles.len() - 1));
+                    let shortest = graph.shortest_path(start, goal).unwrap();
+                    let funnel = navmesh.find_path(start, goal).unwrap();
+                    assert!(
+                        length(&shortest) <= length(&funnel) + 1e-4,
+                        "{} {}",
+                        method,
+                        seed
+                    );
+                    // every segment lies in the polygon
+                    for pair in shortest.windows(2) {
+                        let (p, q) = (pair[0], pair[1]);
+                        assert!(
+                            graph.visible([p[0] as f64, p[1] as f64], [q[0] as f64, q[1] as f64])
+                        );
+                    }
+                }
+            }
+        }
+    }
+}